@@ -1,32 +1,239 @@
 use crate::{
     DockerBuilder,
     config::{
-        compose::{ComposeConfig, Service},
+        compose::{ComposeConfig, DependencyCondition, ExposedPort, PortMapping, Service},
         health::HealthCheck,
-        volume::Volume,
+        network::{NetworkMode, ServiceNetworkConfig},
+        resources::{ByteSize, ulimits_to_docker},
+        secrets::{CONFIGS_DIR, DEFAULT_FILE_MODE, FILES_LABEL, FileObjectReference, SECRETS_DIR},
+        volume::{Volume, expand_home},
     },
-    error::DockerError,
+    error::{DeploymentFailure, DockerError},
 };
-use bollard::container::{Config, CreateContainerOptions, StartContainerOptions};
-use bollard::network::CreateNetworkOptions;
-use bollard::service::{HealthConfig, HostConfig, Mount, PortBinding};
-use futures_util::StreamExt;
-use std::collections::HashMap;
+use bollard::container::{
+    Config, CreateContainerOptions, NetworkingConfig, RemoveContainerOptions, StartContainerOptions,
+};
+use bollard::network::ConnectNetworkOptions;
+use bollard::service::{
+    ContainerStateStatusEnum, DeviceMapping, DeviceRequest, EndpointIpamConfig, EndpointSettings,
+    HealthConfig, HealthStatusEnum, HostConfig, Mount, MountBindOptionsPropagationEnum,
+    PortBinding,
+};
+use futures_util::{StreamExt, stream};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::Display;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 use tar;
 use tempfile;
 use uuid::Uuid;
 use walkdir;
 
+/// The default number of services built, pulled or started at the same time
+pub const DEFAULT_DEPLOY_CONCURRENCY: usize = 4;
+
+/// The network services are attached to when they don't specify any `networks`
+const DEFAULT_NETWORK: &str = "default";
+
+/// The default time to wait for a dependency to meet its `depends_on` condition
+pub const DEFAULT_DEPENDENCY_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Options for [`DockerBuilder::deploy_compose_with_options()`]
+#[derive(Debug, Clone)]
+pub struct ComposeDeployOptions {
+    concurrency: usize,
+    rollback_on_failure: bool,
+    dependency_timeout: Duration,
+    profiles: Vec<String>,
+    services: Vec<String>,
+}
+
+impl Default for ComposeDeployOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: DEFAULT_DEPLOY_CONCURRENCY,
+            rollback_on_failure: true,
+            dependency_timeout: DEFAULT_DEPENDENCY_TIMEOUT,
+            profiles: Vec::new(),
+            services: Vec::new(),
+        }
+    }
+}
+
+impl ComposeDeployOptions {
+    /// Create a new `ComposeDeployOptions` with the default settings
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum number of services that are built, pulled or started concurrently
+    ///
+    /// Services still wait for their dependencies regardless of this limit. A value of `1`
+    /// deploys everything sequentially. Defaults to [`DEFAULT_DEPLOY_CONCURRENCY`].
+    #[must_use]
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Whether to remove everything created by a failed deployment
+    ///
+    /// When enabled (the default), a failed deployment stops and removes every container,
+    /// network and volume it created. Disable this to keep the partial deployment around for
+    /// debugging. Either way, the returned [`DockerError::DeploymentFailed`] lists the affected
+    /// resources.
+    #[must_use]
+    pub fn rollback_on_failure(mut self, rollback: bool) -> Self {
+        self.rollback_on_failure = rollback;
+        self
+    }
+
+    /// Set how long to wait for a dependency to meet its `depends_on` condition
+    ///
+    /// This applies to the `service_healthy` and `service_completed_successfully` conditions.
+    /// Defaults to [`DEFAULT_DEPENDENCY_TIMEOUT`].
+    #[must_use]
+    pub fn dependency_timeout(mut self, timeout: Duration) -> Self {
+        self.dependency_timeout = timeout;
+        self
+    }
+
+    /// Set the active profiles
+    ///
+    /// Services with `profiles` are only deployed when one of them is active, services without
+    /// any profiles are always deployed. No profiles are active by default.
+    #[must_use]
+    pub fn profiles<I, S>(mut self, profiles: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.profiles = profiles.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Only deploy the given services, along with everything they depend on
+    ///
+    /// The services are deployed regardless of the active profiles. By default, every service
+    /// enabled by the active profiles is deployed, see [`ComposeConfig::select_services()`].
+    #[must_use]
+    pub fn services<I, S>(mut self, services: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.services = services.into_iter().map(Into::into).collect();
+        self
+    }
+}
+
+/// Runs `futures`, at most `concurrency` at a time, and returns their outputs once all are done
+///
+/// Futures are never dropped halfway, as a container could be created without being tracked
+/// for rollback yet. The first error is only returned after every future completed.
+async fn run_all<T, E>(
+    futures: Vec<impl Future<Output = Result<T, E>>>,
+    concurrency: usize,
+) -> Result<Vec<T>, E> {
+    let results: Vec<Result<T, E>> = stream::iter(futures)
+        .buffer_unordered(concurrency)
+        .collect()
+        .await;
+    results.into_iter().collect()
+}
+
+/// Resources created by an in-progress compose deployment
+#[derive(Debug, Default)]
+struct DeployedResources {
+    /// `(service name, container ID)` pairs, in creation order
+    containers: Vec<(String, String)>,
+    networks: Vec<String>,
+    volumes: Vec<String>,
+    /// Directories holding the secrets and configs mounted into the containers
+    directories: Vec<PathBuf>,
+}
+
+fn track(resources: &Mutex<DeployedResources>, f: impl FnOnce(&mut DeployedResources)) {
+    let mut resources = resources
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner);
+    f(&mut resources);
+}
+
+/// The resources affected by the cleanup of a failed compose deployment
+#[derive(Debug, Clone, Default)]
+pub struct RollbackReport {
+    /// Whether the resources were removed, or kept around as requested by
+    /// [`ComposeDeployOptions::rollback_on_failure()`]
+    pub performed: bool,
+    /// Names of the services whose containers were removed (or kept)
+    pub containers: Vec<String>,
+    /// Names of the networks that were removed (or kept)
+    pub networks: Vec<String>,
+    /// Names of the volumes that were removed (or kept)
+    pub volumes: Vec<String>,
+    /// Resources that could not be removed, with the reason
+    pub errors: Vec<String>,
+}
+
+impl RollbackReport {
+    fn kept(resources: DeployedResources) -> Self {
+        Self {
+            performed: false,
+            containers: resources
+                .containers
+                .into_iter()
+                .map(|(service_name, _)| service_name)
+                .collect(),
+            networks: resources.networks,
+            volumes: resources.volumes,
+            errors: Vec::new(),
+        }
+    }
+
+    fn record_error(&mut self, resource: &str, error: &bollard::errors::Error) {
+        log::warn!("Failed to remove {} during rollback: {}", resource, error);
+        self.errors.push(format!("{}: {}", resource, error));
+    }
+}
+
+impl Display for RollbackReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.performed {
+            write!(f, "rolled back")?;
+        } else {
+            write!(f, "kept for debugging")?;
+        }
+
+        write!(
+            f,
+            " containers [{}], networks [{}], volumes [{}]",
+            self.containers.join(", "),
+            self.networks.join(", "),
+            self.volumes.join(", ")
+        )?;
+
+        if !self.errors.is_empty() {
+            write!(f, "; failed to remove: {}", self.errors.join("; "))?;
+        }
+
+        Ok(())
+    }
+}
+
 impl DockerBuilder {
     /// Deploys a Docker Compose configuration with a custom base directory
     ///
     /// This method deploys services defined in a Docker Compose configuration, using the specified
     /// base directory for resolving relative paths. It handles:
-    /// - Creating a dedicated network for the services
+    /// - Creating the networks used by the services
     /// - Creating required volumes
     /// - Deploying services in dependency order
     /// - Making bind mount paths absolute
+    /// - Mounting secrets and configs as read-only files
+    /// - Skipping services whose profiles are not active, see [`ComposeDeployOptions::profiles()`]
     ///
     /// # Arguments
     ///
@@ -55,11 +262,13 @@ impl DockerBuilder {
     ///
     /// # Errors
     ///
-    /// Returns `DockerError` if:
+    /// Returns [`DockerError::DeploymentFailed`] if:
     /// - Network creation fails
     /// - Volume creation fails
     /// - Container creation or startup fails
     /// - Path resolution fails
+    ///
+    /// Everything created before the failure is stopped and removed again.
     pub async fn deploy_compose(
         &self,
         config: &mut ComposeConfig,
@@ -72,10 +281,12 @@ impl DockerBuilder {
     ///
     /// This method deploys services defined in a Docker Compose configuration using the current
     /// working directory for resolving relative paths. It handles:
-    /// - Creating a dedicated network for the services
+    /// - Creating the networks used by the services
     /// - Creating required volumes
     /// - Deploying services in dependency order
     /// - Making bind mount paths absolute
+    /// - Mounting secrets and configs as read-only files
+    /// - Skipping services whose profiles are not active, see [`ComposeDeployOptions::profiles()`]
     ///
     /// # Arguments
     ///
@@ -109,65 +320,592 @@ impl DockerBuilder {
     ///
     /// # Errors
     ///
-    /// Returns `DockerError` if:
+    /// Returns [`DockerError::DeploymentFailed`] if:
     /// - Network creation fails
     /// - Volume creation fails
     /// - Container creation or startup fails
     /// - Path resolution fails
+    ///
+    /// Everything created before the failure is stopped and removed again.
     pub async fn deploy_compose_with_base_dir(
         &self,
         config: &mut ComposeConfig,
         base_dir: PathBuf,
     ) -> Result<HashMap<String, String>, DockerError> {
+        self.deploy_compose_with_options(config, base_dir, ComposeDeployOptions::default())
+            .await
+    }
+
+    /// Deploys a Docker Compose configuration with custom [`ComposeDeployOptions`]
+    ///
+    /// Only the services selected by [`ComposeDeployOptions::profiles()`] and
+    /// [`ComposeDeployOptions::services()`] are deployed, the others are skipped.
+    ///
+    /// Services are deployed concurrently wherever their dependencies allow it. All images are
+    /// built or pulled up front, after which services are started level by level, see
+    /// [`ComposeConfig::resolve_service_levels()`]. The number of concurrent operations is
+    /// bounded by [`ComposeDeployOptions::concurrency()`].
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The Docker Compose configuration to deploy
+    /// * `base_dir` - Base directory for resolving relative paths
+    /// * `options` - Options controlling the deployment
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing a [`HashMap`] mapping service names to their container IDs,
+    /// or a `DockerError` if deployment fails
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use docktopus::DockerBuilder;
+    /// use docktopus::builder::compose::ComposeDeployOptions;
+    /// use docktopus::parser::ComposeParser;
+    ///
+    /// # #[tokio::main]
+    /// # async fn main() -> Result<(), docktopus::DockerError> {
+    /// let builder = DockerBuilder::new().await?;
+    /// let mut config = ComposeParser::new().parse_from_path("docker-compose.yml")?;
+    ///
+    /// let options = ComposeDeployOptions::new().concurrency(8);
+    /// let container_ids = builder
+    ///     .deploy_compose_with_options(&mut config, std::env::current_dir()?, options)
+    ///     .await?;
+    /// # Ok(()) }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns [`DockerError::ValidationError`] if a requested service is not defined.
+    ///
+    /// Returns [`DockerError::DeploymentFailed`] if any step fails, see
+    /// [`DockerBuilder::deploy_compose_with_base_dir()`]. The error names the service that failed
+    /// (if any) and which resources were rolled back, see
+    /// [`ComposeDeployOptions::rollback_on_failure()`].
+    pub async fn deploy_compose_with_options(
+        &self,
+        config: &mut ComposeConfig,
+        base_dir: PathBuf,
+        options: ComposeDeployOptions,
+    ) -> Result<HashMap<String, String>, DockerError> {
+        // Set aside the services that aren't selected, and put them back once done
+        let selected = config.select_services(&options.profiles, &options.services)?;
+        let unselected: Vec<String> = config
+            .services
+            .keys()
+            .filter(|name| !selected.contains(*name))
+            .cloned()
+            .collect();
+        let unselected: HashMap<String, Service> = unselected
+            .into_iter()
+            .filter_map(|name| config.services.remove_entry(&name))
+            .collect();
+
+        let resources = Mutex::new(DeployedResources::default());
+        let result = self
+            .deploy_compose_tracked(config, base_dir, &options, &resources)
+            .await;
+        config.services.extend(unselected);
+
+        let (service, error) = match result {
+            Ok(container_ids) => return Ok(container_ids),
+            Err(e) => e,
+        };
+
+        let resources = resources
+            .into_inner()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let rollback = if options.rollback_on_failure {
+            self.rollback(resources).await
+        } else {
+            RollbackReport::kept(resources)
+        };
+
+        log::error!("Compose deployment failed: {}", error);
+        Err(DockerError::DeploymentFailed(Box::new(DeploymentFailure {
+            service,
+            source: error,
+            rollback,
+        })))
+    }
+
+    /// Performs the actual deployment, recording everything it creates in `resources`
+    ///
+    /// On failure, the name of the service being deployed (if any) is returned alongside the error.
+    async fn deploy_compose_tracked(
+        &self,
+        config: &mut ComposeConfig,
+        base_dir: PathBuf,
+        options: &ComposeDeployOptions,
+        resources: &Mutex<DeployedResources>,
+    ) -> Result<HashMap<String, String>, (Option<String>, DockerError)> {
         // Make all bind mount paths absolute relative to the base directory
-        make_bind_paths_absolute(config, Some(base_dir.clone()))?;
+        make_bind_paths_absolute(config, Some(base_dir.clone())).map_err(|e| (None, e))?;
 
-        let network_name = format!("compose_network_{}", Uuid::new_v4());
+        // Validate each service and load its env files up front, so an invalid restart policy or a
+        // missing file fails before anything is created
+        let mut services = HashMap::with_capacity(config.services.len());
+        for (service_name, service) in &config.services {
+            let fail = |e| (Some(service_name.clone()), e);
+            service.restart_policy().map_err(fail)?;
+            let environment = service.resolved_environment(&base_dir).map_err(fail)?;
+            services.insert(
+                service_name.clone(),
+                Service {
+                    environment,
+                    env_file: None,
+                    ..service.clone()
+                },
+            );
+        }
 
-        // Create a network for the compose services
-        self.client
-            .create_network(CreateNetworkOptions {
-                name: network_name.as_str(),
-                driver: "bridge",
-                ..Default::default()
-            })
+        // Secrets and configs are mounted as files, they never end up in the environment
+        mount_file_objects(config, &mut services, &base_dir, resources)?;
+
+        // Create the networks used by the compose services
+        let networks = self
+            .create_networks(config, resources)
             .await
-            .map_err(DockerError::BollardError)?;
+            .map_err(|e| (None, e))?;
 
         // Collect all volumes from services
         config.collect_volumes();
 
-        // Create volumes defined in the compose file
-        for (volume_name, volume_type) in &config.volumes {
-            if let Volume::Named(_) = volume_type {
-                self.client
-                    .create_volume(bollard::volume::CreateVolumeOptions {
-                        name: volume_name.to_string(),
-                        ..Default::default()
-                    })
+        // Create volumes defined in the compose file, leaving existing ones untouched
+        self.create_volumes(config, &mut services, &base_dir, resources)
+            .await
+            .map_err(|e| (None, e))?;
+
+        // Build or pull every image up front, these don't depend on deployment order
+        let mut image_futures = Vec::with_capacity(config.services.len());
+        for (service_name, service) in &config.services {
+            let base_dir = base_dir.as_path();
+            image_futures.push(async move {
+                let image = self
+                    .prepare_service_image(service_name, service, base_dir)
                     .await
-                    .map_err(DockerError::BollardError)?;
-            }
+                    .map_err(|e| (Some(service_name.clone()), e))?;
+                Ok((service_name.clone(), image))
+            });
         }
+        let images: HashMap<String, String> = run_all(image_futures, options.concurrency)
+            .await?
+            .into_iter()
+            .collect();
 
         let mut container_ids = HashMap::new();
 
-        // Get service deployment order
-        let service_order = config.resolve_service_order()?;
+        // Deploy each level concurrently, a level only starts once the previous one is up
+        for level in config.resolve_service_levels().map_err(|e| (None, e))? {
+            let mut deploy_futures = Vec::with_capacity(level.len());
+            for service_name in level {
+                let (Some(service), Some(image)) =
+                    (services.get(&service_name), images.get(&service_name))
+                else {
+                    continue;
+                };
+
+                let networks = &networks;
+                let container_ids = &container_ids;
+                deploy_futures.push(async move {
+                    let result = async {
+                        self.wait_for_dependencies(
+                            &service_name,
+                            service,
+                            container_ids,
+                            options.dependency_timeout,
+                        )
+                        .await?;
+                        self.deploy_service(
+                            &service_name,
+                            service,
+                            image,
+                            networks,
+                            container_ids,
+                            resources,
+                        )
+                        .await
+                    };
+
+                    match result.await {
+                        Ok(container_id) => Ok((service_name, container_id)),
+                        Err(e) => Err((Some(service_name), e)),
+                    }
+                });
+            }
+
+            container_ids.extend(run_all(deploy_futures, options.concurrency).await?);
+        }
+
+        Ok(container_ids)
+    }
+
+    /// Creates every network referenced by the compose services
+    ///
+    /// Services without any `networks` are attached to the `default` network, which is created
+    /// with a generated name unless it is configured in the top-level `networks` section.
+    /// External networks are only checked for existence.
+    ///
+    /// # Returns
+    ///
+    /// Returns a map of compose network names to the names of the Docker networks backing them
+    async fn create_networks(
+        &self,
+        config: &ComposeConfig,
+        resources: &Mutex<DeployedResources>,
+    ) -> Result<HashMap<String, String>, DockerError> {
+        let deployment_id = Uuid::new_v4();
 
-        // Deploy services in order
-        for service_name in service_order {
-            let Some(service) = config.services.get(&service_name) else {
+        let mut used_networks = BTreeSet::new();
+        for (service_name, service) in &config.services {
+            if service.network_mode.is_some() {
+                if service.networks.as_ref().is_some_and(|n| !n.is_empty()) {
+                    return Err(DockerError::ValidationError(format!(
+                        "Service '{}' cannot combine network_mode and networks",
+                        service_name
+                    )));
+                }
                 continue;
+            }
+
+            match &service.networks {
+                Some(networks) if !networks.is_empty() => {
+                    for network in networks.names() {
+                        if network != DEFAULT_NETWORK && !config.networks.contains_key(network) {
+                            return Err(DockerError::ValidationError(format!(
+                                "Service '{}' refers to undefined network '{}'",
+                                service_name, network
+                            )));
+                        }
+                        used_networks.insert(network);
+                    }
+                }
+                _ => {
+                    used_networks.insert(DEFAULT_NETWORK);
+                }
+            }
+        }
+
+        // Invalid address pools fail the deployment before any network is created
+        for network in &used_networks {
+            if let Some(network_config) = config.networks.get(*network) {
+                network_config.validate()?;
+            }
+        }
+
+        let mut networks = HashMap::new();
+        for network in used_networks {
+            let network_config = config.networks.get(network).cloned().unwrap_or_default();
+
+            let docker_name = match &network_config.name {
+                Some(name) => name.clone(),
+                None if network_config.external => network.to_string(),
+                None if network == DEFAULT_NETWORK => {
+                    format!("compose_network_{}", deployment_id)
+                }
+                None => format!("compose_{}_{}", network, deployment_id),
             };
 
-            let container_id = self
-                .deploy_service(&service_name, service, &network_name, &base_dir)
-                .await?;
-            container_ids.insert(service_name, container_id);
+            let exists = self
+                .client
+                .inspect_network::<String>(&docker_name, None)
+                .await
+                .is_ok();
+            if network_config.external && !exists {
+                return Err(DockerError::NetworkCreationError(format!(
+                    "External network '{}' does not exist",
+                    docker_name
+                )));
+            }
+
+            if exists {
+                log::debug!("Using existing network '{}'", docker_name);
+            } else {
+                self.client
+                    .create_network(network_config.create_options(&docker_name))
+                    .await
+                    .map_err(|e| {
+                        DockerError::NetworkCreationError(format!("{}: {}", docker_name, e))
+                    })?;
+                track(resources, |r| r.networks.push(docker_name.clone()));
+            }
+
+            networks.insert(network.to_string(), docker_name);
         }
 
-        Ok(container_ids)
+        Ok(networks)
+    }
+
+    /// Creates the volumes of the compose configuration that don't exist yet
+    ///
+    /// External volumes must already exist, and are never created or removed. Services mounting a
+    /// volume with a custom `name` are updated to mount it by that name.
+    async fn create_volumes(
+        &self,
+        config: &ComposeConfig,
+        services: &mut HashMap<String, Service>,
+        base_dir: &Path,
+        resources: &Mutex<DeployedResources>,
+    ) -> Result<(), DockerError> {
+        let mut docker_names = HashMap::new();
+        for (volume_name, volume) in &config.volumes {
+            let (name, driver, driver_opts, labels, external) = match volume {
+                Volume::Config {
+                    name,
+                    driver,
+                    driver_opts,
+                    labels,
+                    external,
+                } => (
+                    name.as_str(),
+                    driver.clone(),
+                    driver_opts.clone().unwrap_or_default(),
+                    labels.clone().unwrap_or_default(),
+                    *external,
+                ),
+                _ => ("", None, HashMap::new(), HashMap::new(), false),
+            };
+            let docker_name = if name.is_empty() {
+                volume_name.clone()
+            } else {
+                name.to_string()
+            };
+
+            match self.client.inspect_volume(&docker_name).await {
+                Ok(existing) => {
+                    if driver
+                        .as_ref()
+                        .is_some_and(|driver| *driver != existing.driver)
+                    {
+                        log::warn!(
+                            "Volume '{}' already exists with driver '{}', not '{}'",
+                            docker_name,
+                            existing.driver,
+                            driver.unwrap_or_default()
+                        );
+                    }
+                    log::debug!("Using existing volume '{}'", docker_name);
+                }
+                Err(_) if external => {
+                    return Err(DockerError::VolumeCreationError(format!(
+                        "External volume '{}' does not exist",
+                        docker_name
+                    )));
+                }
+                Err(_) => {
+                    let driver_opts =
+                        resolve_bind_device(driver.as_deref(), driver_opts, base_dir)?;
+                    self.client
+                        .create_volume(bollard::volume::CreateVolumeOptions {
+                            name: docker_name.clone(),
+                            driver: driver.unwrap_or_default(),
+                            driver_opts,
+                            labels,
+                        })
+                        .await
+                        .map_err(|e| {
+                            DockerError::VolumeCreationError(format!("{}: {}", docker_name, e))
+                        })?;
+                    track(resources, |r| r.volumes.push(docker_name.clone()));
+                }
+            }
+
+            if docker_name != *volume_name {
+                docker_names.insert(volume_name.as_str(), docker_name);
+            }
+        }
+
+        for service in services.values_mut() {
+            for volume in service.volumes.iter_mut().flatten() {
+                if let Volume::Named { source, .. } = volume {
+                    if let Some(docker_name) = docker_names.get(source.as_str()) {
+                        source.clone_from(docker_name);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Waits for the dependencies of a service to meet their `depends_on` conditions
+    ///
+    /// Dependencies marked as `required: false` that are missing or fail their condition are
+    /// logged and skipped.
+    ///
+    /// # Arguments
+    ///
+    /// * `service_name` - Name of the dependent service
+    /// * `service` - The dependent service configuration
+    /// * `container_ids` - Container IDs of the services deployed so far
+    /// * `timeout` - How long to wait for each dependency
+    async fn wait_for_dependencies(
+        &self,
+        service_name: &str,
+        service: &Service,
+        container_ids: &HashMap<String, String>,
+        timeout: Duration,
+    ) -> Result<(), DockerError> {
+        let Some(depends_on) = &service.depends_on else {
+            return Ok(());
+        };
+
+        for (dependency_name, dependency) in depends_on {
+            let result = match container_ids.get(dependency_name) {
+                Some(container_id) => {
+                    self.wait_for_condition(container_id, dependency.condition, timeout)
+                        .await
+                }
+                None => Err(DockerError::ValidationError(
+                    "Dependency was not deployed".to_string(),
+                )),
+            };
+
+            if let Err(e) = result {
+                if dependency.required {
+                    return Err(DockerError::ValidationError(format!(
+                        "Dependency '{}' of service '{}' is not ready: {}",
+                        dependency_name, service_name, e
+                    )));
+                }
+
+                log::warn!(
+                    "Optional dependency '{}' of service '{}' is not ready: {}",
+                    dependency_name,
+                    service_name,
+                    e
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Polls a container until it meets `condition`, or `timeout` elapses
+    async fn wait_for_condition(
+        &self,
+        container_id: &str,
+        condition: DependencyCondition,
+        timeout: Duration,
+    ) -> Result<(), DockerError> {
+        if condition == DependencyCondition::ServiceStarted {
+            return Ok(());
+        }
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            let state = self
+                .client
+                .inspect_container(container_id, None)
+                .await?
+                .state
+                .unwrap_or_default();
+
+            let exited = matches!(
+                state.status,
+                Some(ContainerStateStatusEnum::EXITED | ContainerStateStatusEnum::DEAD)
+            );
+            match condition {
+                DependencyCondition::ServiceStarted => return Ok(()),
+                DependencyCondition::ServiceHealthy => {
+                    match state.health.and_then(|health| health.status) {
+                        Some(HealthStatusEnum::HEALTHY) => return Ok(()),
+                        Some(HealthStatusEnum::UNHEALTHY) => {
+                            return Err(DockerError::ValidationError(
+                                "Container is unhealthy".to_string(),
+                            ));
+                        }
+                        Some(HealthStatusEnum::STARTING) if !exited => {}
+                        _ if exited => {
+                            return Err(DockerError::ContainerNotRunning(container_id.to_string()));
+                        }
+                        _ => {
+                            return Err(DockerError::ValidationError(
+                                "Container has no healthcheck".to_string(),
+                            ));
+                        }
+                    }
+                }
+                DependencyCondition::ServiceCompletedSuccessfully => {
+                    if exited {
+                        return match state.exit_code {
+                            Some(0) => Ok(()),
+                            code => Err(DockerError::ValidationError(format!(
+                                "Container exited with code {}",
+                                code.unwrap_or(-1)
+                            ))),
+                        };
+                    }
+                }
+            }
+
+            if Instant::now() >= deadline {
+                return Err(DockerError::ValidationError(format!(
+                    "Timed out after {:?} waiting for condition {:?}",
+                    timeout, condition
+                )));
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+    }
+
+    /// Stops and removes everything recorded in `resources`
+    ///
+    /// Containers are removed first, followed by networks and volumes. Failures are logged and
+    /// collected in the returned [`RollbackReport`] rather than aborting the rollback.
+    async fn rollback(&self, resources: DeployedResources) -> RollbackReport {
+        let mut report = RollbackReport {
+            performed: true,
+            ..Default::default()
+        };
+
+        for (service_name, container_id) in resources.containers.into_iter().rev() {
+            match self
+                .client
+                .remove_container(
+                    &container_id,
+                    Some(RemoveContainerOptions {
+                        force: true,
+                        ..Default::default()
+                    }),
+                )
+                .await
+            {
+                Ok(()) => report.containers.push(service_name),
+                Err(e) => report.record_error(&format!("container '{}'", service_name), &e),
+            }
+        }
+
+        for network_name in resources.networks {
+            match self.client.remove_network(&network_name).await {
+                Ok(()) => report.networks.push(network_name),
+                Err(e) => report.record_error(&format!("network '{}'", network_name), &e),
+            }
+        }
+
+        for volume_name in resources.volumes {
+            match self.client.remove_volume(&volume_name, None).await {
+                Ok(()) => report.volumes.push(volume_name),
+                Err(e) => report.record_error(&format!("volume '{}'", volume_name), &e),
+            }
+        }
+
+        for directory in resources.directories {
+            if let Err(e) = std::fs::remove_dir_all(&directory) {
+                log::warn!(
+                    "Failed to remove {} during rollback: {}",
+                    directory.display(),
+                    e
+                );
+                report
+                    .errors
+                    .push(format!("directory {}: {}", directory.display(), e));
+            }
+        }
+
+        report
     }
 
     /// Creates a Docker [`HealthConfig`] from a [`HealthCheck`] configuration
@@ -186,13 +924,7 @@ impl DockerBuilder {
     #[allow(clippy::cast_possible_truncation)]
     fn create_health_config(health: &HealthCheck) -> HealthConfig {
         HealthConfig {
-            test: Some(vec![
-                "CMD-SHELL".to_string(),
-                format!(
-                    "curl -X {} {} -s -f -o /dev/null -w '%{{http_code}}' | grep -q {}",
-                    health.method, health.endpoint, health.expected_status
-                ),
-            ]),
+            test: Some(health.test_command()),
             interval: Some(health.interval.as_nanos() as i64),
             timeout: Some(health.timeout.as_nanos() as i64),
             retries: Some(i64::from(health.retries)),
@@ -201,29 +933,26 @@ impl DockerBuilder {
         }
     }
 
-    /// Deploys a single service from a Docker Compose configuration
+    /// Builds or pulls the image for a single service
     ///
-    /// This method deploys a single service defined in a Docker Compose configuration. It handles:
+    /// This method handles:
     /// - Building the image if a build configuration is provided
     /// - Pulling the image if it doesn't exist
-    /// - Creating a container with the specified configuration
-    /// - Starting the container
     ///
     /// # Arguments
     ///
-    /// * `service_name` - Name of the service to deploy
-    /// * `service` - The service configuration to deploy
-    /// * `network_name` - Name of the Docker network to connect the container to
+    /// * `service_name` - Name of the service to prepare
+    /// * `service` - The service configuration
     /// * `base_dir` - Base directory for resolving relative paths
     ///
     /// # Returns
     ///
-    /// Returns a `Result` containing the container ID of the deployed service or a `DockerError` if deployment fails
-    async fn deploy_service(
+    /// Returns a `Result` containing the image to use for the service or a `DockerError` if
+    /// building or pulling fails
+    async fn prepare_service_image(
         &self,
         service_name: &str,
         service: &Service,
-        network_name: &str,
         base_dir: &Path,
     ) -> Result<String, DockerError> {
         let image = if let Some(build_config) = &service.build {
@@ -336,18 +1065,103 @@ impl DockerBuilder {
             }
         }
 
+        Ok(image)
+    }
+
+    /// Deploys a single service from a Docker Compose configuration
+    ///
+    /// This method deploys a single service defined in a Docker Compose configuration. It handles:
+    /// - Creating a container with the specified configuration
+    /// - Starting the container
+    ///
+    /// The service image is expected to exist already, see [`Self::prepare_service_image()`].
+    ///
+    /// # Arguments
+    ///
+    /// * `service_name` - Name of the service to deploy
+    /// * `service` - The service configuration to deploy
+    /// * `image` - The image to create the container from
+    /// * `networks` - Compose network names mapped to their Docker networks, see [`Self::create_networks()`]
+    /// * `container_ids` - Container IDs of the services deployed so far
+    /// * `resources` - Tracker the created container is recorded in, for rollbacks
+    ///
+    /// # Returns
+    ///
+    /// Returns a `Result` containing the container ID of the deployed service or a `DockerError` if deployment fails
+    async fn deploy_service(
+        &self,
+        service_name: &str,
+        service: &Service,
+        image: &str,
+        networks: &HashMap<String, String>,
+        container_ids: &HashMap<String, String>,
+        resources: &Mutex<DeployedResources>,
+    ) -> Result<String, DockerError> {
+        // Resolve the networks to attach to, the one with the highest priority comes first.
+        // Services with a `network_mode` are not attached to any compose networks.
+        let mut endpoints = Vec::new();
+        let network_mode = match &service.network_mode {
+            Some(NetworkMode::Service(name)) => {
+                let container_id = container_ids.get(name).ok_or_else(|| {
+                    DockerError::ValidationError(format!(
+                        "Service '{}' shares the network of service '{}', which has not been deployed",
+                        service_name, name
+                    ))
+                })?;
+                format!("container:{}", container_id)
+            }
+            Some(network_mode) => network_mode.to_string(),
+            None => {
+                let service_networks = match &service.networks {
+                    Some(service_networks) if !service_networks.is_empty() => {
+                        service_networks.clone()
+                    }
+                    _ => std::iter::once(DEFAULT_NETWORK).collect(),
+                };
+                for (network, network_config) in service_networks.by_priority() {
+                    let docker_name = networks.get(network).ok_or_else(|| {
+                        DockerError::ValidationError(format!(
+                            "Service '{}' refers to undefined network '{}'",
+                            service_name, network
+                        ))
+                    })?;
+                    endpoints.push((
+                        docker_name.clone(),
+                        create_endpoint_settings(service_name, network_config),
+                    ));
+                }
+                endpoints[0].0.clone()
+            }
+        };
+        let mut endpoints = endpoints.into_iter();
+
         // Create container configuration
         let mut container_config = Config {
-            image: Some(image),
+            image: Some(image.to_string()),
             cmd: service.command.clone(),
             env: Self::prepare_environment_variables(service),
             labels: service.labels.clone(),
+            exposed_ports: create_exposed_ports(service),
+            entrypoint: service.entrypoint.clone(),
+            user: service.user.clone(),
+            working_dir: service.working_dir.clone(),
+            hostname: service.hostname.clone(),
+            stop_signal: service.stop_signal.clone(),
+            stop_timeout: service
+                .stop_grace_period
+                .map(|period| i64::try_from(period.as_secs()).unwrap_or(i64::MAX)),
             ..Default::default()
         };
 
         // Configure host settings
-        let host_config = create_host_config(service, network_name);
+        let mut host_config = create_host_config(service, &network_mode)?;
+        host_config.restart_policy = service.restart_policy()?;
         container_config.host_config = Some(host_config);
+        if let Some(primary_endpoint) = endpoints.next() {
+            container_config.networking_config = Some(NetworkingConfig {
+                endpoints_config: HashMap::from([primary_endpoint]),
+            });
+        }
 
         // Add health check if specified
         if let Some(health) = &service.healthcheck {
@@ -365,6 +1179,23 @@ impl DockerBuilder {
                 container_config,
             )
             .await?;
+        track(resources, |r| {
+            r.containers
+                .push((service_name.to_string(), container.id.clone()));
+        });
+
+        // Containers can only be created with a single network, connect the rest before starting
+        for (network, endpoint_config) in endpoints {
+            self.client
+                .connect_network(
+                    &network,
+                    ConnectNetworkOptions {
+                        container: container.id.as_str(),
+                        endpoint_config,
+                    },
+                )
+                .await?;
+        }
 
         self.client
             .start_container(&container.id, None::<StartContainerOptions<String>>)
@@ -412,6 +1243,178 @@ impl DockerBuilder {
     }
 }
 
+/// Writes the secrets and configs used by each service to a new directory, and bind-mounts them
+///
+/// Every reference gets its own copy of the file, so its `uid`, `gid` and `mode` can be applied.
+/// The directory is only readable by the current user. It is removed when the deployment is
+/// rolled back, or along with the service's container, which points to it with [`FILES_LABEL`].
+fn mount_file_objects(
+    config: &ComposeConfig,
+    services: &mut HashMap<String, Service>,
+    base_dir: &Path,
+    resources: &Mutex<DeployedResources>,
+) -> Result<(), (Option<String>, DockerError)> {
+    for (service_name, service) in services.iter_mut() {
+        let fail = |e: DockerError| (Some(service_name.clone()), e);
+        let mut directory: Option<PathBuf> = None;
+        let kinds = [
+            (
+                "secret",
+                &config.secrets,
+                service.secrets.clone(),
+                SECRETS_DIR,
+            ),
+            (
+                "config",
+                &config.configs,
+                service.configs.clone(),
+                CONFIGS_DIR,
+            ),
+        ];
+
+        for (kind, definitions, references, default_dir) in kinds {
+            for (index, reference) in references.iter().flatten().enumerate() {
+                let definition = definitions.get(&reference.source).ok_or_else(|| {
+                    fail(DockerError::ValidationError(format!(
+                        "Service uses undefined {} '{}'",
+                        kind, reference.source
+                    )))
+                })?;
+                let content = definition.load(&reference.source, base_dir).map_err(fail)?;
+
+                let directory = match &directory {
+                    Some(directory) => directory.clone(),
+                    None => {
+                        let created = tempfile::Builder::new()
+                            .prefix(&format!("docktopus-{}-", service_name))
+                            .tempdir()
+                            .map_err(|e| fail(e.into()))?
+                            .into_path();
+                        track(resources, |r| r.directories.push(created.clone()));
+                        service.labels.get_or_insert_with(HashMap::new).insert(
+                            FILES_LABEL.to_string(),
+                            created.to_string_lossy().into_owned(),
+                        );
+                        directory.insert(created).clone()
+                    }
+                };
+
+                let path = directory.join(format!("{}-{}-{}", service_name, kind, index));
+                write_file_object(&path, &content, reference).map_err(fail)?;
+
+                service.volumes.get_or_insert_with(Vec::new).push(
+                    Volume::bind(
+                        path.to_string_lossy().into_owned(),
+                        reference.target_path(default_dir),
+                    )
+                    .read_only(true),
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Makes the `device` of a local volume bound to a host directory absolute
+///
+/// The local driver accepts `type: none, o: bind, device: PATH` to back a volume with a host
+/// directory, which has to exist when the volume is mounted.
+fn resolve_bind_device(
+    driver: Option<&str>,
+    mut driver_opts: HashMap<String, String>,
+    base_dir: &Path,
+) -> Result<HashMap<String, String>, DockerError> {
+    let is_bind = driver_opts
+        .get("o")
+        .is_some_and(|o| o.split(',').any(|option| option == "bind"));
+    if !matches!(driver, None | Some("local")) || !is_bind {
+        return Ok(driver_opts);
+    }
+
+    if let Some(device) = driver_opts.get_mut("device") {
+        let path = normalize_path(base_dir, device);
+        if !path.is_dir() {
+            return Err(DockerError::VolumeCreationError(format!(
+                "Bind device {} is not a directory",
+                path.display()
+            )));
+        }
+        *device = path.to_string_lossy().into_owned();
+    }
+
+    Ok(driver_opts)
+}
+
+/// The `SOURCE:TARGET:OPTIONS` specification of a bind mount
+fn bind_spec(volume: &Volume) -> Option<String> {
+    let Volume::Bind {
+        source,
+        target,
+        read_only,
+        options,
+    } = volume
+    else {
+        return None;
+    };
+
+    let mut flags = vec![if *read_only { "ro" } else { "rw" }];
+    if let Some(selinux) = options.selinux {
+        flags.push(selinux.flag());
+    }
+    let propagation = options
+        .propagation
+        .map(|propagation| MountBindOptionsPropagationEnum::from(propagation).to_string());
+    flags.extend(propagation.as_deref());
+
+    Some(format!("{}:{}:{}", source, target, flags.join(",")))
+}
+
+/// Writes a secret or config, applying the ownership and permissions of the reference
+fn write_file_object(
+    path: &Path,
+    content: &[u8],
+    reference: &FileObjectReference,
+) -> Result<(), DockerError> {
+    std::fs::write(path, content)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = reference.mode.unwrap_or(DEFAULT_FILE_MODE);
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+
+        let parse_id = |id: &Option<String>| {
+            id.as_deref()
+                .map(|id| {
+                    id.parse::<u32>().map_err(|_| {
+                        DockerError::ValidationError(format!(
+                            "Invalid uid or gid '{}' for '{}'",
+                            id, reference.source
+                        ))
+                    })
+                })
+                .transpose()
+        };
+        let uid = parse_id(&reference.uid)?;
+        let gid = parse_id(&reference.gid)?;
+        if uid.is_some() || gid.is_some() {
+            // Changing the owner requires root, the container may still be able to read the file
+            match std::os::unix::fs::chown(path, uid, gid) {
+                Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => log::warn!(
+                    "Not permitted to change the owner of '{}', keeping the current user: {}",
+                    reference.source,
+                    e
+                ),
+                result => result?,
+            }
+        }
+    }
+
+    Ok(())
+}
+
 fn make_bind_paths_absolute(
     config: &mut ComposeConfig,
     base_dir: Option<PathBuf>,
@@ -448,16 +1451,15 @@ fn make_bind_paths_absolute(
     Ok(())
 }
 
+/// Resolves `path` against `base`, removing any `.` and `..` components
+///
+/// `..` removes the previous component of the joined path, so `../data` resolves to a sibling of
+/// `base`. It never goes above the root.
 fn normalize_path(base: &Path, path: &str) -> PathBuf {
-    let path = PathBuf::from(path);
-    if path.is_absolute() {
-        return path;
-    }
+    let path = base.join(expand_home(path));
 
-    // Remove any ./ or ../ from the path
-    let normalized = path.components().fold(PathBuf::new(), |mut acc, comp| {
+    path.components().fold(PathBuf::new(), |mut acc, comp| {
         match comp {
-            std::path::Component::Normal(x) => acc.push(x),
             std::path::Component::ParentDir => {
                 acc.pop();
             }
@@ -465,47 +1467,196 @@ fn normalize_path(base: &Path, path: &str) -> PathBuf {
             _ => acc.push(comp.as_os_str()),
         }
         acc
+    })
+}
+
+// Docker represents exposed ports as a map to empty objects
+#[allow(clippy::zero_sized_map_values)]
+fn create_exposed_ports(service: &Service) -> Option<HashMap<String, HashMap<(), ()>>> {
+    let published = service
+        .ports
+        .iter()
+        .flatten()
+        .flat_map(PortMapping::container_ports);
+    let exposed = service
+        .expose
+        .iter()
+        .flatten()
+        .flat_map(ExposedPort::container_ports);
+
+    let exposed_ports: HashMap<_, _> = published
+        .chain(exposed)
+        .map(|port| (port, HashMap::new()))
+        .collect();
+    (!exposed_ports.is_empty()).then_some(exposed_ports)
+}
+
+fn create_endpoint_settings(
+    service_name: &str,
+    network_config: &ServiceNetworkConfig,
+) -> EndpointSettings {
+    // Services are always reachable by their name
+    let mut aliases = vec![service_name.to_string()];
+    aliases.extend(network_config.aliases.iter().flatten().cloned());
+
+    let ipam_config = (network_config.ipv4_address.is_some()
+        || network_config.ipv6_address.is_some())
+    .then(|| EndpointIpamConfig {
+        ipv4_address: network_config.ipv4_address.clone(),
+        ipv6_address: network_config.ipv6_address.clone(),
+        ..Default::default()
     });
 
-    // Join with base path and normalize
-    base.join(normalized)
+    EndpointSettings {
+        aliases: Some(aliases),
+        ipam_config,
+        ..Default::default()
+    }
+}
+
+/// Applies the compose resource keys of a service on top of its `requirements`
+///
+/// `deploy.resources` takes precedence over the equivalent top-level keys such as `mem_limit`.
+/// CPU reservations are only enforced by swarm and are ignored.
+#[allow(clippy::cast_possible_truncation)]
+fn apply_resource_limits(service: &Service, host_config: &mut HostConfig) {
+    let resources = service
+        .deploy
+        .as_ref()
+        .and_then(|deploy| deploy.resources.as_ref());
+    let limits = resources.and_then(|resources| resources.limits.as_ref());
+    let reservations = resources.and_then(|resources| resources.reservations.as_ref());
+
+    if let Some(memory) = limits.and_then(|l| l.memory).or(service.mem_limit) {
+        host_config.memory = Some(memory.as_i64());
+    }
+    if let Some(memory) = reservations
+        .and_then(|r| r.memory)
+        .or(service.mem_reservation)
+    {
+        host_config.memory_reservation = Some(memory.as_i64());
+    }
+    if let Some(swap) = service.memswap_limit {
+        host_config.memory_swap = Some(swap.as_i64());
+    }
+    if let Some(cpus) = limits.and_then(|l| l.cpus).or(service.cpus) {
+        host_config.nano_cpus = Some((cpus * 1e9) as i64);
+    }
+    if let Some(pids) = limits.and_then(|l| l.pids).or(service.pids_limit) {
+        host_config.pids_limit = Some(pids);
+    }
+    if let Some(shares) = service.cpu_shares {
+        host_config.cpu_shares = Some(shares);
+    }
+    if let Some(cpuset) = &service.cpuset {
+        host_config.cpuset_cpus = Some(cpuset.clone());
+    }
+    if let Some(devices) = reservations.and_then(|r| r.devices.as_ref()) {
+        host_config.device_requests =
+            Some(devices.iter().cloned().map(DeviceRequest::from).collect());
+    }
 }
 
-fn create_host_config(service: &Service, network_name: &str) -> HostConfig {
+fn create_host_config(service: &Service, network_mode: &str) -> Result<HostConfig, DockerError> {
     let mut host_config = HostConfig {
-        network_mode: Some(network_name.to_string()),
+        network_mode: Some(network_mode.to_string()),
         ..Default::default()
     };
 
     // Add resource limits if specified
     if let Some(requirements) = &service.requirements {
         host_config = requirements.to_host_config();
-        host_config.network_mode = Some(network_name.to_string());
+        host_config.network_mode = Some(network_mode.to_string());
     }
+    apply_resource_limits(service, &mut host_config);
 
-    // Configure mounts if volumes are specified
+    // Configure runtime options
+    host_config.ulimits = service.ulimits.as_ref().map(ulimits_to_docker);
+    host_config.shm_size = service.shm_size.map(ByteSize::as_i64);
+    host_config.sysctls.clone_from(&service.sysctls);
+    host_config.cap_add.clone_from(&service.cap_add);
+    host_config.cap_drop.clone_from(&service.cap_drop);
+    host_config.privileged = service.privileged;
+    host_config.readonly_rootfs = service.read_only;
+    host_config.security_opt.clone_from(&service.security_opt);
+    host_config.init = service.init;
+    host_config.extra_hosts.clone_from(&service.extra_hosts);
+    host_config.devices = service
+        .devices
+        .as_ref()
+        .map(|devices| devices.iter().cloned().map(DeviceMapping::from).collect());
+    host_config.tmpfs = service.tmpfs.as_ref().map(|tmpfs| {
+        tmpfs
+            .iter()
+            .map(|mount| match mount.split_once(':') {
+                Some((path, options)) => (path.to_string(), options.to_string()),
+                None => (mount.clone(), String::new()),
+            })
+            .collect()
+    });
+
+    // Configure legacy links, containers are named after their services
+    let links: Vec<String> = service
+        .links
+        .iter()
+        .flatten()
+        .map(|link| match link.split_once(':') {
+            Some(_) => link.clone(),
+            None => format!("{}:{}", link, link),
+        })
+        .chain(service.external_links.iter().flatten().cloned())
+        .collect();
+    if !links.is_empty() {
+        host_config.links = Some(links);
+    }
+
+    // Configure mounts if volumes are specified, relabeled binds can only be passed as binds
     if let Some(volumes) = &service.volumes {
-        let mounts: Vec<Mount> = volumes.iter().cloned().map(Mount::from).collect();
-        host_config.mounts = Some(mounts);
+        let (binds, mounts): (Vec<&Volume>, Vec<&Volume>) = volumes.iter().partition(
+            |volume| matches!(volume, Volume::Bind { options, .. } if options.selinux.is_some()),
+        );
+        host_config.mounts = Some(
+            mounts
+                .into_iter()
+                .cloned()
+                .map(Mount::try_from)
+                .collect::<Result<_, _>>()?,
+        );
+        if !binds.is_empty() {
+            host_config.binds = Some(binds.into_iter().filter_map(bind_spec).collect());
+        }
     }
 
-    // Configure port bindings
+    // Configure port bindings, a container port may be published on several host ports
     if let Some(ports) = &service.ports {
-        let mut port_bindings = HashMap::new();
-        for port_mapping in ports {
-            let parts: Vec<&str> = port_mapping.split(':').collect();
-            if parts.len() == 2 {
-                port_bindings.insert(
-                    format!("{}/tcp", parts[1]),
-                    Some(vec![PortBinding {
-                        host_ip: Some("0.0.0.0".to_string()),
-                        host_port: Some(parts[0].to_string()),
-                    }]),
-                );
-            }
+        let mut port_bindings: HashMap<String, Option<Vec<PortBinding>>> = HashMap::new();
+        for (container_port, binding) in ports.iter().flat_map(PortMapping::port_bindings) {
+            port_bindings
+                .entry(container_port)
+                .or_default()
+                .get_or_insert_with(Vec::new)
+                .push(binding);
         }
         host_config.port_bindings = Some(port_bindings);
     }
 
-    host_config
+    Ok(host_config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_path() {
+        let base = Path::new("/srv/app");
+        assert_eq!(normalize_path(base, "./data"), Path::new("/srv/app/data"));
+        assert_eq!(normalize_path(base, "../data"), Path::new("/srv/data"));
+        assert_eq!(
+            normalize_path(base, "data/../config/."),
+            Path::new("/srv/app/config")
+        );
+        assert_eq!(normalize_path(base, "../../../data"), Path::new("/data"));
+        assert_eq!(normalize_path(base, "/mnt/../data"), Path::new("/data"));
+    }
 }
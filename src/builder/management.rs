@@ -1,14 +1,178 @@
 use crate::DockerBuilder;
+use crate::config::NetworkConfig;
 use crate::error::DockerError;
-use bollard::container::LogsOptions;
+use bollard::container::{
+    Config, CreateContainerOptions, DownloadFromContainerOptions, ListContainersOptions,
+    LogsOptions, RemoveContainerOptions, UploadToContainerOptions,
+};
 use bollard::exec::{CreateExecOptions, StartExecOptions};
-use bollard::network::CreateNetworkOptions;
+use bollard::network::{
+    ConnectNetworkOptions, DisconnectNetworkOptions, InspectNetworkOptions, ListNetworksOptions,
+};
+use bollard::service::{EndpointIpamConfig, EndpointSettings, HostConfig, Mount, MountTypeEnum};
 use bollard::volume::{CreateVolumeOptions, ListVolumesOptions};
-use futures_util::{StreamExt, TryStreamExt};
+use futures_util::{Stream, StreamExt, TryStreamExt, stream};
+use ipnet::IpNet;
 use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use tokio::time::sleep;
 
+/// Image of the short-lived containers used to access the contents of volumes
+pub const VOLUME_HELPER_IMAGE: &str = "alpine:latest";
+
+/// Size of the chunks read from a restored archive
+const ARCHIVE_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Filters for listing volumes and networks
+///
+/// All filters must match. Multiple labels must all be present, while multiple names match any of
+/// them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResourceFilters {
+    /// Labels, either `key` or `key=value`
+    pub labels: Vec<String>,
+    /// Names, or parts of names
+    pub names: Vec<String>,
+    /// Only resources that are (or aren't) unused by any container
+    pub dangling: Option<bool>,
+}
+
+impl ResourceFilters {
+    /// Only resources that have the label `key`, with any value
+    #[must_use]
+    pub fn label(mut self, key: impl Into<String>) -> Self {
+        self.labels.push(key.into());
+        self
+    }
+
+    /// Only resources that have the label `key` set to `value`
+    #[must_use]
+    pub fn label_value(mut self, key: impl AsRef<str>, value: impl AsRef<str>) -> Self {
+        self.labels
+            .push(format!("{}={}", key.as_ref(), value.as_ref()));
+        self
+    }
+
+    /// Only resources whose name contains `name`
+    #[must_use]
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.names.push(name.into());
+        self
+    }
+
+    /// Only resources that are unused (`true`) or used (`false`) by containers
+    #[must_use]
+    pub fn dangling(mut self, dangling: bool) -> Self {
+        self.dangling = Some(dangling);
+        self
+    }
+
+    fn to_docker_filters(&self) -> HashMap<String, Vec<String>> {
+        let mut filters = HashMap::new();
+        if !self.labels.is_empty() {
+            filters.insert("label".to_string(), self.labels.clone());
+        }
+        if !self.names.is_empty() {
+            filters.insert("name".to_string(), self.names.clone());
+        }
+        if let Some(dangling) = self.dangling {
+            filters.insert("dangling".to_string(), vec![dangling.to_string()]);
+        }
+        filters
+    }
+}
+
+/// Details of a Docker volume
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct VolumeInfo {
+    pub name: String,
+    pub driver: String,
+    /// Path of the volume's data on the host
+    pub mountpoint: String,
+    pub labels: HashMap<String, String>,
+    /// Either `local` or `global`
+    pub scope: Option<String>,
+    /// Disk usage in bytes, if the driver reports it
+    pub size: Option<u64>,
+    /// Names of the containers (running or not) that mount the volume
+    pub in_use_by: Vec<String>,
+}
+
+/// Details of a Docker network
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NetworkInfo {
+    pub id: String,
+    pub name: String,
+    pub driver: Option<String>,
+    pub scope: Option<String>,
+    pub labels: HashMap<String, String>,
+    /// Whether external access to the network is restricted
+    pub internal: bool,
+    pub subnets: Vec<NetworkSubnet>,
+    /// The containers connected to the network
+    pub containers: Vec<NetworkContainerInfo>,
+}
+
+/// An address pool of a [`NetworkInfo`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NetworkSubnet {
+    pub subnet: Option<String>,
+    pub gateway: Option<String>,
+}
+
+/// A container connected to a [`NetworkInfo`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NetworkContainerInfo {
+    pub id: String,
+    pub name: Option<String>,
+    /// IPv4 address of the container, with the prefix length of the network
+    pub ipv4_address: Option<String>,
+    /// IPv6 address of the container, with the prefix length of the network
+    pub ipv6_address: Option<String>,
+}
+
+impl From<bollard::models::Network> for NetworkInfo {
+    fn from(network: bollard::models::Network) -> Self {
+        let non_empty = |value: Option<String>| value.filter(|value| !value.is_empty());
+
+        let mut containers: Vec<NetworkContainerInfo> = network
+            .containers
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(id, container)| NetworkContainerInfo {
+                id,
+                name: container.name,
+                ipv4_address: non_empty(container.ipv4_address),
+                ipv6_address: non_empty(container.ipv6_address),
+            })
+            .collect();
+        containers.sort_by(|a, b| a.name.cmp(&b.name));
+
+        NetworkInfo {
+            id: network.id.unwrap_or_default(),
+            name: network.name.unwrap_or_default(),
+            driver: network.driver,
+            scope: network.scope,
+            labels: network.labels.unwrap_or_default(),
+            internal: network.internal.unwrap_or_default(),
+            subnets: network
+                .ipam
+                .and_then(|ipam| ipam.config)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|config| NetworkSubnet {
+                    subnet: config.subnet,
+                    gateway: config.gateway,
+                })
+                .collect(),
+            containers,
+        }
+    }
+}
+
 impl DockerBuilder {
     /// Creates a network with extra creation settings
     ///
@@ -39,25 +203,19 @@ impl DockerBuilder {
         initial_delay: Duration,
         labels: Option<HashMap<String, String>>,
     ) -> Result<(), DockerError> {
+        let config = NetworkConfig {
+            labels,
+            ..Default::default()
+        };
         let mut delay = initial_delay;
         let mut attempts = 0;
 
         while attempts < max_retries {
-            let result = self
-                .client()
-                .create_network(CreateNetworkOptions {
-                    name: name.to_string(),
-                    driver: "bridge".to_string(),
-                    labels: labels.clone().unwrap_or_default(),
-                    ..Default::default()
-                })
-                .await;
-
-            match result {
+            match self.create_network_with_config(name, &config).await {
                 Ok(_) => return Ok(()),
                 Err(e) => {
                     if attempts == max_retries - 1 {
-                        return Err(DockerError::BollardError(e));
+                        return Err(e);
                     }
                     attempts += 1;
                     sleep(delay).await;
@@ -69,6 +227,189 @@ impl DockerBuilder {
         Ok(())
     }
 
+    /// Creates a network with the driver, address pools and options of `config`
+    ///
+    /// The `name` and `external` fields of `config` are ignored, the network is always created as
+    /// `name`. The bridge driver is used when `config` has no driver.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the network to create
+    /// * `config` - Settings of the network
+    ///
+    /// # Returns
+    ///
+    /// The ID of the created network
+    ///
+    /// # Errors
+    ///
+    /// * `DockerError::InvalidIpamConfig` if the address pools are invalid, see
+    ///   [`NetworkConfig::validate()`]
+    /// * `DockerError::BollardError` if the network can't be created
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use docktopus::DockerBuilder;
+    /// use docktopus::config::{NetworkConfig, NetworkIpam, NetworkIpamPool};
+    ///
+    /// # async fn example() -> Result<(), docktopus::DockerError> {
+    /// let builder = DockerBuilder::new().await?;
+    /// let config = NetworkConfig {
+    ///     ipam: Some(NetworkIpam {
+    ///         config: Some(vec![NetworkIpamPool {
+    ///             subnet: Some("172.30.0.0/16".to_string()),
+    ///             gateway: Some("172.30.0.1".to_string()),
+    ///             ..Default::default()
+    ///         }]),
+    ///         ..Default::default()
+    ///     }),
+    ///     attachable: true,
+    ///     ..Default::default()
+    /// };
+    /// builder.create_network_with_config("p2p", &config).await?;
+    /// # Ok(()) }
+    /// ```
+    pub async fn create_network_with_config(
+        &self,
+        name: &str,
+        config: &NetworkConfig,
+    ) -> Result<String, DockerError> {
+        config.validate()?;
+
+        let response = self
+            .client()
+            .create_network(config.create_options(name))
+            .await
+            .map_err(DockerError::BollardError)?;
+
+        Ok(response.id)
+    }
+
+    /// Connects a container to a network
+    ///
+    /// # Arguments
+    ///
+    /// * `network` - Name or ID of the network
+    /// * `container` - Name or ID of the container
+    /// * `aliases` - Additional hostnames of the container on the network
+    /// * `ipv4_address` - Static address of the container, which must be in one of the network's
+    ///   subnets
+    ///
+    /// # Errors
+    ///
+    /// * `DockerError::InvalidIpamConfig` if `ipv4_address` is invalid or outside of the network's
+    ///   subnets
+    /// * `DockerError::BollardError` if the network doesn't exist or the container can't be
+    ///   connected
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use docktopus::DockerBuilder;
+    ///
+    /// # async fn example() -> Result<(), docktopus::DockerError> {
+    /// let builder = DockerBuilder::new().await?;
+    /// builder
+    ///     .connect_container("p2p", "bootnode", &["boot"], Some("172.30.0.10"))
+    ///     .await?;
+    /// # Ok(()) }
+    /// ```
+    pub async fn connect_container(
+        &self,
+        network: &str,
+        container: &str,
+        aliases: &[&str],
+        ipv4_address: Option<&str>,
+    ) -> Result<(), DockerError> {
+        let ipam_config = match ipv4_address {
+            Some(address) => {
+                self.validate_network_address(network, address).await?;
+                Some(EndpointIpamConfig {
+                    ipv4_address: Some(address.to_string()),
+                    ..Default::default()
+                })
+            }
+            None => None,
+        };
+
+        self.client()
+            .connect_network(
+                network,
+                ConnectNetworkOptions {
+                    container,
+                    endpoint_config: EndpointSettings {
+                        aliases: (!aliases.is_empty())
+                            .then(|| aliases.iter().map(ToString::to_string).collect()),
+                        ipam_config,
+                        ..Default::default()
+                    },
+                },
+            )
+            .await
+            .map_err(DockerError::BollardError)
+    }
+
+    /// Disconnects a container from a network
+    ///
+    /// # Arguments
+    ///
+    /// * `network` - Name or ID of the network
+    /// * `container` - Name or ID of the container
+    /// * `force` - Disconnect the container even if it isn't running
+    ///
+    /// # Errors
+    ///
+    /// Will return a `DockerError::BollardError` if the container isn't connected to the network
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use docktopus::DockerBuilder;
+    ///
+    /// # async fn example() -> Result<(), docktopus::DockerError> {
+    /// let builder = DockerBuilder::new().await?;
+    /// builder.disconnect_container("p2p", "bootnode", false).await?;
+    /// # Ok(()) }
+    /// ```
+    pub async fn disconnect_container(
+        &self,
+        network: &str,
+        container: &str,
+        force: bool,
+    ) -> Result<(), DockerError> {
+        self.client()
+            .disconnect_network(network, DisconnectNetworkOptions { container, force })
+            .await
+            .map_err(DockerError::BollardError)
+    }
+
+    /// Checks that `address` is an IPv4 address in one of the subnets of `network`
+    async fn validate_network_address(
+        &self,
+        network: &str,
+        address: &str,
+    ) -> Result<(), DockerError> {
+        let ip: Ipv4Addr = address.parse().map_err(|_| {
+            DockerError::InvalidIpamConfig(format!("Invalid IPv4 address '{}'", address))
+        })?;
+
+        let info = self.network_info(network).await?;
+        let in_subnet = info
+            .subnets
+            .iter()
+            .filter_map(|subnet| subnet.subnet.as_deref()?.parse::<IpNet>().ok())
+            .any(|subnet| subnet.contains(&IpAddr::V4(ip)));
+        if !in_subnet {
+            return Err(DockerError::InvalidIpamConfig(format!(
+                "{} is not in a subnet of network '{}'",
+                address, network
+            )));
+        }
+
+        Ok(())
+    }
+
     /// Removes a Docker network with the specified name
     ///
     /// This method attempts to remove a Docker network by its name. It will fail if the network
@@ -306,6 +647,504 @@ impl DockerBuilder {
             .collect())
     }
 
+    /// Writes the contents of a volume to `writer`, as a tar archive
+    ///
+    /// The archive is streamed from a helper container that mounts the volume read-only, so the
+    /// volume can stay in use by other containers. Paths in the archive are relative to the root
+    /// of the volume.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the volume to back up
+    /// * `writer` - Destination of the archive
+    ///
+    /// # Errors
+    ///
+    /// * The volume doesn't exist, or the helper container can't be created
+    /// * Writing to `writer` fails
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use docktopus::DockerBuilder;
+    ///
+    /// # async fn example() -> Result<(), docktopus::DockerError> {
+    /// let builder = DockerBuilder::new().await?;
+    /// let mut file = tokio::fs::File::create("chain-data.tar").await?;
+    /// builder.backup_volume("chain_data", &mut file).await?;
+    /// # Ok(()) }
+    /// ```
+    pub async fn backup_volume<W>(&self, name: &str, mut writer: W) -> Result<(), DockerError>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        self.client()
+            .inspect_volume(name)
+            .await
+            .map_err(DockerError::BollardError)?;
+
+        let helper = self
+            .create_volume_helper(vec![volume_mount(name, "/volume", true)])
+            .await?;
+        let result = async {
+            let mut archive = self.client().download_from_container(
+                &helper,
+                Some(DownloadFromContainerOptions { path: "/volume/." }),
+            );
+            while let Some(chunk) = archive.try_next().await? {
+                writer.write_all(&chunk).await?;
+            }
+            writer.flush().await?;
+            Ok(())
+        }
+        .await;
+
+        self.remove_volume_helper(&helper).await;
+        result
+    }
+
+    /// Extracts a tar archive, such as one written by [`backup_volume()`], into a volume
+    ///
+    /// The volume is created if it doesn't exist. Files in the archive replace existing files at
+    /// the same paths, other files in the volume are kept.
+    ///
+    /// The archive is buffered in a temporary file and checked before anything is extracted, so
+    /// the volume is left untouched when `reader` fails or the archive is truncated.
+    ///
+    /// [`backup_volume()`]: Self::backup_volume
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the volume to restore into
+    /// * `reader` - Source of the archive
+    ///
+    /// # Errors
+    ///
+    /// * The volume or the helper container can't be created
+    /// * Reading from `reader` fails, or the archive is invalid
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use docktopus::DockerBuilder;
+    ///
+    /// # async fn example() -> Result<(), docktopus::DockerError> {
+    /// let builder = DockerBuilder::new().await?;
+    /// let file = tokio::fs::File::open("chain-data.tar").await?;
+    /// builder.restore_volume("chain_data", file).await?;
+    /// # Ok(()) }
+    /// ```
+    pub async fn restore_volume<R>(&self, name: &str, mut reader: R) -> Result<(), DockerError>
+    where
+        R: AsyncRead + Send + Unpin + 'static,
+    {
+        let mut archive = tokio::fs::File::from_std(tempfile::tempfile()?);
+        tokio::io::copy(&mut reader, &mut archive).await?;
+        let archive = validate_archive(archive.into_std().await)?;
+
+        if self.client().inspect_volume(name).await.is_err() {
+            self.create_volume(name).await?;
+        }
+
+        let chunks = stream::unfold(archive, |mut reader| async move {
+            let mut chunk = vec![0; ARCHIVE_CHUNK_SIZE];
+            match reader.read(&mut chunk).await {
+                Ok(0) => None,
+                Ok(read) => {
+                    chunk.truncate(read);
+                    Some((Ok(chunk), reader))
+                }
+                Err(e) => Some((Err(e), reader)),
+            }
+        });
+        let (archive, read_error) = until_error(chunks);
+
+        self.upload_to_volume(name, archive).await?;
+        match take_error(&read_error) {
+            Some(e) => Err(DockerError::FileError(e)),
+            None => Ok(()),
+        }
+    }
+
+    /// Copies the contents of the volume `source` into the volume `destination`
+    ///
+    /// The destination is created if it doesn't exist, existing files are handled as in
+    /// [`restore_volume()`](Self::restore_volume).
+    ///
+    /// # Arguments
+    ///
+    /// * `source` - Name of the volume to copy
+    /// * `destination` - Name of the volume to copy into
+    ///
+    /// # Errors
+    ///
+    /// * The source volume doesn't exist
+    /// * The destination volume or the helper containers can't be created
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use docktopus::DockerBuilder;
+    ///
+    /// # async fn example() -> Result<(), docktopus::DockerError> {
+    /// let builder = DockerBuilder::new().await?;
+    /// builder.clone_volume("chain_data", "chain_data_snapshot").await?;
+    /// # Ok(()) }
+    /// ```
+    pub async fn clone_volume(&self, source: &str, destination: &str) -> Result<(), DockerError> {
+        if source == destination {
+            return Err(DockerError::ValidationError(format!(
+                "Cannot clone volume '{}' into itself",
+                source
+            )));
+        }
+        self.client()
+            .inspect_volume(source)
+            .await
+            .map_err(DockerError::BollardError)?;
+        if self.client().inspect_volume(destination).await.is_err() {
+            self.create_volume(destination).await?;
+        }
+
+        let helper = self
+            .create_volume_helper(vec![volume_mount(source, "/volume", true)])
+            .await?;
+        let archive = self.client().download_from_container(
+            &helper,
+            Some(DownloadFromContainerOptions { path: "/volume/." }),
+        );
+        let (archive, download_error) = until_error(archive);
+
+        let result = self
+            .upload_to_volume(destination, archive.map(Vec::from))
+            .await;
+        self.remove_volume_helper(&helper).await;
+
+        match take_error(&download_error) {
+            Some(e) => Err(DockerError::BollardError(e)),
+            None => result,
+        }
+    }
+
+    /// Extracts a tar archive into a volume through a helper container
+    async fn upload_to_volume(
+        &self,
+        name: &str,
+        archive: impl Stream<Item = Vec<u8>> + Send + 'static,
+    ) -> Result<(), DockerError> {
+        let helper = self
+            .create_volume_helper(vec![volume_mount(name, "/volume", false)])
+            .await?;
+        let result = self
+            .client()
+            .upload_to_container_streaming(
+                &helper,
+                Some(UploadToContainerOptions {
+                    path: "/volume",
+                    ..Default::default()
+                }),
+                archive.map(Into::into),
+            )
+            .await
+            .map_err(DockerError::BollardError);
+
+        self.remove_volume_helper(&helper).await;
+        result
+    }
+
+    /// Creates a stopped container with `mounts`, whose filesystem can be read and written
+    async fn create_volume_helper(&self, mounts: Vec<Mount>) -> Result<String, DockerError> {
+        if self
+            .client()
+            .inspect_image(VOLUME_HELPER_IMAGE)
+            .await
+            .is_err()
+        {
+            self.pull_image(VOLUME_HELPER_IMAGE, None).await?;
+        }
+
+        let container = self
+            .client()
+            .create_container(
+                None::<CreateContainerOptions<String>>,
+                Config {
+                    image: Some(VOLUME_HELPER_IMAGE.to_string()),
+                    host_config: Some(HostConfig {
+                        mounts: Some(mounts),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+            )
+            .await
+            .map_err(DockerError::BollardError)?;
+
+        Ok(container.id)
+    }
+
+    async fn remove_volume_helper(&self, container_id: &str) {
+        let options = RemoveContainerOptions {
+            force: true,
+            v: false,
+            ..Default::default()
+        };
+        if let Err(e) = self
+            .client()
+            .remove_container(container_id, Some(options))
+            .await
+        {
+            log::warn!(
+                "Failed to remove volume helper container {}: {}",
+                container_id,
+                e
+            );
+        }
+    }
+
+    /// Inspects a volume, including its disk usage and the containers using it
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the volume
+    ///
+    /// # Errors
+    ///
+    /// Will return a `DockerError::BollardError` if the volume doesn't exist or the operation fails
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use docktopus::DockerBuilder;
+    ///
+    /// # async fn example() -> Result<(), docktopus::DockerError> {
+    /// let builder = DockerBuilder::new().await?;
+    /// let info = builder.volume_info("chain_data").await?;
+    /// println!("{} uses {:?} bytes", info.name, info.size);
+    /// # Ok(()) }
+    /// ```
+    pub async fn volume_info(&self, name: &str) -> Result<VolumeInfo, DockerError> {
+        let volume = self
+            .client()
+            .inspect_volume(name)
+            .await
+            .map_err(DockerError::BollardError)?;
+
+        let mut volumes = self.volume_infos(vec![volume]).await?;
+        Ok(volumes.remove(0))
+    }
+
+    /// Lists the volumes matching `filters`, with the details of [`volume_info()`]
+    ///
+    /// Disk usage is computed by the daemon for every volume, which can be slow on hosts with many
+    /// large volumes.
+    ///
+    /// [`volume_info()`]: Self::volume_info
+    ///
+    /// # Arguments
+    ///
+    /// * `filters` - Filters the volumes must match
+    ///
+    /// # Errors
+    ///
+    /// Will return a `DockerError::BollardError` if the operation fails
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use docktopus::DockerBuilder;
+    /// use docktopus::builder::management::ResourceFilters;
+    ///
+    /// # async fn example() -> Result<(), docktopus::DockerError> {
+    /// let builder = DockerBuilder::new().await?;
+    /// let filters = ResourceFilters::default().label("app").dangling(true);
+    /// for volume in builder.list_volume_infos(&filters).await? {
+    ///     builder.remove_volume(&volume.name).await?;
+    /// }
+    /// # Ok(()) }
+    /// ```
+    pub async fn list_volume_infos(
+        &self,
+        filters: &ResourceFilters,
+    ) -> Result<Vec<VolumeInfo>, DockerError> {
+        let volumes = self
+            .client()
+            .list_volumes(Some(ListVolumesOptions {
+                filters: filters.to_docker_filters(),
+            }))
+            .await
+            .map_err(DockerError::BollardError)?;
+
+        self.volume_infos(volumes.volumes.unwrap_or_default()).await
+    }
+
+    /// Adds the disk usage and users of each volume
+    async fn volume_infos(
+        &self,
+        volumes: Vec<bollard::models::Volume>,
+    ) -> Result<Vec<VolumeInfo>, DockerError> {
+        if volumes.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let usage = self
+            .client()
+            .df()
+            .await
+            .map_err(DockerError::BollardError)?;
+        let sizes: HashMap<String, u64> = usage
+            .volumes
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|volume| {
+                let size = u64::try_from(volume.usage_data?.size).ok()?;
+                Some((volume.name, size))
+            })
+            .collect();
+
+        let containers = self
+            .client()
+            .list_containers(Some(ListContainersOptions::<String> {
+                all: true,
+                ..Default::default()
+            }))
+            .await
+            .map_err(DockerError::BollardError)?;
+        let mut users: HashMap<String, Vec<String>> = HashMap::new();
+        for container in containers {
+            let container_name = container
+                .names
+                .and_then(|names| names.into_iter().next())
+                .map(|name| name.trim_start_matches('/').to_string())
+                .or(container.id)
+                .unwrap_or_default();
+            for mount in container.mounts.unwrap_or_default() {
+                if let Some(volume) = mount.name {
+                    users
+                        .entry(volume)
+                        .or_default()
+                        .push(container_name.clone());
+                }
+            }
+        }
+
+        Ok(volumes
+            .into_iter()
+            .map(|volume| {
+                let mut in_use_by = users.remove(&volume.name).unwrap_or_default();
+                in_use_by.sort();
+                VolumeInfo {
+                    size: sizes.get(&volume.name).copied(),
+                    scope: volume
+                        .scope
+                        .map(|scope| scope.to_string())
+                        .filter(|scope| !scope.is_empty()),
+                    name: volume.name,
+                    driver: volume.driver,
+                    mountpoint: volume.mountpoint,
+                    labels: volume.labels,
+                    in_use_by,
+                }
+            })
+            .collect())
+    }
+
+    /// Inspects a network, including the containers connected to it
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name or ID of the network
+    ///
+    /// # Errors
+    ///
+    /// Will return a `DockerError::BollardError` if the network doesn't exist or the operation fails
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use docktopus::DockerBuilder;
+    ///
+    /// # async fn example() -> Result<(), docktopus::DockerError> {
+    /// let builder = DockerBuilder::new().await?;
+    /// let info = builder.network_info("my-network").await?;
+    /// for container in info.containers {
+    ///     println!("{:?}: {:?}", container.name, container.ipv4_address);
+    /// }
+    /// # Ok(()) }
+    /// ```
+    pub async fn network_info(&self, name: &str) -> Result<NetworkInfo, DockerError> {
+        self.client()
+            .inspect_network(
+                name,
+                Some(InspectNetworkOptions::<String> {
+                    verbose: false,
+                    ..Default::default()
+                }),
+            )
+            .await
+            .map(NetworkInfo::from)
+            .map_err(DockerError::BollardError)
+    }
+
+    /// Lists the networks matching `filters`, with the details of [`network_info()`]
+    ///
+    /// [`network_info()`]: Self::network_info
+    ///
+    /// # Arguments
+    ///
+    /// * `filters` - Filters the networks must match
+    ///
+    /// # Errors
+    ///
+    /// Will return a `DockerError::BollardError` if the operation fails
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use docktopus::DockerBuilder;
+    /// use docktopus::builder::management::ResourceFilters;
+    ///
+    /// # async fn example() -> Result<(), docktopus::DockerError> {
+    /// let builder = DockerBuilder::new().await?;
+    /// let filters = ResourceFilters::default().label_value("env", "test");
+    /// for network in builder.list_network_infos(&filters).await? {
+    ///     if network.containers.is_empty() {
+    ///         builder.remove_network(&network.id).await?;
+    ///     }
+    /// }
+    /// # Ok(()) }
+    /// ```
+    pub async fn list_network_infos(
+        &self,
+        filters: &ResourceFilters,
+    ) -> Result<Vec<NetworkInfo>, DockerError> {
+        let networks = self
+            .client()
+            .list_networks(Some(ListNetworksOptions {
+                filters: filters.to_docker_filters(),
+            }))
+            .await
+            .map_err(DockerError::BollardError)?;
+
+        // Listed networks don't include their containers
+        let mut infos = Vec::with_capacity(networks.len());
+        for network in networks {
+            let Some(id) = network.id else {
+                continue;
+            };
+            match self.network_info(&id).await {
+                Ok(info) => infos.push(info),
+                // Removed since it was listed
+                Err(DockerError::BollardError(
+                    bollard::errors::Error::DockerResponseServerError {
+                        status_code: 404, ..
+                    },
+                )) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(infos)
+    }
+
     /// Waits for a container to be in a running state
     ///
     /// This method polls the container status until it is running or the maximum number of retries
@@ -466,3 +1305,57 @@ impl DockerBuilder {
         }
     }
 }
+
+fn volume_mount(name: &str, target: &str, read_only: bool) -> Mount {
+    Mount {
+        source: Some(name.to_string()),
+        target: Some(target.to_string()),
+        typ: Some(MountTypeEnum::VOLUME),
+        read_only: Some(read_only),
+        ..Default::default()
+    }
+}
+
+/// Reads through the tar archive in `file`, and rewinds it if it is valid
+fn validate_archive(mut file: std::fs::File) -> Result<tokio::fs::File, DockerError> {
+    use std::io::{Seek, SeekFrom};
+
+    file.seek(SeekFrom::Start(0))?;
+    let invalid =
+        |e: std::io::Error| DockerError::ValidationError(format!("Invalid archive: {}", e));
+    for entry in tar::Archive::new(&mut file).entries().map_err(invalid)? {
+        entry.map_err(invalid)?;
+    }
+    file.seek(SeekFrom::Start(0))?;
+
+    Ok(tokio::fs::File::from_std(file))
+}
+
+/// Ends `stream` at its first error, which is kept for after the stream is consumed
+///
+/// The archive APIs take streams of chunks, which can't carry the errors of their source.
+fn until_error<T, E>(
+    stream: impl Stream<Item = Result<T, E>>,
+) -> (impl Stream<Item = T>, Arc<Mutex<Option<E>>>) {
+    let error = Arc::new(Mutex::new(None));
+    let slot = Arc::clone(&error);
+    let stream = stream.scan((), move |(), item| {
+        std::future::ready(match item {
+            Ok(item) => Some(item),
+            Err(e) => {
+                *slot
+                    .lock()
+                    .unwrap_or_else(std::sync::PoisonError::into_inner) = Some(e);
+                None
+            }
+        })
+    });
+    (stream, error)
+}
+
+fn take_error<E>(error: &Mutex<Option<E>>) -> Option<E> {
+    error
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .take()
+}
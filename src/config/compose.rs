@@ -1,11 +1,22 @@
 use super::EnvironmentVars;
-use super::volume::Volume;
+use super::env_vars::{EnvFile, deserialize_env_files};
+use super::network::{NetworkConfig, NetworkMode, ServiceNetworks, deserialize_networks};
+use super::resources::{
+    ByteSize, DeployConfig, Device, Ulimit, compose_duration, deserialize_cpus,
+    deserialize_key_values,
+};
+use super::scalar;
+use super::secrets::{FileObject, FileObjectReference};
+use super::volume::{Volume, deserialize_service_volumes};
 use crate::config::health::HealthCheck;
 use crate::config::requirements::SystemRequirements;
 use crate::error::DockerError;
-use regex::Regex;
+#[cfg(feature = "deploy")]
+use bollard::service::{PortBinding, RestartPolicy, RestartPolicyNameEnum};
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::Path;
+use std::time::Duration;
 
 /// Configuration for a single service in a Docker Compose file
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
@@ -16,13 +27,130 @@ pub struct Service {
     #[serde(deserialize_with = "deserialize_command")]
     pub command: Option<Vec<String>>,
     pub environment: Option<EnvironmentVars>,
-    pub env_file: Option<Vec<String>>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_env_files",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub env_file: Option<Vec<EnvFile>>,
+    #[serde(default, deserialize_with = "deserialize_service_volumes")]
     pub volumes: Option<Vec<Volume>>,
-    pub depends_on: Option<Vec<String>>,
-    pub ports: Option<Vec<String>>,
-    pub networks: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub depends_on: Option<DependsOn>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub links: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_links: Option<Vec<String>>,
+    pub ports: Option<Vec<PortMapping>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expose: Option<Vec<ExposedPort>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub networks: Option<ServiceNetworks>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network_mode: Option<NetworkMode>,
     pub requirements: Option<SystemRequirements>,
     #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deploy: Option<DeployConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mem_limit: Option<ByteSize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mem_reservation: Option<ByteSize>,
+    /// Total memory including swap
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memswap_limit: Option<ByteSize>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_cpus",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub cpus: Option<f64>,
+    #[serde(
+        default,
+        deserialize_with = "scalar::deserialize_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub cpu_shares: Option<i64>,
+    /// CPUs in which to allow execution (`0-3`, `0,1`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cpuset: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ulimits: Option<BTreeMap<String, Ulimit>>,
+    #[serde(
+        default,
+        deserialize_with = "scalar::deserialize_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub pids_limit: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shm_size: Option<ByteSize>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_key_values",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub sysctls: Option<HashMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cap_add: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cap_drop: Option<Vec<String>>,
+    #[serde(
+        default,
+        deserialize_with = "scalar::deserialize_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub privileged: Option<bool>,
+    /// Mount the container's root filesystem as read only
+    #[serde(
+        default,
+        deserialize_with = "scalar::deserialize_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub read_only: Option<bool>,
+    /// Paths to mount as tmpfs, optionally followed by `:` and mount options
+    #[serde(
+        default,
+        deserialize_with = "deserialize_string_or_list",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub tmpfs: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub devices: Option<Vec<Device>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub security_opt: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub working_dir: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_command",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub entrypoint: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hostname: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_signal: Option<String>,
+    /// Time to wait for the container to stop before killing it
+    #[serde(
+        default,
+        with = "compose_duration",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub stop_grace_period: Option<Duration>,
+    /// Run an init process inside the container that forwards signals and reaps processes
+    #[serde(
+        default,
+        deserialize_with = "scalar::deserialize_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub init: Option<bool>,
+    /// Additional `/etc/hosts` entries, in `HOST:IP` format
+    #[serde(
+        default,
+        deserialize_with = "deserialize_extra_hosts",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub extra_hosts: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub healthcheck: Option<HealthCheck>,
     pub restart: Option<String>,
     pub user: Option<String>,
@@ -30,8 +158,726 @@ pub struct Service {
     pub labels: Option<HashMap<String, String>>,
     #[serde(default)]
     pub platform: Option<String>,
+    /// Secrets mounted into the container, see [`ComposeConfig::secrets`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secrets: Option<Vec<FileObjectReference>>,
+    /// Configs mounted into the container, see [`ComposeConfig::configs`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub configs: Option<Vec<FileObjectReference>>,
+    /// Profiles the service belongs to, it is only deployed when one of them is active
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profiles: Option<Vec<String>>,
+    /// Extension fields (`x-*` keys), which are not interpreted but kept for tools to read
+    #[serde(flatten, deserialize_with = "deserialize_extensions")]
+    pub extensions: BTreeMap<String, serde_yaml::Value>,
+}
+
+impl Service {
+    /// Whether the service is enabled with the given active profiles
+    ///
+    /// Services without any `profiles` are always enabled.
+    #[must_use]
+    pub fn is_enabled<S: AsRef<str>>(&self, active_profiles: &[S]) -> bool {
+        match &self.profiles {
+            Some(profiles) if !profiles.is_empty() => profiles
+                .iter()
+                .any(|profile| active_profiles.iter().any(|p| p.as_ref() == profile)),
+            _ => true,
+        }
+    }
+
+    /// Names of all services this service depends on
+    ///
+    /// This includes `depends_on` entries, the services referenced by `links` and a
+    /// `network_mode` of `service:<name>`.
+    pub fn dependency_names(&self) -> impl Iterator<Item = &str> {
+        let depends_on = self.depends_on.iter().flat_map(DependsOn::names);
+        let links = self
+            .links
+            .iter()
+            .flatten()
+            .map(|link| link.split_once(':').map_or(link.as_str(), |(name, _)| name));
+        let network_mode = self.network_mode.as_ref().and_then(NetworkMode::service);
+        depends_on.chain(links).chain(network_mode)
+    }
+
+    /// The service's `environment`, merged on top of the variables from its `env_file` entries
+    ///
+    /// Later env files override earlier ones, and `environment` overrides all of them. Relative
+    /// env file paths are resolved against `base_dir`, the compose file directory.
+    ///
+    /// # Errors
+    ///
+    /// A required env file cannot be read
+    pub fn resolved_environment(
+        &self,
+        base_dir: &Path,
+    ) -> Result<Option<EnvironmentVars>, DockerError> {
+        let Some(env_files) = &self.env_file else {
+            return Ok(self.environment.clone());
+        };
+
+        let mut environment = EnvironmentVars::default();
+        for env_file in env_files {
+            if let Some(vars) = env_file.load(base_dir)? {
+                environment.extend(vars);
+            }
+        }
+        if let Some(service_environment) = &self.environment {
+            environment.extend(service_environment.clone());
+        }
+
+        Ok(Some(environment))
+    }
 }
 
+#[cfg(feature = "deploy")]
+impl Service {
+    /// The `restart` policy of the service, in the format expected by Docker
+    ///
+    /// # Errors
+    ///
+    /// `restart` is not one of `no`, `always`, `on-failure[:max-retries]` or `unless-stopped`
+    pub fn restart_policy(&self) -> Result<Option<RestartPolicy>, DockerError> {
+        let Some(restart) = &self.restart else {
+            return Ok(None);
+        };
+
+        let invalid =
+            || DockerError::ValidationError(format!("Invalid restart policy: {}", restart));
+        let (name, maximum_retry_count) = match restart.trim() {
+            "no" => (RestartPolicyNameEnum::NO, None),
+            "always" => (RestartPolicyNameEnum::ALWAYS, None),
+            "unless-stopped" => (RestartPolicyNameEnum::UNLESS_STOPPED, None),
+            "on-failure" => (RestartPolicyNameEnum::ON_FAILURE, None),
+            policy => {
+                let retries = policy
+                    .strip_prefix("on-failure:")
+                    .ok_or_else(invalid)?
+                    .parse::<i64>()
+                    .map_err(|_| invalid())?;
+                (RestartPolicyNameEnum::ON_FAILURE, Some(retries))
+            }
+        };
+
+        Ok(Some(RestartPolicy {
+            name: Some(name),
+            maximum_retry_count,
+        }))
+    }
+}
+
+/// The condition a dependency must meet before a dependent service is started
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DependencyCondition {
+    /// The dependency container has been started
+    #[default]
+    ServiceStarted,
+    /// The dependency container reports itself as healthy
+    ServiceHealthy,
+    /// The dependency container ran to completion with a zero exit code
+    ServiceCompletedSuccessfully,
+}
+
+/// A single entry in a service's `depends_on`
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServiceDependency {
+    #[serde(default)]
+    pub condition: DependencyCondition,
+    /// Whether `docker compose` restarts the dependent service when this dependency is updated
+    ///
+    /// Only kept so the dependency is written back unchanged, deploys never restart dependent
+    /// services.
+    #[serde(default, deserialize_with = "scalar::deserialize")]
+    pub restart: bool,
+    /// Whether the dependency must exist and meet its condition
+    ///
+    /// Optional dependencies that are missing or fail their condition only produce a warning.
+    #[serde(default = "default_true", deserialize_with = "scalar::deserialize")]
+    pub required: bool,
+}
+
+impl Default for ServiceDependency {
+    fn default() -> Self {
+        Self {
+            condition: DependencyCondition::default(),
+            restart: false,
+            required: true,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// The `depends_on` section of a service
+///
+/// Both the short list form and the long map form are accepted:
+///
+/// ```yaml
+/// depends_on:
+///   - db
+/// ```
+///
+/// ```yaml
+/// depends_on:
+///   db:
+///     condition: service_healthy
+///     restart: true
+///     required: false
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct DependsOn(BTreeMap<String, ServiceDependency>);
+
+impl<'de> Deserialize<'de> for DependsOn {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+        let value = serde_yaml::Value::deserialize(deserializer)?;
+
+        let map = match value {
+            serde_yaml::Value::Sequence(seq) => seq
+                .into_iter()
+                .map(|item| {
+                    item.as_str()
+                        .map(|name| (name.to_string(), ServiceDependency::default()))
+                        .ok_or_else(|| Error::custom("depends_on list item must be a string"))
+                })
+                .collect::<Result<_, _>>()?,
+            serde_yaml::Value::Mapping(map) => {
+                let mut dependencies = BTreeMap::new();
+                for (name, dependency) in map {
+                    let name = name
+                        .as_str()
+                        .ok_or_else(|| Error::custom("depends_on key must be a string"))?
+                        .to_string();
+                    let dependency = if dependency.is_null() {
+                        ServiceDependency::default()
+                    } else {
+                        serde_yaml::from_value(dependency).map_err(Error::custom)?
+                    };
+                    dependencies.insert(name, dependency);
+                }
+                dependencies
+            }
+            _ => return Err(Error::custom("depends_on must be a sequence or mapping")),
+        };
+
+        Ok(DependsOn(map))
+    }
+}
+
+impl DependsOn {
+    /// Names of the services depended on
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.0.keys().map(String::as_str)
+    }
+
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&ServiceDependency> {
+        self.0.get(name)
+    }
+
+    #[must_use]
+    pub fn contains(&self, name: &str) -> bool {
+        self.0.contains_key(name)
+    }
+
+    pub fn insert(&mut self, name: impl Into<String>, dependency: ServiceDependency) {
+        self.0.insert(name.into(), dependency);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &ServiceDependency)> {
+        self.0.iter()
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<S: Into<String>> FromIterator<S> for DependsOn {
+    fn from_iter<T: IntoIterator<Item = S>>(iter: T) -> Self {
+        DependsOn(
+            iter.into_iter()
+                .map(|name| (name.into(), ServiceDependency::default()))
+                .collect(),
+        )
+    }
+}
+
+impl From<Vec<String>> for DependsOn {
+    fn from(names: Vec<String>) -> Self {
+        names.into_iter().collect()
+    }
+}
+
+impl<'a> IntoIterator for &'a DependsOn {
+    type Item = (&'a String, &'a ServiceDependency);
+    type IntoIter = std::collections::btree_map::Iter<'a, String, ServiceDependency>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+/// The transport protocol of a port
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PortProtocol {
+    #[default]
+    Tcp,
+    Udp,
+    Sctp,
+}
+
+impl std::fmt::Display for PortProtocol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PortProtocol::Tcp => write!(f, "tcp"),
+            PortProtocol::Udp => write!(f, "udp"),
+            PortProtocol::Sctp => write!(f, "sctp"),
+        }
+    }
+}
+
+impl std::str::FromStr for PortProtocol {
+    type Err = DockerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "tcp" => Ok(PortProtocol::Tcp),
+            "udp" => Ok(PortProtocol::Udp),
+            "sctp" => Ok(PortProtocol::Sctp),
+            _ => Err(DockerError::ValidationError(format!(
+                "Invalid port protocol: {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// An inclusive range of ports, such as `8000-8010`, or a single port
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PortRange {
+    pub start: u16,
+    pub end: u16,
+}
+
+impl PortRange {
+    /// A range containing only `port`
+    #[must_use]
+    pub fn single(port: u16) -> Self {
+        PortRange {
+            start: port,
+            end: port,
+        }
+    }
+
+    /// The number of ports in the range
+    #[must_use]
+    pub fn count(&self) -> usize {
+        usize::from(self.end - self.start) + 1
+    }
+
+    /// Whether the range is a single port
+    #[must_use]
+    pub fn is_single(&self) -> bool {
+        self.start == self.end
+    }
+
+    /// The ports in the range
+    pub fn iter(&self) -> impl Iterator<Item = u16> {
+        self.start..=self.end
+    }
+}
+
+impl std::fmt::Display for PortRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_single() {
+            write!(f, "{}", self.start)
+        } else {
+            write!(f, "{}-{}", self.start, self.end)
+        }
+    }
+}
+
+impl std::str::FromStr for PortRange {
+    type Err = DockerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_port = |port: &str| {
+            port.trim()
+                .parse::<u16>()
+                .map_err(|_| DockerError::ValidationError(format!("Invalid port: {}", s)))
+        };
+
+        let range = match s.split_once('-') {
+            Some((start, end)) => PortRange {
+                start: parse_port(start)?,
+                end: parse_port(end)?,
+            },
+            None => PortRange::single(parse_port(s)?),
+        };
+
+        if range.start > range.end {
+            return Err(DockerError::ValidationError(format!(
+                "Invalid port range: {}",
+                s
+            )));
+        }
+
+        Ok(range)
+    }
+}
+
+impl Serialize for PortRange {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        if self.is_single() {
+            serializer.serialize_u16(self.start)
+        } else {
+            serializer.collect_str(self)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for PortRange {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+        match serde_yaml::Value::deserialize(deserializer)? {
+            serde_yaml::Value::Number(n) => n
+                .as_u64()
+                .and_then(|port| u16::try_from(port).ok())
+                .map(PortRange::single)
+                .ok_or_else(|| Error::custom(format!("Invalid port: {}", n))),
+            serde_yaml::Value::String(s) => s.parse().map_err(Error::custom),
+            _ => Err(Error::custom("port must be a number or string")),
+        }
+    }
+}
+
+/// A published port in a service's `ports` section
+///
+/// Both the short syntax and the long syntax are accepted:
+///
+/// ```yaml
+/// ports:
+///   - "3000"
+///   - "8000-8010:8000-8010"
+///   - "127.0.0.1:8545:8545"
+///   - "[::1]:6001:6001"
+///   - "30303:30303/udp"
+///   - target: 9000
+///     published: "9000"
+///     host_ip: 127.0.0.1
+///     protocol: udp
+/// ```
+///
+/// Mappings are serialized using the short syntax unless a `mode` is set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortMapping {
+    /// The container port(s)
+    pub target: PortRange,
+    /// The host port(s), a random port is used if unset
+    pub published: Option<PortRange>,
+    /// The host address to bind to, all interfaces if unset
+    pub host_ip: Option<String>,
+    pub protocol: PortProtocol,
+    /// Either `host` or `ingress`, only meaningful for swarm deployments
+    pub mode: Option<String>,
+}
+
+impl PortMapping {
+    /// Publishes the container port `target` on the host port `published`
+    #[must_use]
+    pub fn new(published: u16, target: u16) -> Self {
+        PortMapping {
+            target: PortRange::single(target),
+            published: Some(PortRange::single(published)),
+            host_ip: None,
+            protocol: PortProtocol::Tcp,
+            mode: None,
+        }
+    }
+
+    /// The container ports in Docker's `<port>/<protocol>` format
+    pub fn container_ports(&self) -> impl Iterator<Item = String> + '_ {
+        self.target
+            .iter()
+            .map(move |port| format!("{}/{}", port, self.protocol))
+    }
+
+    /// The host bindings of each container port, in Docker's `<port>/<protocol>` format
+    #[cfg(feature = "deploy")]
+    #[must_use]
+    pub fn port_bindings(&self) -> Vec<(String, PortBinding)> {
+        let host_ports: Vec<Option<String>> = match self.published {
+            // One to one mapping of each port
+            Some(published) if published.count() == self.target.count() => published
+                .iter()
+                .map(|port| Some(port.to_string()))
+                .collect(),
+            // A single container port published on any free port of the host range
+            Some(published) => vec![Some(published.to_string())],
+            None => vec![None; self.target.count()],
+        };
+
+        self.container_ports()
+            .zip(host_ports)
+            .map(|(container_port, host_port)| {
+                (
+                    container_port,
+                    PortBinding {
+                        host_ip: self.host_ip.clone(),
+                        host_port,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    fn validate(self) -> Result<Self, DockerError> {
+        if let Some(published) = self.published {
+            if published.count() != self.target.count() && !self.target.is_single() {
+                return Err(DockerError::ValidationError(format!(
+                    "Port range {} cannot be published on {}",
+                    self.target, published
+                )));
+            }
+        }
+
+        Ok(self)
+    }
+}
+
+impl std::fmt::Display for PortMapping {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.host_ip {
+            Some(host_ip) if host_ip.contains(':') => write!(f, "[{}]:", host_ip)?,
+            Some(host_ip) => write!(f, "{}:", host_ip)?,
+            None => {}
+        }
+        match self.published {
+            Some(published) => write!(f, "{}:", published)?,
+            None if self.host_ip.is_some() => write!(f, ":")?,
+            None => {}
+        }
+        write!(f, "{}", self.target)?;
+        if self.protocol != PortProtocol::Tcp {
+            write!(f, "/{}", self.protocol)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for PortMapping {
+    type Err = DockerError;
+
+    /// Parses the short syntax, `[[HOST_IP:]PUBLISHED:]TARGET[/PROTOCOL]`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (spec, protocol) = match s.rsplit_once('/') {
+            Some((spec, protocol)) => (spec, protocol.parse()?),
+            None => (s, PortProtocol::Tcp),
+        };
+
+        // IPv6 host addresses may be wrapped in brackets to separate them from the ports
+        let (host_ip, ports) = match spec.strip_prefix('[') {
+            Some(rest) => {
+                let (host_ip, ports) = rest
+                    .split_once("]:")
+                    .ok_or_else(|| DockerError::ValidationError(format!("Invalid port: {}", s)))?;
+                (Some(host_ip), ports)
+            }
+            None => (None, spec),
+        };
+
+        let mut parts = ports.rsplitn(3, ':');
+        let target = parts
+            .next()
+            .ok_or_else(|| DockerError::ValidationError(format!("Invalid port: {}", s)))?
+            .parse()?;
+        let published = match parts.next() {
+            Some("") | None => None,
+            Some(published) => Some(published.parse()?),
+        };
+        let host_ip = match (host_ip, parts.next()) {
+            (Some(_), Some(_)) => {
+                return Err(DockerError::ValidationError(format!("Invalid port: {}", s)));
+            }
+            (Some(host_ip), None) | (None, Some(host_ip)) => Some(host_ip.to_string()),
+            (None, None) => None,
+        };
+
+        PortMapping {
+            target,
+            published,
+            host_ip,
+            protocol,
+            mode: None,
+        }
+        .validate()
+    }
+}
+
+/// The long syntax of a [`PortMapping`]
+#[derive(Serialize, Deserialize)]
+struct LongPortMapping {
+    target: PortRange,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    published: Option<PortRange>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    host_ip: Option<String>,
+    #[serde(default)]
+    protocol: PortProtocol,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mode: Option<String>,
+}
+
+impl Serialize for PortMapping {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        // The short syntax has no way to express the mode
+        if self.mode.is_none() {
+            return serializer.collect_str(self);
+        }
+
+        LongPortMapping {
+            target: self.target,
+            published: self.published,
+            host_ip: self.host_ip.clone(),
+            protocol: self.protocol,
+            mode: self.mode.clone(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for PortMapping {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+        let value = serde_yaml::Value::deserialize(deserializer)?;
+
+        match value {
+            serde_yaml::Value::String(s) => s.parse().map_err(Error::custom),
+            serde_yaml::Value::Number(_) => {
+                let target = serde_yaml::from_value(value).map_err(Error::custom)?;
+                Ok(PortMapping {
+                    target,
+                    published: None,
+                    host_ip: None,
+                    protocol: PortProtocol::Tcp,
+                    mode: None,
+                })
+            }
+            serde_yaml::Value::Mapping(_) => {
+                let long: LongPortMapping = serde_yaml::from_value(value).map_err(Error::custom)?;
+                PortMapping {
+                    target: long.target,
+                    published: long.published,
+                    host_ip: long.host_ip,
+                    protocol: long.protocol,
+                    mode: long.mode,
+                }
+                .validate()
+                .map_err(Error::custom)
+            }
+            _ => Err(Error::custom("port must be a string, number or mapping")),
+        }
+    }
+}
+
+/// A port in a service's `expose` section, such as `3000`, `8000-8010` or `30303/udp`
+///
+/// Exposed ports are accessible to other containers but are not published on the host.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ExposedPort {
+    pub target: PortRange,
+    pub protocol: PortProtocol,
+}
+
+impl ExposedPort {
+    /// The container ports in Docker's `<port>/<protocol>` format
+    pub fn container_ports(&self) -> impl Iterator<Item = String> + '_ {
+        self.target
+            .iter()
+            .map(move |port| format!("{}/{}", port, self.protocol))
+    }
+}
+
+impl std::fmt::Display for ExposedPort {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.target)?;
+        if self.protocol != PortProtocol::Tcp {
+            write!(f, "/{}", self.protocol)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for ExposedPort {
+    type Err = DockerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (target, protocol) = match s.rsplit_once('/') {
+            Some((target, protocol)) => (target, protocol.parse()?),
+            None => (s, PortProtocol::Tcp),
+        };
+
+        Ok(ExposedPort {
+            target: target.parse()?,
+            protocol,
+        })
+    }
+}
+
+impl Serialize for ExposedPort {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ExposedPort {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+        match serde_yaml::Value::deserialize(deserializer)? {
+            serde_yaml::Value::String(s) => s.parse().map_err(Error::custom),
+            value @ serde_yaml::Value::Number(_) => Ok(ExposedPort {
+                target: serde_yaml::from_value(value).map_err(Error::custom)?,
+                protocol: PortProtocol::Tcp,
+            }),
+            _ => Err(Error::custom("exposed port must be a string or number")),
+        }
+    }
+}
+
+/// Deserializes a `command` or `entrypoint`, string forms are split like a shell would
 fn deserialize_command<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
 where
     D: serde::Deserializer<'de>,
@@ -40,7 +886,9 @@ where
     let value = serde_yaml::Value::deserialize(deserializer)?;
 
     match value {
-        serde_yaml::Value::String(s) => Ok(Some(vec![s])),
+        serde_yaml::Value::String(s) => shell_words::split(&s)
+            .map(Some)
+            .map_err(|e| Error::custom(format!("Invalid command '{}': {}", s, e))),
         serde_yaml::Value::Sequence(seq) => {
             let items: Result<Vec<String>, _> = seq
                 .into_iter()
@@ -57,6 +905,72 @@ where
     }
 }
 
+fn deserialize_string_or_list<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::de::Error;
+    let value = serde_yaml::Value::deserialize(deserializer)?;
+
+    match value {
+        serde_yaml::Value::String(s) => Ok(Some(vec![s])),
+        serde_yaml::Value::Sequence(_) => serde_yaml::from_value(value)
+            .map(Some)
+            .map_err(Error::custom),
+        serde_yaml::Value::Null => Ok(None),
+        _ => Err(Error::custom("expected a string or sequence")),
+    }
+}
+
+/// Deserializes `extra_hosts` from a list of `HOST:IP` or `HOST=IP` items, or a mapping
+fn deserialize_extra_hosts<'de, D>(deserializer: D) -> Result<Option<Vec<String>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::de::Error;
+    let value = serde_yaml::Value::deserialize(deserializer)?;
+
+    match value {
+        serde_yaml::Value::Sequence(seq) => seq
+            .into_iter()
+            .map(|item| {
+                let item = item
+                    .as_str()
+                    .ok_or_else(|| Error::custom("extra_hosts list item must be a string"))?;
+                Ok(match item.split_once('=') {
+                    Some((host, ip)) => format!("{}:{}", host, ip),
+                    None => item.to_string(),
+                })
+            })
+            .collect::<Result<_, _>>()
+            .map(Some),
+        serde_yaml::Value::Mapping(map) => map
+            .into_iter()
+            .map(|(host, ip)| match (host.as_str(), ip.as_str()) {
+                (Some(host), Some(ip)) => Ok(format!("{}:{}", host, ip)),
+                _ => Err(Error::custom("extra_hosts entries must be strings")),
+            })
+            .collect::<Result<_, _>>()
+            .map(Some),
+        serde_yaml::Value::Null => Ok(None),
+        _ => Err(Error::custom("extra_hosts must be a sequence or mapping")),
+    }
+}
+
+/// Keeps the `x-*` keys among the fields not otherwise handled by a struct
+fn deserialize_extensions<'de, D>(
+    deserializer: D,
+) -> Result<BTreeMap<String, serde_yaml::Value>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let fields = BTreeMap::<String, serde_yaml::Value>::deserialize(deserializer)?;
+    Ok(fields
+        .into_iter()
+        .filter(|(key, _)| key.starts_with("x-"))
+        .collect())
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BuildConfig {
     pub context: String,
@@ -73,6 +987,21 @@ pub struct ComposeConfig {
     /// Map of volume name to volume configuration
     #[serde(default)]
     pub volumes: HashMap<String, Volume>,
+    /// Map of network name to network configuration
+    #[serde(default, deserialize_with = "deserialize_networks")]
+    pub networks: HashMap<String, NetworkConfig>,
+    /// Map of secret name to its definition
+    ///
+    /// Secrets are mounted read-only under [`SECRETS_DIR`](super::secrets::SECRETS_DIR), rather
+    /// than passed through environment variables.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub secrets: HashMap<String, FileObject>,
+    /// Map of config name to its definition
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub configs: HashMap<String, FileObject>,
+    /// Top-level extension fields (`x-*` keys), such as shared blocks referenced by anchors
+    #[serde(flatten, deserialize_with = "deserialize_extensions")]
+    pub extensions: BTreeMap<String, serde_yaml::Value>,
 }
 
 impl Default for ComposeConfig {
@@ -81,11 +1010,142 @@ impl Default for ComposeConfig {
             version: "3".to_string(),
             services: HashMap::new(),
             volumes: HashMap::new(),
+            networks: HashMap::new(),
+            secrets: HashMap::new(),
+            configs: HashMap::new(),
+            extensions: BTreeMap::new(),
         }
     }
 }
 
 impl ComposeConfig {
+    /// Serializes the configuration as a compose file that `docker compose` accepts
+    ///
+    /// Unlike serializing with serde directly, this:
+    /// - Writes the top-level `volumes` as volume definitions
+    /// - Converts health checks to the compose `healthcheck` syntax
+    /// - Converts the resource limits of `requirements` to the equivalent compose keys, keeping
+    ///   the requirements themselves in an `x-requirements` extension field
+    /// - Leaves out unset fields, and sorts keys so the output is deterministic
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if the configuration can't be serialized, or if a service mounts a
+    /// volume definition ([`Volume::Config`])
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use docktopus::{ComposeConfig, Service};
+    /// # use docktopus::error::DockerError;
+    ///
+    /// # fn main() -> Result<(), DockerError> {
+    /// let mut config = ComposeConfig::default();
+    /// config.services.insert(
+    ///     "web".to_string(),
+    ///     Service {
+    ///         image: Some("nginx".to_string()),
+    ///         ..Service::default()
+    ///     },
+    /// );
+    ///
+    /// let yaml = config.to_compose_yaml()?;
+    /// assert_eq!(yaml, "version: '3'\nservices:\n  web:\n    image: nginx\n");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_compose_yaml(&self) -> Result<String, DockerError> {
+        use serde_yaml::{Mapping, Value};
+
+        for (name, service) in &self.services {
+            if service
+                .volumes
+                .iter()
+                .flatten()
+                .any(|volume| matches!(volume, Volume::Config { .. }))
+            {
+                return Err(DockerError::ValidationError(format!(
+                    "Service '{}' mounts a volume definition, which has no target",
+                    name
+                )));
+            }
+        }
+
+        let Value::Mapping(mut root) = serde_yaml::to_value(self)? else {
+            return Err(DockerError::ValidationError(
+                "Compose config must serialize to a mapping".to_string(),
+            ));
+        };
+
+        let volumes: Mapping = self
+            .volumes
+            .iter()
+            .map(|(name, volume)| (Value::String(name.clone()), volume_definition(volume)))
+            .collect();
+        root.insert("volumes".into(), Value::Mapping(volumes));
+
+        if let Some(Value::Mapping(services)) = root.get_mut("services") {
+            for (name, service) in &self.services {
+                let Some(Value::Mapping(entry)) = services.get_mut(name.as_str()) else {
+                    continue;
+                };
+
+                if let Some(health) = &service.healthcheck {
+                    entry.insert("healthcheck".into(), compose_healthcheck(health));
+                }
+
+                if let Some(requirements) = &service.requirements {
+                    entry.remove("requirements");
+                    let limits = [
+                        ("cpus", requirements.cpu_limit.map(Value::from)),
+                        (
+                            "mem_limit",
+                            requirements.memory_limit.clone().map(Value::from),
+                        ),
+                        (
+                            "memswap_limit",
+                            requirements.memory_swap.clone().map(Value::from),
+                        ),
+                        (
+                            "mem_reservation",
+                            requirements.memory_reservation.clone().map(Value::from),
+                        ),
+                        ("cpu_shares", requirements.cpu_shares.map(Value::from)),
+                        ("cpuset", requirements.cpuset_cpus.clone().map(Value::from)),
+                    ];
+                    // The compose keys take precedence over `requirements` when deploying
+                    for (key, limit) in limits {
+                        if let Some(limit) = limit {
+                            if entry.get(key).is_none_or(Value::is_null) {
+                                entry.insert(key.into(), limit);
+                            }
+                        }
+                    }
+                    entry.insert("x-requirements".into(), serde_yaml::to_value(requirements)?);
+                }
+            }
+        }
+
+        // Keep the conventional order of the top-level sections, and leave out empty ones
+        let mut ordered = Mapping::new();
+        for key in [
+            "version", "services", "networks", "volumes", "secrets", "configs",
+        ] {
+            if let Some(value) = root.remove(key) {
+                let is_empty = value.as_mapping().is_some_and(Mapping::is_empty);
+                if key == "version" || key == "services" || !is_empty {
+                    ordered.insert(key.into(), normalize_yaml(value));
+                }
+            }
+        }
+        let Value::Mapping(rest) = normalize_yaml(Value::Mapping(root)) else {
+            unreachable!("a normalized mapping is a mapping");
+        };
+        ordered.extend(rest);
+
+        Ok(serde_yaml::to_string(&ordered)?)
+    }
+
     /// Validates that required environment variables are present
     ///
     /// # Errors
@@ -146,11 +1206,20 @@ impl ComposeConfig {
         // Build dependency graph
         let mut graph: HashMap<&str, Vec<&str>> = HashMap::new();
         for (service_name, service) in &self.services {
-            let deps = service
-                .depends_on
-                .as_ref()
-                .map(|deps| deps.iter().map(String::as_str).collect())
-                .unwrap_or_default();
+            let deps: Vec<&str> = service.dependency_names().collect();
+            for dep in &deps {
+                let required = service
+                    .depends_on
+                    .as_ref()
+                    .and_then(|depends_on| depends_on.get(dep))
+                    .is_none_or(|dependency| dependency.required);
+                if required && !self.services.contains_key(*dep) {
+                    return Err(DockerError::ValidationError(format!(
+                        "Service '{}' depends on undefined service '{}'",
+                        service_name, dep
+                    )));
+                }
+            }
             graph.insert(service_name, deps);
         }
 
@@ -202,6 +1271,146 @@ impl ComposeConfig {
         Ok(result)
     }
 
+    /// Groups services into deployment levels based on dependencies
+    ///
+    /// Every service in a level only depends on services in earlier levels, so all services
+    /// within a single level can be deployed concurrently. Service names within a level are
+    /// sorted to keep the result deterministic.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error in the presence of circular dependencies
+    pub fn resolve_service_levels(&self) -> Result<Vec<Vec<String>>, DockerError> {
+        let order = self.resolve_service_order()?;
+
+        // Dependencies always precede their dependents in `order`, so a single pass is enough
+        let mut depths: HashMap<&str, usize> = HashMap::new();
+        let mut levels: Vec<Vec<String>> = Vec::new();
+        for service_name in &order {
+            let Some(service) = self.services.get(service_name) else {
+                continue;
+            };
+
+            let depth = service
+                .dependency_names()
+                .filter_map(|dep| depths.get(dep))
+                .map(|depth| depth + 1)
+                .max()
+                .unwrap_or(0);
+            depths.insert(service_name, depth);
+
+            if levels.len() <= depth {
+                levels.resize_with(depth + 1, Vec::new);
+            }
+            levels[depth].push(service_name.clone());
+        }
+
+        for level in &mut levels {
+            level.sort();
+        }
+
+        Ok(levels)
+    }
+
+    /// Selects the services to deploy for the given active profiles and explicitly requested services
+    ///
+    /// Without any requested services, every service enabled by the active profiles is selected,
+    /// see [`Service::is_enabled()`]. Requested services are selected regardless of their
+    /// profiles, and their profiles are activated. In both cases, the dependencies of selected
+    /// services are included transitively.
+    ///
+    /// Like `docker compose`, dependencies are not started implicitly when they belong to an
+    /// inactive profile: this is an error, unless the dependency is declared with
+    /// `required: false`, in which case it is skipped.
+    ///
+    /// # Errors
+    ///
+    /// Will return an error if a requested service is not defined, or if a selected service
+    /// requires a service in an inactive profile
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use docktopus::parser::ComposeParser;
+    /// # use docktopus::error::DockerError;
+    ///
+    /// # fn main() -> Result<(), DockerError> {
+    /// let compose_content = r#"version: "3"
+    /// services:
+    ///     node:
+    ///         image: node
+    ///     explorer:
+    ///         image: explorer
+    ///         profiles: [debug]
+    ///         depends_on: [node]"#;
+    /// let config = ComposeParser::new().parse(&mut compose_content.as_bytes())?;
+    ///
+    /// let selected = config.select_services::<&str>(&[], &[])?;
+    /// assert_eq!(selected.into_iter().collect::<Vec<_>>(), vec!["node"]);
+    ///
+    /// let selected = config.select_services(&["debug"], &[])?;
+    /// assert_eq!(selected.len(), 2);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn select_services<S: AsRef<str>>(
+        &self,
+        active_profiles: &[S],
+        services: &[S],
+    ) -> Result<BTreeSet<String>, DockerError> {
+        let requested: Vec<&str> = services.iter().map(AsRef::as_ref).collect();
+        let mut profiles: Vec<&str> = active_profiles.iter().map(AsRef::as_ref).collect();
+        for name in &requested {
+            let service = self.services.get(*name).ok_or_else(|| {
+                DockerError::ValidationError(format!("Service '{}' is not defined", name))
+            })?;
+            profiles.extend(service.profiles.iter().flatten().map(String::as_str));
+        }
+
+        let mut pending: Vec<&str> = if requested.is_empty() {
+            self.services
+                .iter()
+                .filter(|(_, service)| service.is_enabled(&profiles))
+                .map(|(name, _)| name.as_str())
+                .collect()
+        } else {
+            requested.clone()
+        };
+
+        let mut selected = BTreeSet::new();
+        while let Some(name) = pending.pop() {
+            if !selected.insert(name.to_string()) {
+                continue;
+            }
+            let service = &self.services[name];
+
+            for dep in service.dependency_names() {
+                // Undefined dependencies are reported when resolving the deployment order
+                let Some(dependency) = self.services.get(dep) else {
+                    continue;
+                };
+                if dependency.is_enabled(&profiles) || requested.contains(&dep) {
+                    pending.push(dep);
+                    continue;
+                }
+
+                let required = service
+                    .depends_on
+                    .as_ref()
+                    .and_then(|depends_on| depends_on.get(dep))
+                    .is_none_or(|dependency| dependency.required);
+                if required {
+                    return Err(DockerError::ValidationError(format!(
+                        "Service '{}' depends on service '{}', which is in an inactive profile",
+                        name, dep
+                    )));
+                }
+            }
+        }
+
+        Ok(selected)
+    }
+
     /// Collects all volumes used in services and adds them to the volumes section
     pub fn collect_volumes(&mut self) {
         let mut used_volumes = HashMap::new();
@@ -210,10 +1419,18 @@ impl ComposeConfig {
         for service in self.services.values() {
             if let Some(volumes) = &service.volumes {
                 for volume in volumes {
-                    if let Volume::Named(name) = volume {
-                        let volume_name = name.split(':').next().unwrap_or(name).to_string();
-                        if !self.volumes.contains_key(&volume_name) {
-                            used_volumes.insert(volume_name, volume.clone());
+                    if let Volume::Named { source, .. } = volume {
+                        if !self.volumes.contains_key(source) {
+                            used_volumes.insert(
+                                source.clone(),
+                                Volume::Config {
+                                    name: String::new(),
+                                    driver: None,
+                                    driver_opts: None,
+                                    labels: None,
+                                    external: false,
+                                },
+                            );
                         }
                     }
                 }
@@ -225,33 +1442,47 @@ impl ComposeConfig {
     }
 
     /// Resolves environment variables in the configuration
-    pub fn resolve_env(&mut self, env_vars: &HashMap<String, String>) {
+    ///
+    /// Service environments, volumes and volume definitions are interpolated with `env_vars`,
+    /// following the same rules as [`ComposeParser`](crate::parser::ComposeParser).
+    ///
+    /// # Errors
+    ///
+    /// Returns `DockerError::InterpolationError` if a required variable is missing, or an
+    /// expression is malformed
+    pub fn resolve_env(&mut self, env_vars: &HashMap<String, String>) -> Result<(), DockerError> {
         // Helper function to resolve env vars in a volume
-        fn resolve_volume(volume: &mut Volume, env_vars: &HashMap<String, String>) {
+        fn resolve_volume(
+            volume: &mut Volume,
+            env_vars: &HashMap<String, String>,
+        ) -> Result<(), DockerError> {
             match volume {
-                Volume::Named(name) => {
-                    *name = ComposeConfig::resolve_env_value(name, env_vars);
+                Volume::Named { source, target, .. } | Volume::Bind { source, target, .. } => {
+                    ComposeConfig::resolve_env_value(source, env_vars)?;
+                    ComposeConfig::resolve_env_value(target, env_vars)?;
                 }
-                Volume::Bind { source, target, .. } => {
-                    *source = ComposeConfig::resolve_env_value(source, env_vars);
-                    *target = ComposeConfig::resolve_env_value(target, env_vars);
+                Volume::Anonymous { target, .. } | Volume::Tmpfs { target, .. } => {
+                    ComposeConfig::resolve_env_value(target, env_vars)?;
                 }
                 Volume::Config {
                     name,
                     driver,
                     driver_opts,
+                    labels,
+                    ..
                 } => {
-                    *name = ComposeConfig::resolve_env_value(name, env_vars);
+                    ComposeConfig::resolve_env_value(name, env_vars)?;
                     if let Some(d) = driver {
-                        *d = ComposeConfig::resolve_env_value(d, env_vars);
+                        ComposeConfig::resolve_env_value(d, env_vars)?;
                     }
-                    if let Some(opts) = driver_opts {
+                    for opts in [driver_opts, labels].into_iter().flatten() {
                         for value in opts.values_mut() {
-                            *value = ComposeConfig::resolve_env_value(value, env_vars);
+                            ComposeConfig::resolve_env_value(value, env_vars)?;
                         }
                     }
                 }
             }
+            Ok(())
         }
 
         // Resolve environment variables in services
@@ -259,47 +1490,95 @@ impl ComposeConfig {
             // Resolve service environment
             if let Some(environment) = &mut service.environment {
                 for value in environment.values_mut() {
-                    *value = Self::resolve_env_value(value, env_vars);
+                    Self::resolve_env_value(value, env_vars)?;
                 }
             }
 
             // Resolve service volumes
             if let Some(volumes) = &mut service.volumes {
                 for volume in volumes.iter_mut() {
-                    resolve_volume(volume, env_vars);
+                    resolve_volume(volume, env_vars)?;
                 }
             }
         }
 
         // Resolve environment variables in volume configurations
         for volume in self.volumes.values_mut() {
-            resolve_volume(volume, env_vars);
+            resolve_volume(volume, env_vars)?;
         }
+        Ok(())
     }
 
-    fn resolve_env_value(value: &str, env_vars: &HashMap<String, String>) -> String {
-        let re = Regex::new(r"\$\{([^}]+)}|\$([a-zA-Z_][a-zA-Z0-9_]*)").unwrap();
-        let mut result = value.to_string();
+    fn resolve_env_value(
+        value: &mut String,
+        env_vars: &HashMap<String, String>,
+    ) -> Result<(), DockerError> {
+        *value = crate::parser::env::substitute_env_vars(value, env_vars)?;
+        Ok(())
+    }
+}
 
-        for cap in re.captures_iter(value) {
-            let full_match = cap.get(0).unwrap();
-            let var_name = cap.get(1).or_else(|| cap.get(2)).map_or("", |m| m.as_str());
+/// The top-level definition of a volume, as written in a compose file
+fn volume_definition(volume: &Volume) -> serde_yaml::Value {
+    match volume {
+        Volume::Config { .. } => match serde_yaml::to_value(volume) {
+            Ok(definition @ serde_yaml::Value::Mapping(_)) => definition,
+            _ => serde_yaml::Value::Mapping(serde_yaml::Mapping::new()),
+        },
+        // Volumes only referenced by services have no options
+        _ => serde_yaml::Value::Mapping(serde_yaml::Mapping::new()),
+    }
+}
 
-            let (name, default) = if let Some((n, d)) = var_name.split_once(":-") {
-                (n, Some(d))
-            } else {
-                (var_name, None)
-            };
+/// Converts a [`HealthCheck`] to the compose `healthcheck` syntax
+fn compose_healthcheck(health: &HealthCheck) -> serde_yaml::Value {
+    use super::resources::format_compose_duration;
 
-            let replacement = env_vars
-                .get(name)
-                .cloned()
-                .or_else(|| std::env::var(name).ok())
-                .or_else(|| default.map(ToString::to_string))
-                .unwrap_or_default();
+    let mut healthcheck = serde_yaml::Mapping::new();
+    healthcheck.insert(
+        "test".into(),
+        health
+            .test_command()
+            .into_iter()
+            .map(serde_yaml::Value::from)
+            .collect(),
+    );
+    healthcheck.insert(
+        "interval".into(),
+        format_compose_duration(health.interval).into(),
+    );
+    healthcheck.insert(
+        "timeout".into(),
+        format_compose_duration(health.timeout).into(),
+    );
+    healthcheck.insert("retries".into(), health.retries.into());
+    serde_yaml::Value::Mapping(healthcheck)
+}
 
-            result = result.replace(full_match.as_str(), &replacement);
+/// Removes unset values and sorts mapping keys, recursively
+fn normalize_yaml(value: serde_yaml::Value) -> serde_yaml::Value {
+    use serde_yaml::Value;
+
+    match value {
+        Value::Mapping(mapping) => {
+            let mut entries: Vec<(Value, Value)> = mapping
+                .into_iter()
+                .filter(|(_, value)| !value.is_null())
+                .map(|(key, value)| (key, normalize_yaml(value)))
+                .collect();
+            entries.sort_by_key(|(key, _)| yaml_key(key));
+            Value::Mapping(entries.into_iter().collect())
         }
-        result
+        Value::Sequence(sequence) => sequence.into_iter().map(normalize_yaml).collect(),
+        value => value,
+    }
+}
+
+fn yaml_key(key: &serde_yaml::Value) -> String {
+    match key {
+        serde_yaml::Value::String(s) => s.clone(),
+        serde_yaml::Value::Number(n) => n.to_string(),
+        serde_yaml::Value::Bool(b) => b.to_string(),
+        _ => String::new(),
     }
 }
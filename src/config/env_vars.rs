@@ -1,5 +1,8 @@
+use crate::error::DockerError;
+use crate::parser::env;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::Path;
 
 #[derive(Debug, Default, Clone, Serialize)]
 pub struct EnvironmentVars(HashMap<String, String>);
@@ -21,6 +24,11 @@ impl<'de> Deserialize<'de> for EnvironmentVars {
                         .as_str()
                         .ok_or_else(|| Error::custom("Environment key must be a string"))?
                         .to_string();
+                    // Like a bare `KEY` in a list, a key without a value is not set. The parser
+                    // gives bare keys their value from its variables, leaving only undefined ones.
+                    if value.is_null() {
+                        continue;
+                    }
                     let value = value
                         .as_str()
                         .ok_or_else(|| Error::custom("Environment value must be a string"))?
@@ -73,6 +81,12 @@ impl EnvironmentVars {
     }
 }
 
+impl Extend<(String, String)> for EnvironmentVars {
+    fn extend<T: IntoIterator<Item = (String, String)>>(&mut self, iter: T) {
+        self.0.extend(iter);
+    }
+}
+
 impl From<EnvironmentVars> for HashMap<String, String> {
     fn from(env: EnvironmentVars) -> Self {
         env.0
@@ -102,3 +116,171 @@ impl<'a> IntoIterator for &'a EnvironmentVars {
         self.0.iter()
     }
 }
+
+/// The format of an [`EnvFile`]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EnvFileFormat {
+    /// `KEY=value` lines, with comments and surrounding quotes stripped
+    #[default]
+    Dotenv,
+    /// `KEY=value` lines, with values used verbatim
+    Raw,
+}
+
+/// A single entry in a service's `env_file`
+///
+/// Both the short string form and the long form are accepted:
+///
+/// ```yaml
+/// env_file: ./node.env
+/// ```
+///
+/// ```yaml
+/// env_file:
+///   - ./node.env
+///   - path: ./overrides.env
+///     required: false
+///     format: raw
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvFile {
+    /// Path to the file, relative to the compose file directory
+    pub path: String,
+    /// Whether a missing file is an error, missing optional files are skipped
+    pub required: bool,
+    pub format: EnvFileFormat,
+}
+
+impl EnvFile {
+    /// A required dotenv file at `path`
+    #[must_use]
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            required: true,
+            format: EnvFileFormat::Dotenv,
+        }
+    }
+
+    /// Loads the variables in the file, resolving a relative path against `base_dir`
+    ///
+    /// Returns `None` if the file is optional and does not exist.
+    ///
+    /// # Errors
+    ///
+    /// * The file is required and cannot be read
+    /// * A dotenv file is malformed
+    pub fn load(&self, base_dir: &Path) -> Result<Option<HashMap<String, String>>, DockerError> {
+        let path = base_dir.join(&self.path);
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && !self.required => {
+                return Ok(None);
+            }
+            Err(e) => {
+                return Err(DockerError::ValidationError(format!(
+                    "Failed to read env file {}: {}",
+                    path.display(),
+                    e
+                )));
+            }
+        };
+
+        let vars = match self.format {
+            EnvFileFormat::Dotenv => env::parse_env_file(&content)?,
+            EnvFileFormat::Raw => content
+                .lines()
+                .filter(|line| !line.trim().is_empty() && !line.trim_start().starts_with('#'))
+                .filter_map(|line| line.split_once('='))
+                .map(|(key, value)| (key.trim().to_string(), value.to_string()))
+                .collect(),
+        };
+
+        Ok(Some(vars))
+    }
+}
+
+impl Serialize for EnvFile {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        #[derive(Serialize)]
+        struct LongEnvFile<'a> {
+            path: &'a str,
+            required: bool,
+            format: EnvFileFormat,
+        }
+
+        if self.required && self.format == EnvFileFormat::Dotenv {
+            return serializer.serialize_str(&self.path);
+        }
+
+        LongEnvFile {
+            path: &self.path,
+            required: self.required,
+            format: self.format,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for EnvFile {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct LongEnvFile {
+            path: String,
+            #[serde(
+                default = "default_required",
+                deserialize_with = "super::scalar::deserialize"
+            )]
+            required: bool,
+            #[serde(default)]
+            format: EnvFileFormat,
+        }
+
+        fn default_required() -> bool {
+            true
+        }
+
+        use serde::de::Error;
+        let value = serde_yaml::Value::deserialize(deserializer)?;
+        match value {
+            serde_yaml::Value::String(path) => Ok(EnvFile::new(path)),
+            serde_yaml::Value::Mapping(_) => {
+                let long: LongEnvFile = serde_yaml::from_value(value).map_err(Error::custom)?;
+                Ok(EnvFile {
+                    path: long.path,
+                    required: long.required,
+                    format: long.format,
+                })
+            }
+            _ => Err(Error::custom("env_file entry must be a string or mapping")),
+        }
+    }
+}
+
+/// Deserializes a service's `env_file`, which may be a single path or a list of entries
+pub(crate) fn deserialize_env_files<'de, D>(
+    deserializer: D,
+) -> Result<Option<Vec<EnvFile>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::de::Error;
+    let value = serde_yaml::Value::deserialize(deserializer)?;
+
+    match value {
+        serde_yaml::Value::Null => Ok(None),
+        serde_yaml::Value::Sequence(_) => serde_yaml::from_value(value)
+            .map(Some)
+            .map_err(Error::custom),
+        _ => serde_yaml::from_value(value)
+            .map(|env_file| Some(vec![env_file]))
+            .map_err(Error::custom),
+    }
+}
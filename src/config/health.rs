@@ -36,18 +36,20 @@ impl Display for Method {
 pub struct HealthCheck {
     pub endpoint: String,
     pub method: Method,
+    #[serde(deserialize_with = "super::scalar::deserialize")]
     pub expected_status: u16,
     pub body: Option<String>,
     #[serde(with = "duration_serde")]
     pub interval: Duration,
     #[serde(with = "duration_serde")]
     pub timeout: Duration,
+    #[serde(deserialize_with = "super::scalar::deserialize")]
     pub retries: u32,
 }
 
 // Custom serialization for Duration
 pub(crate) mod duration_serde {
-    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use serde::{Deserializer, Serialize, Serializer};
     use std::time::Duration;
 
     pub fn serialize<S>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error>
@@ -62,13 +64,27 @@ pub(crate) mod duration_serde {
     where
         D: Deserializer<'de>,
     {
-        let nanos = u128::deserialize(deserializer)?;
+        let nanos: u128 = crate::config::scalar::deserialize(deserializer)?;
         let secs = nanos / 1_000_000_000;
         let nanos = (nanos % 1_000_000_000) as u32;
         Ok(Duration::new(secs as u64, nanos))
     }
 }
 
+impl HealthCheck {
+    /// The command run inside the container to check the endpoint, as a `CMD-SHELL` test
+    #[must_use]
+    pub fn test_command(&self) -> Vec<String> {
+        vec![
+            "CMD-SHELL".to_string(),
+            format!(
+                "curl -X {} {} -s -f -o /dev/null -w '%{{http_code}}' | grep -q {}",
+                self.method, self.endpoint, self.expected_status
+            ),
+        ]
+    }
+}
+
 #[cfg(feature = "deploy")]
 impl HealthCheck {
     /// Perform the health check
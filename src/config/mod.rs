@@ -2,12 +2,19 @@ pub mod compose;
 pub mod docker_file;
 pub mod env_vars;
 pub mod health;
+pub mod network;
 pub mod requirements;
+pub mod resources;
+mod scalar;
+pub mod secrets;
 pub mod volume;
 
 pub use compose::*;
 pub use docker_file::*;
 pub use env_vars::*;
 pub use health::*;
+pub use network::*;
 pub use requirements::*;
+pub use resources::*;
+pub use secrets::*;
 pub use volume::*;
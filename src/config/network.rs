@@ -0,0 +1,450 @@
+use super::scalar;
+#[cfg(feature = "deploy")]
+use crate::error::DockerError;
+#[cfg(feature = "deploy")]
+use bollard::network::CreateNetworkOptions;
+#[cfg(feature = "deploy")]
+use bollard::service::{Ipam, IpamConfig};
+#[cfg(feature = "deploy")]
+use ipnet::IpNet;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::{BTreeMap, HashMap};
+#[cfg(feature = "deploy")]
+use std::net::IpAddr;
+
+/// A top-level network definition in a Docker Compose file
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetworkConfig {
+    /// Custom name for the network, used as-is instead of a generated name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub driver: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub driver_opts: Option<HashMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ipam: Option<NetworkIpam>,
+    /// Restrict external access to the network
+    #[serde(default, deserialize_with = "scalar::deserialize")]
+    pub internal: bool,
+    #[serde(default, deserialize_with = "scalar::deserialize")]
+    pub attachable: bool,
+    #[serde(default, deserialize_with = "scalar::deserialize")]
+    pub enable_ipv6: bool,
+    /// The network already exists and is managed outside of this compose file
+    #[serde(default, deserialize_with = "scalar::deserialize")]
+    pub external: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub labels: Option<HashMap<String, String>>,
+}
+
+/// IP address management settings for a network
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetworkIpam {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub driver: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<Vec<NetworkIpamPool>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub options: Option<HashMap<String, String>>,
+}
+
+/// A single address pool in [`NetworkIpam::config`]
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NetworkIpamPool {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subnet: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ip_range: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gateway: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aux_addresses: Option<HashMap<String, String>>,
+}
+
+#[cfg(feature = "deploy")]
+impl NetworkConfig {
+    /// Checks that the IPAM configuration is consistent
+    ///
+    /// # Errors
+    ///
+    /// Returns `DockerError::InvalidIpamConfig` if an address pool is invalid, two pools overlap,
+    /// or an IPv6 pool is used without `enable_ipv6`.
+    pub fn validate(&self) -> Result<(), DockerError> {
+        let pools = self
+            .ipam
+            .iter()
+            .flat_map(|ipam| ipam.config.iter().flatten());
+
+        let mut subnets: Vec<IpNet> = Vec::new();
+        for pool in pools {
+            let Some(subnet) = pool.validate()? else {
+                continue;
+            };
+            if subnet.addr().is_ipv6() && !self.enable_ipv6 {
+                return Err(DockerError::InvalidIpamConfig(format!(
+                    "IPv6 subnet {} requires enable_ipv6",
+                    subnet
+                )));
+            }
+            if let Some(other) = subnets
+                .iter()
+                .find(|other| other.contains(&subnet) || subnet.contains(*other))
+            {
+                return Err(DockerError::InvalidIpamConfig(format!(
+                    "Subnets {} and {} overlap",
+                    other, subnet
+                )));
+            }
+            subnets.push(subnet);
+        }
+
+        Ok(())
+    }
+
+    /// The options creating this network under the name `name`
+    #[must_use]
+    pub fn create_options(&self, name: &str) -> CreateNetworkOptions<String> {
+        CreateNetworkOptions {
+            name: name.to_string(),
+            driver: self.driver.clone().unwrap_or_else(|| "bridge".to_string()),
+            internal: self.internal,
+            attachable: self.attachable,
+            enable_ipv6: self.enable_ipv6,
+            ipam: self.ipam.clone().map(Into::into).unwrap_or_default(),
+            options: self.driver_opts.clone().unwrap_or_default(),
+            labels: self.labels.clone().unwrap_or_default(),
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(feature = "deploy")]
+impl NetworkIpamPool {
+    /// Checks that the ranges and addresses of the pool lie within its subnet
+    ///
+    /// Returns the subnet, if the pool has one.
+    ///
+    /// # Errors
+    ///
+    /// Returns `DockerError::InvalidIpamConfig` if an address or range can't be parsed, lies outside
+    /// of the subnet, or is given without a subnet.
+    pub fn validate(&self) -> Result<Option<IpNet>, DockerError> {
+        let invalid = |kind: &str, value: &str| {
+            DockerError::InvalidIpamConfig(format!("Invalid {} '{}'", kind, value))
+        };
+
+        let Some(subnet) = &self.subnet else {
+            if self.ip_range.is_some()
+                || self.gateway.is_some()
+                || self.aux_addresses.as_ref().is_some_and(|a| !a.is_empty())
+            {
+                return Err(DockerError::InvalidIpamConfig(
+                    "ip_range, gateway and aux_addresses require a subnet".to_string(),
+                ));
+            }
+            return Ok(None);
+        };
+        let subnet: IpNet = subnet.parse().map_err(|_| invalid("subnet", subnet))?;
+        let outside = |kind: &str, value: &str| {
+            DockerError::InvalidIpamConfig(format!(
+                "{} {} is outside of subnet {}",
+                kind, value, subnet
+            ))
+        };
+
+        if let Some(ip_range) = &self.ip_range {
+            let range: IpNet = ip_range
+                .parse()
+                .map_err(|_| invalid("ip_range", ip_range))?;
+            if !subnet.contains(&range) {
+                return Err(outside("ip_range", ip_range));
+            }
+        }
+
+        let addresses = self
+            .gateway
+            .iter()
+            .map(|gateway| ("gateway", gateway))
+            .chain(
+                self.aux_addresses
+                    .iter()
+                    .flat_map(HashMap::values)
+                    .map(|address| ("aux address", address)),
+            );
+        for (kind, address) in addresses {
+            let ip: IpAddr = address.parse().map_err(|_| invalid(kind, address))?;
+            if !subnet.contains(&ip) {
+                return Err(outside(kind, address));
+            }
+        }
+
+        Ok(Some(subnet))
+    }
+}
+
+#[cfg(feature = "deploy")]
+impl From<NetworkIpam> for Ipam {
+    fn from(ipam: NetworkIpam) -> Self {
+        Ipam {
+            driver: ipam.driver,
+            config: ipam.config.map(|pools| {
+                pools
+                    .into_iter()
+                    .map(|pool| IpamConfig {
+                        subnet: pool.subnet,
+                        ip_range: pool.ip_range,
+                        gateway: pool.gateway,
+                        auxiliary_addresses: pool.aux_addresses,
+                    })
+                    .collect()
+            }),
+            options: ipam.options,
+        }
+    }
+}
+
+/// Deserializes the top-level `networks` section, where `my-network:` with no body is allowed
+pub(crate) fn deserialize_networks<'de, D>(
+    deserializer: D,
+) -> Result<HashMap<String, NetworkConfig>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let networks = HashMap::<String, Option<NetworkConfig>>::deserialize(deserializer)?;
+    Ok(networks
+        .into_iter()
+        .map(|(name, config)| (name, config.unwrap_or_default()))
+        .collect())
+}
+
+/// The `network_mode` of a service
+///
+/// Services with a network mode are not attached to any compose networks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NetworkMode {
+    /// Use the host's network stack
+    Host,
+    /// Disable networking entirely
+    None,
+    /// Use Docker's default bridge network
+    Bridge,
+    /// Share the network namespace of another service's container, `service:<name>`
+    Service(String),
+    /// Share the network namespace of an existing container, `container:<name or id>`
+    Container(String),
+    /// Any other mode, passed to Docker unchanged
+    Other(String),
+}
+
+impl NetworkMode {
+    /// The name of the service whose network namespace is shared, if any
+    #[must_use]
+    pub fn service(&self) -> Option<&str> {
+        match self {
+            NetworkMode::Service(name) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+impl std::str::FromStr for NetworkMode {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "host" => NetworkMode::Host,
+            "none" => NetworkMode::None,
+            "bridge" => NetworkMode::Bridge,
+            _ => {
+                if let Some(service) = s.strip_prefix("service:") {
+                    NetworkMode::Service(service.to_string())
+                } else if let Some(container) = s.strip_prefix("container:") {
+                    NetworkMode::Container(container.to_string())
+                } else {
+                    NetworkMode::Other(s.to_string())
+                }
+            }
+        })
+    }
+}
+
+impl std::fmt::Display for NetworkMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NetworkMode::Host => write!(f, "host"),
+            NetworkMode::None => write!(f, "none"),
+            NetworkMode::Bridge => write!(f, "bridge"),
+            NetworkMode::Service(service) => write!(f, "service:{}", service),
+            NetworkMode::Container(container) => write!(f, "container:{}", container),
+            NetworkMode::Other(mode) => write!(f, "{}", mode),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for NetworkMode {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use serde::de::Error;
+        let mode = String::deserialize(deserializer)?;
+        if mode.is_empty() || mode == "service:" || mode == "container:" {
+            return Err(Error::custom(format!("Invalid network_mode: '{}'", mode)));
+        }
+
+        let Ok(mode) = mode.parse();
+        Ok(mode)
+    }
+}
+
+impl Serialize for NetworkMode {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+/// Per-service settings for an attached network
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServiceNetworkConfig {
+    /// Additional hostnames the service is reachable under on this network
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aliases: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ipv4_address: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ipv6_address: Option<String>,
+    /// Networks with a higher priority are connected first
+    #[serde(
+        default,
+        deserialize_with = "scalar::deserialize_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub priority: Option<i32>,
+}
+
+/// The `networks` section of a service
+///
+/// Both the short list form and the long map form are accepted:
+///
+/// ```yaml
+/// networks:
+///   - public
+/// ```
+///
+/// ```yaml
+/// networks:
+///   internal:
+///     aliases: [node]
+///     ipv4_address: 172.20.0.10
+///     priority: 100
+///   public:
+/// ```
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct ServiceNetworks(BTreeMap<String, ServiceNetworkConfig>);
+
+impl<'de> Deserialize<'de> for ServiceNetworks {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use serde::de::Error;
+        let value = serde_yaml::Value::deserialize(deserializer)?;
+
+        let map = match value {
+            serde_yaml::Value::Sequence(seq) => seq
+                .into_iter()
+                .map(|item| {
+                    item.as_str()
+                        .map(|name| (name.to_string(), ServiceNetworkConfig::default()))
+                        .ok_or_else(|| Error::custom("networks list item must be a string"))
+                })
+                .collect::<Result<_, _>>()?,
+            serde_yaml::Value::Mapping(map) => {
+                let mut networks = BTreeMap::new();
+                for (name, config) in map {
+                    let name = name
+                        .as_str()
+                        .ok_or_else(|| Error::custom("network name must be a string"))?
+                        .to_string();
+                    let config = if config.is_null() {
+                        ServiceNetworkConfig::default()
+                    } else {
+                        serde_yaml::from_value(config).map_err(Error::custom)?
+                    };
+                    networks.insert(name, config);
+                }
+                networks
+            }
+            _ => return Err(Error::custom("networks must be a sequence or mapping")),
+        };
+
+        Ok(ServiceNetworks(map))
+    }
+}
+
+impl ServiceNetworks {
+    /// Names of the attached networks
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.0.keys().map(String::as_str)
+    }
+
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<&ServiceNetworkConfig> {
+        self.0.get(name)
+    }
+
+    pub fn insert(&mut self, name: impl Into<String>, config: ServiceNetworkConfig) {
+        self.0.insert(name.into(), config);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &ServiceNetworkConfig)> {
+        self.0.iter()
+    }
+
+    /// The attached networks, ordered by descending priority and then by name
+    #[must_use]
+    pub fn by_priority(&self) -> Vec<(&String, &ServiceNetworkConfig)> {
+        let mut networks: Vec<_> = self.0.iter().collect();
+        networks.sort_by_key(|(_, config)| std::cmp::Reverse(config.priority.unwrap_or(0)));
+        networks
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl<S: Into<String>> FromIterator<S> for ServiceNetworks {
+    fn from_iter<T: IntoIterator<Item = S>>(iter: T) -> Self {
+        ServiceNetworks(
+            iter.into_iter()
+                .map(|name| (name.into(), ServiceNetworkConfig::default()))
+                .collect(),
+        )
+    }
+}
+
+impl From<Vec<String>> for ServiceNetworks {
+    fn from(names: Vec<String>) -> Self {
+        names.into_iter().collect()
+    }
+}
+
+impl<'a> IntoIterator for &'a ServiceNetworks {
+    type Item = (&'a String, &'a ServiceNetworkConfig);
+    type IntoIter = std::collections::btree_map::Iter<'a, String, ServiceNetworkConfig>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
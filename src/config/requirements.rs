@@ -1,3 +1,4 @@
+use super::scalar;
 use crate::error::DockerError;
 use serde::{Deserialize, Serialize};
 use std::path::Path;
@@ -11,17 +12,23 @@ use sysinfo::System;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SystemRequirements {
+    #[serde(deserialize_with = "scalar::deserialize")]
     pub min_memory_gb: u64,
+    #[serde(deserialize_with = "scalar::deserialize")]
     pub min_disk_gb: u64,
+    #[serde(deserialize_with = "scalar::deserialize")]
     pub min_bandwidth_mbps: u64,
+    #[serde(deserialize_with = "scalar::deserialize_vec")]
     pub required_ports: Vec<u16>,
     pub data_directory: String,
     // Resource limit fields
-    pub cpu_limit: Option<f64>,             // Number of CPUs
+    #[serde(default, deserialize_with = "scalar::deserialize_option")]
+    pub cpu_limit: Option<f64>, // Number of CPUs
     pub memory_limit: Option<String>,       // e.g., "1G", "512M"
     pub memory_swap: Option<String>,        // Total memory including swap
     pub memory_reservation: Option<String>, // Soft limit
-    pub cpu_shares: Option<i64>,            // CPU shares (relative weight)
+    #[serde(default, deserialize_with = "scalar::deserialize_option")]
+    pub cpu_shares: Option<i64>, // CPU shares (relative weight)
     pub cpuset_cpus: Option<String>,        // CPUs in which to allow execution (0-3, 0,1)
 }
 
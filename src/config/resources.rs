@@ -0,0 +1,534 @@
+use super::scalar;
+use crate::error::DockerError;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::time::Duration;
+
+#[cfg(feature = "deploy")]
+use bollard::service::{DeviceMapping, DeviceRequest, ResourcesUlimits};
+#[cfg(feature = "deploy")]
+use std::collections::BTreeMap;
+
+/// Parses a compose byte value like `512m`, `1gb` or `2048` into bytes
+///
+/// Units are case insensitive and binary, so `1k` is 1024 bytes.
+///
+/// # Errors
+///
+/// The input is not a valid byte value
+pub fn parse_byte_size(size: &str) -> Result<u64, DockerError> {
+    let size = size.trim();
+    let split = size
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(size.len());
+    let (num, unit) = size.split_at(split);
+    let base = num
+        .parse::<f64>()
+        .map_err(|_| DockerError::InvalidResourceLimit(format!("Invalid byte value: {}", size)))?;
+
+    let multiplier: u64 = match unit.to_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" => 1024,
+        "m" | "mb" => 1024 * 1024,
+        "g" | "gb" => 1024 * 1024 * 1024,
+        _ => {
+            return Err(DockerError::InvalidResourceLimit(format!(
+                "Invalid byte unit: {}",
+                unit
+            )));
+        }
+    };
+
+    #[allow(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        clippy::cast_precision_loss
+    )]
+    Ok((base * multiplier as f64) as u64)
+}
+
+/// Parses a compose duration like `1m30s`, `10s` or `500ms`
+///
+/// Supported units are `us`, `ms`, `s`, `m` and `h`.
+///
+/// # Errors
+///
+/// The input is not a valid duration
+pub fn parse_compose_duration(duration: &str) -> Result<Duration, DockerError> {
+    let invalid = || DockerError::ValidationError(format!("Invalid duration: {}", duration));
+
+    let mut total = Duration::ZERO;
+    let mut rest = duration.trim();
+    if rest.is_empty() {
+        return Err(invalid());
+    }
+
+    while !rest.is_empty() {
+        let split = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .ok_or_else(invalid)?;
+        let (num, tail) = rest.split_at(split);
+        let value = num.parse::<f64>().map_err(|_| invalid())?;
+
+        let unit_len = tail
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_len);
+        let seconds = match unit {
+            "us" => value / 1_000_000.0,
+            "ms" => value / 1_000.0,
+            "s" => value,
+            "m" => value * 60.0,
+            "h" => value * 3600.0,
+            _ => return Err(invalid()),
+        };
+
+        total += Duration::from_secs_f64(seconds);
+        rest = tail;
+    }
+
+    Ok(total)
+}
+
+/// Formats a duration in the compose duration syntax
+pub(crate) fn format_compose_duration(duration: Duration) -> String {
+    if duration.subsec_nanos() == 0 {
+        format!("{}s", duration.as_secs())
+    } else if duration.subsec_nanos() % 1_000_000 == 0 {
+        format!("{}ms", duration.as_millis())
+    } else {
+        format!("{}us", duration.as_micros())
+    }
+}
+
+/// (De)serializes an optional [`Duration`] using the compose duration syntax
+pub(crate) mod compose_duration {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    #[allow(clippy::ref_option)]
+    pub fn serialize<S>(duration: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match duration {
+            Some(duration) => serializer.serialize_str(&super::format_compose_duration(*duration)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use serde::de::Error;
+        match serde_yaml::Value::deserialize(deserializer)? {
+            serde_yaml::Value::Null => Ok(None),
+            serde_yaml::Value::String(s) => super::parse_compose_duration(&s)
+                .map(Some)
+                .map_err(Error::custom),
+            // Plain numbers are seconds
+            serde_yaml::Value::Number(n) => n
+                .as_f64()
+                .filter(|secs| *secs >= 0.0)
+                .map(|secs| Some(Duration::from_secs_f64(secs)))
+                .ok_or_else(|| Error::custom(format!("Invalid duration: {}", n))),
+            _ => Err(Error::custom("duration must be a string or number")),
+        }
+    }
+}
+
+/// A size in bytes, written as a number of bytes or a string like `512m`
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ByteSize(pub u64);
+
+impl ByteSize {
+    /// The size in bytes, saturated to fit Docker's signed fields
+    #[must_use]
+    pub fn as_i64(self) -> i64 {
+        i64::try_from(self.0).unwrap_or(i64::MAX)
+    }
+}
+
+impl std::str::FromStr for ByteSize {
+    type Err = DockerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_byte_size(s).map(ByteSize)
+    }
+}
+
+impl Serialize for ByteSize {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_u64(self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for ByteSize {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use serde::de::Error;
+        match serde_yaml::Value::deserialize(deserializer)? {
+            serde_yaml::Value::Number(n) => n
+                .as_u64()
+                .map(ByteSize)
+                .ok_or_else(|| Error::custom(format!("Invalid byte value: {}", n))),
+            serde_yaml::Value::String(s) => s.parse().map_err(Error::custom),
+            _ => Err(Error::custom("byte value must be a number or string")),
+        }
+    }
+}
+
+/// Deserializes a CPU count, which compose allows as either a number or a string
+pub(crate) fn deserialize_cpus<'de, D>(deserializer: D) -> Result<Option<f64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    use serde::de::Error;
+    match serde_yaml::Value::deserialize(deserializer)? {
+        serde_yaml::Value::Null => Ok(None),
+        serde_yaml::Value::Number(n) => n
+            .as_f64()
+            .map(Some)
+            .ok_or_else(|| Error::custom(format!("Invalid cpus value: {}", n))),
+        serde_yaml::Value::String(s) => s
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| Error::custom(format!("Invalid cpus value: {}", s))),
+        _ => Err(Error::custom("cpus must be a number or string")),
+    }
+}
+
+/// Deserializes a `KEY=VALUE` list or a mapping with scalar values, such as `sysctls`
+pub(crate) fn deserialize_key_values<'de, D>(
+    deserializer: D,
+) -> Result<Option<HashMap<String, String>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    use serde::de::Error;
+    let scalar = |value: serde_yaml::Value| match value {
+        serde_yaml::Value::String(s) => Ok(s),
+        serde_yaml::Value::Number(n) => Ok(n.to_string()),
+        serde_yaml::Value::Bool(b) => Ok(b.to_string()),
+        serde_yaml::Value::Null => Ok(String::new()),
+        _ => Err(Error::custom("value must be a scalar")),
+    };
+
+    match serde_yaml::Value::deserialize(deserializer)? {
+        serde_yaml::Value::Null => Ok(None),
+        serde_yaml::Value::Mapping(map) => map
+            .into_iter()
+            .map(|(key, value)| Ok((scalar(key)?, scalar(value)?)))
+            .collect::<Result<_, _>>()
+            .map(Some),
+        serde_yaml::Value::Sequence(seq) => seq
+            .into_iter()
+            .map(|item| {
+                let item = scalar(item)?;
+                let (key, value) = item.split_once('=').unwrap_or((&item, ""));
+                Ok((key.trim().to_string(), value.trim().to_string()))
+            })
+            .collect::<Result<_, _>>()
+            .map(Some),
+        _ => Err(Error::custom("expected a mapping or sequence")),
+    }
+}
+
+/// The `deploy` section of a service
+///
+/// Only `resources` is applied, the remaining keys are specific to swarm deployments.
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeployConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resources: Option<Resources>,
+}
+
+/// The `deploy.resources` section of a service
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Resources {
+    /// Hard limits the container may not exceed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limits: Option<ResourceLimits>,
+    /// Resources guaranteed to the container
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reservations: Option<ResourceReservations>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResourceLimits {
+    #[serde(
+        default,
+        deserialize_with = "deserialize_cpus",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub cpus: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<ByteSize>,
+    #[serde(
+        default,
+        deserialize_with = "scalar::deserialize_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub pids: Option<i64>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResourceReservations {
+    #[serde(
+        default,
+        deserialize_with = "deserialize_cpus",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub cpus: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub memory: Option<ByteSize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub devices: Option<Vec<DeviceReservation>>,
+}
+
+/// A device reservation, such as a GPU, in `deploy.resources.reservations.devices`
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeviceReservation {
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub driver: Option<String>,
+    /// Either a number of devices or `all`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub count: Option<DeviceCount>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_ids: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub options: Option<HashMap<String, String>>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DeviceCount {
+    Count(i64),
+    All,
+}
+
+impl Serialize for DeviceCount {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            DeviceCount::Count(count) => serializer.serialize_i64(*count),
+            DeviceCount::All => serializer.serialize_str("all"),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for DeviceCount {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use serde::de::Error;
+        match serde_yaml::Value::deserialize(deserializer)? {
+            serde_yaml::Value::Number(n) => n
+                .as_i64()
+                .map(DeviceCount::Count)
+                .ok_or_else(|| Error::custom(format!("Invalid device count: {}", n))),
+            serde_yaml::Value::String(s) if s == "all" => Ok(DeviceCount::All),
+            serde_yaml::Value::String(s) => s
+                .trim()
+                .parse()
+                .map(DeviceCount::Count)
+                .map_err(|_| Error::custom(format!("Invalid device count: {}", s))),
+            value => Err(Error::custom(format!("Invalid device count: {:?}", value))),
+        }
+    }
+}
+
+#[cfg(feature = "deploy")]
+impl From<DeviceReservation> for DeviceRequest {
+    fn from(device: DeviceReservation) -> Self {
+        DeviceRequest {
+            driver: device.driver,
+            count: device.count.map(|count| match count {
+                DeviceCount::Count(count) => count,
+                // Docker uses -1 for all available devices
+                DeviceCount::All => -1,
+            }),
+            device_ids: device.device_ids,
+            capabilities: Some(vec![device.capabilities]),
+            options: device.options,
+        }
+    }
+}
+
+/// A single entry in a service's `ulimits`
+///
+/// Written either as a single value used for both limits, or as `{ soft, hard }`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum Ulimit {
+    Single(#[serde(deserialize_with = "scalar::deserialize")] i64),
+    SoftHard {
+        #[serde(deserialize_with = "scalar::deserialize")]
+        soft: i64,
+        #[serde(deserialize_with = "scalar::deserialize")]
+        hard: i64,
+    },
+}
+
+impl Ulimit {
+    #[must_use]
+    pub fn soft(&self) -> i64 {
+        match self {
+            Ulimit::Single(limit) | Ulimit::SoftHard { soft: limit, .. } => *limit,
+        }
+    }
+
+    #[must_use]
+    pub fn hard(&self) -> i64 {
+        match self {
+            Ulimit::Single(limit) | Ulimit::SoftHard { hard: limit, .. } => *limit,
+        }
+    }
+}
+
+/// Converts a service's `ulimits` into the format expected by Docker
+#[cfg(feature = "deploy")]
+#[must_use]
+pub fn ulimits_to_docker(ulimits: &BTreeMap<String, Ulimit>) -> Vec<ResourcesUlimits> {
+    ulimits
+        .iter()
+        .map(|(name, limit)| ResourcesUlimits {
+            name: Some(name.clone()),
+            soft: Some(limit.soft()),
+            hard: Some(limit.hard()),
+        })
+        .collect()
+}
+
+/// A host device mapped into the container, `HOST_PATH[:CONTAINER_PATH[:PERMISSIONS]]`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Device {
+    pub host_path: String,
+    pub container_path: String,
+    /// cgroup permissions, a combination of `r`, `w` and `m`
+    pub permissions: String,
+}
+
+impl std::str::FromStr for Device {
+    type Err = DockerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(':');
+        let host_path = parts
+            .next()
+            .filter(|path| !path.is_empty())
+            .ok_or_else(|| DockerError::ValidationError(format!("Invalid device: {}", s)))?;
+        let container_path = parts.next().unwrap_or(host_path);
+        let permissions = parts.next().unwrap_or("rwm");
+
+        if parts.next().is_some()
+            || permissions.is_empty()
+            || !permissions.chars().all(|c| matches!(c, 'r' | 'w' | 'm'))
+        {
+            return Err(DockerError::ValidationError(format!(
+                "Invalid device: {}",
+                s
+            )));
+        }
+
+        Ok(Device {
+            host_path: host_path.to_string(),
+            container_path: container_path.to_string(),
+            permissions: permissions.to_string(),
+        })
+    }
+}
+
+impl std::fmt::Display for Device {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}",
+            self.host_path, self.container_path, self.permissions
+        )
+    }
+}
+
+impl Serialize for Device {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Device {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use serde::de::Error;
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(Error::custom)
+    }
+}
+
+#[cfg(feature = "deploy")]
+impl From<Device> for DeviceMapping {
+    fn from(device: Device) -> Self {
+        DeviceMapping {
+            path_on_host: Some(device.host_path),
+            path_in_container: Some(device.container_path),
+            cgroup_permissions: Some(device.permissions),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_byte_size, parse_compose_duration};
+    use std::time::Duration;
+
+    #[test]
+    fn test_byte_size_parsing() {
+        assert_eq!(parse_byte_size("2048").unwrap(), 2048);
+        assert_eq!(parse_byte_size("100b").unwrap(), 100);
+        assert_eq!(parse_byte_size("64k").unwrap(), 64 * 1024);
+        assert_eq!(parse_byte_size("512m").unwrap(), 512 * 1024 * 1024);
+        assert_eq!(parse_byte_size("1GB").unwrap(), 1024 * 1024 * 1024);
+        assert_eq!(parse_byte_size("1.5g").unwrap(), 3 * 512 * 1024 * 1024);
+        assert!(parse_byte_size("1x").is_err());
+        assert!(parse_byte_size("m").is_err());
+    }
+
+    #[test]
+    fn test_compose_duration_parsing() {
+        assert_eq!(
+            parse_compose_duration("10s").unwrap(),
+            Duration::from_secs(10)
+        );
+        assert_eq!(
+            parse_compose_duration("1m30s").unwrap(),
+            Duration::from_secs(90)
+        );
+        assert_eq!(
+            parse_compose_duration("1h5m").unwrap(),
+            Duration::from_secs(3900)
+        );
+        assert_eq!(
+            parse_compose_duration("500ms").unwrap(),
+            Duration::from_millis(500)
+        );
+        assert!(parse_compose_duration("").is_err());
+        assert!(parse_compose_duration("10").is_err());
+        assert!(parse_compose_duration("10d").is_err());
+    }
+}
@@ -0,0 +1,59 @@
+//! Deserializers for numbers and booleans that are also accepted as strings
+//!
+//! Interpolated values are always strings, so `cpu_shares: ${SHARES}` has to be read back as a
+//! number.
+
+use serde::de::{DeserializeOwned, Error};
+use serde::{Deserialize, Deserializer};
+use serde_yaml::Value;
+use std::str::FromStr;
+
+fn from_value<T, E>(value: Value) -> Result<T, E>
+where
+    T: DeserializeOwned + FromStr,
+    E: Error,
+{
+    match value {
+        Value::String(s) => s.trim().parse().map_err(|_| {
+            E::custom(format!(
+                "Invalid value '{}', expected {}",
+                s,
+                std::any::type_name::<T>()
+            ))
+        }),
+        value => serde_yaml::from_value(value).map_err(E::custom),
+    }
+}
+
+/// Deserializes a number or boolean, or a string holding one
+pub(crate) fn deserialize<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned + FromStr,
+{
+    from_value(Value::deserialize(deserializer)?)
+}
+
+/// Like [`deserialize()`], with `null` as `None`
+pub(crate) fn deserialize_option<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned + FromStr,
+{
+    match Value::deserialize(deserializer)? {
+        Value::Null => Ok(None),
+        value => from_value(value).map(Some),
+    }
+}
+
+/// Like [`deserialize()`], for every item of a sequence
+pub(crate) fn deserialize_vec<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: DeserializeOwned + FromStr,
+{
+    Vec::<Value>::deserialize(deserializer)?
+        .into_iter()
+        .map(from_value)
+        .collect()
+}
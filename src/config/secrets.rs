@@ -0,0 +1,311 @@
+use super::scalar;
+use crate::error::DockerError;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::path::Path;
+
+/// The directory secrets are mounted in when their target is not an absolute path
+pub const SECRETS_DIR: &str = "/run/secrets";
+
+/// The directory configs are mounted in when their target is not an absolute path
+pub const CONFIGS_DIR: &str = "/";
+
+/// The label of a deployed container holding the host directory its secrets and configs are
+/// mounted from, removed along with the container by [`Container::remove()`]
+///
+/// [`Container::remove()`]: crate::container::Container::remove
+pub const FILES_LABEL: &str = "com.docktopus.files";
+
+/// The permissions of a mounted secret or config when no `mode` is given
+pub const DEFAULT_FILE_MODE: u32 = 0o444;
+
+/// A top-level `secrets` or `configs` entry, defining where the file's content comes from
+///
+/// Exactly one of `file`, `environment` or `content` is expected. External objects are
+/// managed by a swarm, and can't be used by a standalone deployment.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileObject {
+    /// Path of a file on the host, relative to the base directory of the deployment
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// Name of a host environment variable holding the content
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<String>,
+    /// The content itself
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+    /// Whether the object is managed outside of the compose file
+    #[serde(
+        default,
+        deserialize_with = "scalar::deserialize",
+        skip_serializing_if = "std::ops::Not::not"
+    )]
+    pub external: bool,
+    /// Name of the object, for external objects
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+impl FileObject {
+    /// Reads the content of the object
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - Name of the object, used in error messages
+    /// * `base_dir` - Directory that relative `file` paths are resolved against
+    ///
+    /// # Errors
+    ///
+    /// * The object is external
+    /// * The file can't be read, or the environment variable is not set
+    /// * No source is defined
+    pub fn load(&self, name: &str, base_dir: &Path) -> Result<Vec<u8>, DockerError> {
+        if self.external {
+            return Err(DockerError::ValidationError(format!(
+                "'{}' is external, which is only supported in swarm mode",
+                name
+            )));
+        }
+
+        match (&self.file, &self.environment, &self.content) {
+            (Some(file), None, None) => {
+                let path = base_dir.join(file);
+                std::fs::read(&path).map_err(|e| {
+                    DockerError::ValidationError(format!(
+                        "Failed to read '{}' from {}: {}",
+                        name,
+                        path.display(),
+                        e
+                    ))
+                })
+            }
+            (None, Some(var), None) => std::env::var(var).map(String::into_bytes).map_err(|_| {
+                DockerError::ValidationError(format!(
+                    "Environment variable {} for '{}' is not set",
+                    var, name
+                ))
+            }),
+            (None, None, Some(content)) => Ok(content.clone().into_bytes()),
+            _ => Err(DockerError::ValidationError(format!(
+                "'{}' must define exactly one of file, environment or content",
+                name
+            ))),
+        }
+    }
+}
+
+/// A service's reference to a secret or config
+///
+/// Supports both the short syntax (the name of the object) and the long syntax with
+/// `source`, `target`, `uid`, `gid` and `mode`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileObjectReference {
+    /// Name of the top-level secret or config
+    pub source: String,
+    /// Path in the container, relative paths are placed in [`SECRETS_DIR`] or [`CONFIGS_DIR`]
+    pub target: Option<String>,
+    /// Owner of the mounted file
+    pub uid: Option<String>,
+    /// Group of the mounted file
+    pub gid: Option<String>,
+    /// Permissions of the mounted file, defaults to [`DEFAULT_FILE_MODE`]
+    pub mode: Option<u32>,
+}
+
+impl FileObjectReference {
+    /// A reference to `source`, mounted with the default target and permissions
+    #[must_use]
+    pub fn new(source: impl Into<String>) -> Self {
+        Self {
+            source: source.into(),
+            target: None,
+            uid: None,
+            gid: None,
+            mode: None,
+        }
+    }
+
+    /// The absolute path of the file in the container
+    ///
+    /// # Arguments
+    ///
+    /// * `default_dir` - Directory for relative targets, such as [`SECRETS_DIR`]
+    #[must_use]
+    pub fn target_path(&self, default_dir: &str) -> String {
+        let target = self.target.as_deref().unwrap_or(&self.source);
+        if target.starts_with('/') {
+            target.to_string()
+        } else {
+            format!("{}/{}", default_dir.trim_end_matches('/'), target)
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct LongFileObjectReference {
+    source: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    target: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    uid: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    gid: Option<String>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_mode",
+        serialize_with = "serialize_mode",
+        skip_serializing_if = "Option::is_none"
+    )]
+    mode: Option<u32>,
+}
+
+impl<'de> Deserialize<'de> for FileObjectReference {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        use serde::de::Error;
+        let value = serde_yaml::Value::deserialize(deserializer)?;
+
+        match value {
+            serde_yaml::Value::String(source) => Ok(FileObjectReference::new(source)),
+            serde_yaml::Value::Mapping(_) => {
+                let long: LongFileObjectReference =
+                    serde_yaml::from_value(value).map_err(Error::custom)?;
+                Ok(FileObjectReference {
+                    source: long.source,
+                    target: long.target,
+                    uid: long.uid,
+                    gid: long.gid,
+                    mode: long.mode,
+                })
+            }
+            _ => Err(Error::custom(
+                "secret or config reference must be a string or mapping",
+            )),
+        }
+    }
+}
+
+impl Serialize for FileObjectReference {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if self == &FileObjectReference::new(self.source.clone()) {
+            return serializer.serialize_str(&self.source);
+        }
+
+        LongFileObjectReference {
+            source: self.source.clone(),
+            target: self.target.clone(),
+            uid: self.uid.clone(),
+            gid: self.gid.clone(),
+            mode: self.mode,
+        }
+        .serialize(serializer)
+    }
+}
+
+/// Deserializes a file mode, where strings such as `"0440"` are octal
+pub(crate) fn deserialize_mode<'de, D>(deserializer: D) -> Result<Option<u32>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    use serde::de::Error;
+    let value = serde_yaml::Value::deserialize(deserializer)?;
+
+    match value {
+        serde_yaml::Value::Number(n) => n
+            .as_u64()
+            .and_then(|n| u32::try_from(n).ok())
+            .map(Some)
+            .ok_or_else(|| Error::custom(format!("Invalid file mode: {}", n))),
+        serde_yaml::Value::String(s) => {
+            let digits = s.trim_start_matches("0o");
+            u32::from_str_radix(digits, 8)
+                .map(Some)
+                .map_err(|_| Error::custom(format!("Invalid file mode: {}", s)))
+        }
+        serde_yaml::Value::Null => Ok(None),
+        _ => Err(Error::custom("file mode must be a number or string")),
+    }
+}
+
+#[allow(clippy::ref_option, clippy::trivially_copy_pass_by_ref)]
+pub(crate) fn serialize_mode<S>(mode: &Option<u32>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match mode {
+        Some(mode) => serializer.serialize_str(&format!("0{:o}", mode)),
+        None => serializer.serialize_none(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_object_load() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("key"), "secret").unwrap();
+
+        let file = FileObject {
+            file: Some("key".to_string()),
+            ..FileObject::default()
+        };
+        assert_eq!(file.load("key", dir.path()).unwrap(), b"secret");
+
+        let content = FileObject {
+            content: Some("inline".to_string()),
+            ..FileObject::default()
+        };
+        assert_eq!(content.load("inline", dir.path()).unwrap(), b"inline");
+
+        let missing = FileObject {
+            file: Some("missing".to_string()),
+            ..FileObject::default()
+        };
+        assert!(missing.load("missing", dir.path()).is_err());
+
+        let external = FileObject {
+            external: true,
+            ..FileObject::default()
+        };
+        assert!(external.load("external", dir.path()).is_err());
+        assert!(FileObject::default().load("empty", dir.path()).is_err());
+    }
+
+    #[test]
+    fn test_file_object_reference() {
+        let reference: FileObjectReference = serde_yaml::from_str("validator_key").unwrap();
+        assert_eq!(reference, FileObjectReference::new("validator_key"));
+        assert_eq!(
+            reference.target_path(SECRETS_DIR),
+            "/run/secrets/validator_key"
+        );
+
+        let reference: FileObjectReference = serde_yaml::from_str(
+            "{ source: validator_key, target: keys/validator, uid: '1000', mode: 0440 }",
+        )
+        .unwrap();
+        assert_eq!(reference.mode, Some(0o440));
+        assert_eq!(reference.uid.as_deref(), Some("1000"));
+        assert_eq!(
+            reference.target_path(SECRETS_DIR),
+            "/run/secrets/keys/validator"
+        );
+
+        let reference: FileObjectReference =
+            serde_yaml::from_str("{ source: config, target: /etc/app.toml, mode: 256 }").unwrap();
+        assert_eq!(reference.mode, Some(0o400));
+        assert_eq!(reference.target_path(CONFIGS_DIR), "/etc/app.toml");
+
+        let serialized = serde_yaml::to_string(&reference).unwrap();
+        let reparsed: FileObjectReference = serde_yaml::from_str(&serialized).unwrap();
+        assert_eq!(reparsed, reference);
+
+        assert!(serde_yaml::from_str::<FileObjectReference>("{ source: a, mode: rw }").is_err());
+    }
+}
@@ -1,123 +1,609 @@
+use super::resources::ByteSize;
+use super::scalar;
+use super::secrets::{deserialize_mode, serialize_mode};
 #[cfg(feature = "deploy")]
-use bollard::service::{Mount, MountTypeEnum};
+use crate::error::DockerError;
+#[cfg(feature = "deploy")]
+use bollard::service::{
+    Mount, MountBindOptions, MountBindOptionsPropagationEnum, MountTmpfsOptions, MountTypeEnum,
+    MountVolumeOptions,
+};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+/// A volume, either mounted into a service or defined in the top-level `volumes` section
+///
+/// Service volumes accept both the short syntax (`[SOURCE:]TARGET[:MODE]`) and the long syntax
+/// with `type`, `source`, `target` and the per-type options.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Volume {
-    Named(String),
+    /// A named volume mounted at `target`
+    Named {
+        source: String,
+        target: String,
+        read_only: bool,
+        options: VolumeOptions,
+    },
+    /// A volume created for the container, without a name
+    Anonymous {
+        target: String,
+        read_only: bool,
+        options: VolumeOptions,
+    },
+    /// A host path mounted at `target`
     Bind {
         source: String,
         target: String,
         read_only: bool,
+        options: BindOptions,
+    },
+    /// An in-memory filesystem mounted at `target`
+    Tmpfs {
+        target: String,
+        options: TmpfsOptions,
     },
+    /// A top-level volume definition
     Config {
+        /// Custom name of the Docker volume, the key in the `volumes` section is used when empty
         name: String,
         driver: Option<String>,
         driver_opts: Option<HashMap<String, String>>,
+        labels: Option<HashMap<String, String>>,
+        /// The volume already exists and is managed outside of this compose file
+        external: bool,
     },
 }
 
-// For top-level volume definitions
-#[derive(Debug, Deserialize)]
-#[serde(untagged)]
-enum VolumeSpec {
-    Empty,
-    Full {
-        driver: Option<String>,
-        driver_opts: Option<HashMap<String, String>>,
-    },
+/// Options of named and anonymous volume mounts
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct VolumeOptions {
+    /// Don't copy the data at the target into a new volume
+    pub nocopy: bool,
+    /// Mount a subdirectory of the volume instead of its root
+    pub subpath: Option<String>,
+    /// Consistency requirements of the mount, such as `cached`
+    pub consistency: Option<String>,
 }
 
-impl<'de> Deserialize<'de> for Volume {
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
-    where
-        D: serde::Deserializer<'de>,
-    {
-        #[derive(Deserialize)]
-        #[serde(untagged)]
-        enum VolumeInput {
-            String(String),
-            Long {
-                source: String,
-                target: String,
-                #[serde(rename = "type")]
-                typ: Option<String>,
-                #[serde(default)]
-                read_only: bool,
+/// Options of bind mounts
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct BindOptions {
+    pub propagation: Option<BindPropagation>,
+    /// Create the host path if it doesn't exist
+    pub create_host_path: Option<bool>,
+    /// Relabel the host path for `SELinux`
+    ///
+    /// Mounts can't express this, so the deployer passes these bind mounts as binds instead.
+    pub selinux: Option<SelinuxRelabel>,
+    /// Consistency requirements of the mount, such as `cached`
+    pub consistency: Option<String>,
+}
+
+/// Options of tmpfs mounts
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TmpfsOptions {
+    pub size: Option<ByteSize>,
+    /// Permissions of the mount
+    pub mode: Option<u32>,
+}
+
+/// Mount propagation of a bind mount
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BindPropagation {
+    Private,
+    Rprivate,
+    Shared,
+    Rshared,
+    Slave,
+    Rslave,
+}
+
+impl BindPropagation {
+    fn parse(mode: &str) -> Option<Self> {
+        serde_yaml::from_value(serde_yaml::Value::String(mode.to_string())).ok()
+    }
+}
+
+#[cfg(feature = "deploy")]
+impl From<BindPropagation> for MountBindOptionsPropagationEnum {
+    fn from(propagation: BindPropagation) -> Self {
+        match propagation {
+            BindPropagation::Private => MountBindOptionsPropagationEnum::PRIVATE,
+            BindPropagation::Rprivate => MountBindOptionsPropagationEnum::RPRIVATE,
+            BindPropagation::Shared => MountBindOptionsPropagationEnum::SHARED,
+            BindPropagation::Rshared => MountBindOptionsPropagationEnum::RSHARED,
+            BindPropagation::Slave => MountBindOptionsPropagationEnum::SLAVE,
+            BindPropagation::Rslave => MountBindOptionsPropagationEnum::RSLAVE,
+        }
+    }
+}
+
+/// `SELinux` relabeling of a bind mount
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SelinuxRelabel {
+    /// The content is shared between containers (`z`)
+    #[serde(rename = "z")]
+    Shared,
+    /// The content is private to the container (`Z`)
+    #[serde(rename = "Z")]
+    Private,
+}
+
+impl SelinuxRelabel {
+    /// The flag of the relabeling in a bind specification
+    #[must_use]
+    pub fn flag(self) -> &'static str {
+        match self {
+            SelinuxRelabel::Shared => "z",
+            SelinuxRelabel::Private => "Z",
+        }
+    }
+}
+
+impl Volume {
+    /// A named volume `source` mounted at `target`
+    #[must_use]
+    pub fn named(source: impl Into<String>, target: impl Into<String>) -> Self {
+        Volume::Named {
+            source: source.into(),
+            target: target.into(),
+            read_only: false,
+            options: VolumeOptions::default(),
+        }
+    }
+
+    /// An anonymous volume mounted at `target`
+    #[must_use]
+    pub fn anonymous(target: impl Into<String>) -> Self {
+        Volume::Anonymous {
+            target: target.into(),
+            read_only: false,
+            options: VolumeOptions::default(),
+        }
+    }
+
+    /// The host path `source` bind-mounted at `target`
+    #[must_use]
+    pub fn bind(source: impl Into<String>, target: impl Into<String>) -> Self {
+        Volume::Bind {
+            source: source.into(),
+            target: target.into(),
+            read_only: false,
+            options: BindOptions::default(),
+        }
+    }
+
+    /// A tmpfs mounted at `target`
+    #[must_use]
+    pub fn tmpfs(target: impl Into<String>) -> Self {
+        Volume::Tmpfs {
+            target: target.into(),
+            options: TmpfsOptions::default(),
+        }
+    }
+
+    /// Make the mount read-only, this has no effect on tmpfs mounts and definitions
+    #[must_use]
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        match &mut self {
+            Volume::Named { read_only: ro, .. }
+            | Volume::Anonymous { read_only: ro, .. }
+            | Volume::Bind { read_only: ro, .. } => *ro = read_only,
+            Volume::Tmpfs { .. } | Volume::Config { .. } => {}
+        }
+        self
+    }
+
+    /// The path the volume is mounted at in the container, if it is a mount
+    #[must_use]
+    pub fn target(&self) -> Option<&str> {
+        match self {
+            Volume::Named { target, .. }
+            | Volume::Anonymous { target, .. }
+            | Volume::Bind { target, .. }
+            | Volume::Tmpfs { target, .. } => Some(target),
+            Volume::Config { .. } => None,
+        }
+    }
+
+    #[must_use]
+    pub fn matches_name(&self, name: &str) -> bool {
+        match self {
+            Volume::Named { source, .. } => source == name,
+            Volume::Config {
+                name: volume_name, ..
+            } => volume_name == name,
+            _ => self.target() == Some(name),
+        }
+    }
+
+    /// Parses the short syntax, `[SOURCE:]TARGET[:MODE]`
+    fn parse_short(spec: &str) -> Result<Self, String> {
+        let parts: Vec<&str> = spec.split(':').collect();
+        let (source, target, mode) = match parts.as_slice() {
+            [target] => (None, *target, None),
+            // A single path followed by a mode is an anonymous volume
+            [target, mode] if !mode.starts_with('/') => (None, *target, Some(*mode)),
+            [source, target] => (Some(*source), *target, None),
+            [source, target, mode] => (Some(*source), *target, Some(*mode)),
+            _ => return Err(format!("Invalid volume: {}", spec)),
+        };
+        if target.is_empty() {
+            return Err(format!("Invalid volume, missing target: {}", spec));
+        }
+
+        let mut volume = match source {
+            Some(source) if is_bind_source(source) => Volume::bind(expand_home(source), target),
+            Some(source) => Volume::named(source, target),
+            None => Volume::anonymous(target),
+        };
+
+        for flag in mode.into_iter().flat_map(|mode| mode.split(',')) {
+            volume
+                .apply_mode_flag(flag)
+                .map_err(|e| format!("{}: {}", e, spec))?;
+        }
+
+        Ok(volume)
+    }
+
+    fn apply_mode_flag(&mut self, flag: &str) -> Result<(), String> {
+        const CONSISTENCY: [&str; 4] = ["consistent", "cached", "delegated", "default"];
+
+        match self {
+            volume if flag == "ro" || flag == "rw" => {
+                *volume = volume.clone().read_only(flag == "ro");
+            }
+            Volume::Named { options, .. } | Volume::Anonymous { options, .. } => match flag {
+                "nocopy" => options.nocopy = true,
+                flag if CONSISTENCY.contains(&flag) => {
+                    options.consistency = Some(flag.to_string());
+                }
+                _ => return Err(format!("Invalid volume mode '{}'", flag)),
             },
-            TopLevel(VolumeSpec),
-        }
-
-        let input = VolumeInput::deserialize(deserializer)?;
-        match input {
-            VolumeInput::String(s) => {
-                let parts: Vec<&str> = s.split(':').collect();
-                match parts.len() {
-                    2 => {
-                        if parts[0].starts_with('/') || parts[0].starts_with("./") {
-                            Ok(Volume::Bind {
-                                source: parts[0].to_string(),
-                                target: parts[1].to_string(),
-                                read_only: false,
-                            })
-                        } else {
-                            Ok(Volume::Named(s.to_string()))
-                        }
-                    }
-                    3 if parts[2] == "ro" => {
-                        if parts[0].starts_with('/') || parts[0].starts_with("./") {
-                            Ok(Volume::Bind {
-                                source: parts[0].to_string(),
-                                target: parts[1].to_string(),
-                                read_only: true,
-                            })
-                        } else {
-                            Ok(Volume::Named(s.to_string()))
-                        }
-                    }
-                    _ => Ok(Volume::Named(s.to_string())),
+            Volume::Bind { options, .. } => match flag {
+                "z" => options.selinux = Some(SelinuxRelabel::Shared),
+                "Z" => options.selinux = Some(SelinuxRelabel::Private),
+                flag if CONSISTENCY.contains(&flag) => {
+                    options.consistency = Some(flag.to_string());
+                }
+                flag => {
+                    options.propagation = Some(
+                        BindPropagation::parse(flag)
+                            .ok_or_else(|| format!("Invalid bind mount mode '{}'", flag))?,
+                    );
                 }
+            },
+            Volume::Tmpfs { .. } | Volume::Config { .. } => {
+                return Err(format!("Invalid volume mode '{}'", flag));
             }
-            VolumeInput::Long {
+        }
+        Ok(())
+    }
+
+    /// The short syntax of the volume, if it can be written with it
+    fn to_short(&self) -> Option<String> {
+        let (source, target, read_only) = match self {
+            Volume::Named {
                 source,
                 target,
-                typ,
                 read_only,
-            } => match typ.as_deref() {
-                Some("bind") => Ok(Volume::Bind {
+                options,
+            } if *options == VolumeOptions::default() => (Some(source), target, *read_only),
+            Volume::Anonymous {
+                target,
+                read_only,
+                options,
+            } if *options == VolumeOptions::default() => (None, target, *read_only),
+            Volume::Bind {
+                source,
+                target,
+                read_only,
+                options,
+            } if *options == BindOptions::default() => (Some(source), target, *read_only),
+            _ => return None,
+        };
+
+        let mut short = match source {
+            Some(source) => format!("{}:{}", source, target),
+            None => target.clone(),
+        };
+        if read_only {
+            short.push_str(":ro");
+        }
+        Some(short)
+    }
+}
+
+/// Whether the source of a short syntax volume is a host path rather than a volume name
+///
+/// Like compose, any source starting with `.` or `~`, or containing a path separator, is a path.
+pub(crate) fn is_bind_source(source: &str) -> bool {
+    source.starts_with(['.', '~']) || source.contains(['/', '\\'])
+}
+
+/// Replaces a leading `~` in a host path with the user's home directory
+///
+/// Paths are left unchanged when the home directory is unknown, or when they refer to another
+/// user's home (`~user/...`).
+#[must_use]
+pub fn expand_home(path: &str) -> String {
+    let Some(rest) = path.strip_prefix('~') else {
+        return path.to_string();
+    };
+    if !(rest.is_empty() || rest.starts_with(['/', '\\'])) {
+        return path.to_string();
+    }
+
+    match std::env::var("HOME").or_else(|_| std::env::var("USERPROFILE")) {
+        Ok(home) if !home.is_empty() => format!("{}{}", home.trim_end_matches('/'), rest),
+        _ => path.to_string(),
+    }
+}
+
+/// The long syntax of a service volume
+#[derive(Default, Serialize, Deserialize)]
+struct LongVolume {
+    #[serde(rename = "type", default, skip_serializing_if = "Option::is_none")]
+    typ: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source: Option<String>,
+    target: String,
+    #[serde(
+        default,
+        deserialize_with = "scalar::deserialize",
+        skip_serializing_if = "std::ops::Not::not"
+    )]
+    read_only: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    consistency: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    volume: Option<LongVolumeOptions>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    bind: Option<LongBindOptions>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tmpfs: Option<LongTmpfsOptions>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LongVolumeOptions {
+    #[serde(
+        default,
+        deserialize_with = "scalar::deserialize",
+        skip_serializing_if = "std::ops::Not::not"
+    )]
+    nocopy: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    subpath: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LongBindOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    propagation: Option<BindPropagation>,
+    #[serde(
+        default,
+        deserialize_with = "scalar::deserialize_option",
+        skip_serializing_if = "Option::is_none"
+    )]
+    create_host_path: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    selinux: Option<SelinuxRelabel>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LongTmpfsOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    size: Option<ByteSize>,
+    #[serde(
+        default,
+        deserialize_with = "deserialize_mode",
+        serialize_with = "serialize_mode",
+        skip_serializing_if = "Option::is_none"
+    )]
+    mode: Option<u32>,
+}
+
+impl TryFrom<LongVolume> for Volume {
+    type Error = String;
+
+    fn try_from(long: LongVolume) -> Result<Self, Self::Error> {
+        let volume_options = |long: &LongVolume| VolumeOptions {
+            nocopy: long.volume.as_ref().is_some_and(|v| v.nocopy),
+            subpath: long.volume.as_ref().and_then(|v| v.subpath.clone()),
+            consistency: long.consistency.clone(),
+        };
+
+        match long.typ.as_deref() {
+            Some("volume") | None => {
+                let options = volume_options(&long);
+                Ok(match long.source {
+                    Some(source) if !source.is_empty() => Volume::Named {
+                        source,
+                        target: long.target,
+                        read_only: long.read_only,
+                        options,
+                    },
+                    _ => Volume::Anonymous {
+                        target: long.target,
+                        read_only: long.read_only,
+                        options,
+                    },
+                })
+            }
+            Some("bind") => {
+                let source = long
+                    .source
+                    .map(|source| expand_home(&source))
+                    .ok_or_else(|| format!("Bind mount at {} has no source", long.target))?;
+                let bind = long.bind;
+                Ok(Volume::Bind {
                     source,
-                    target,
-                    read_only,
-                }),
-                Some("volume") | None => {
-                    let name = if read_only {
-                        format!("{}:{}:ro", source, target)
-                    } else {
-                        format!("{}:{}", source, target)
-                    };
-                    Ok(Volume::Named(name))
-                }
-                Some(t) => Err(serde::de::Error::custom(format!(
-                    "Invalid volume type: {}",
-                    t
-                ))),
+                    target: long.target,
+                    read_only: long.read_only,
+                    options: BindOptions {
+                        propagation: bind.as_ref().and_then(|b| b.propagation),
+                        create_host_path: bind.as_ref().and_then(|b| b.create_host_path),
+                        selinux: bind.as_ref().and_then(|b| b.selinux),
+                        consistency: long.consistency,
+                    },
+                })
+            }
+            Some("tmpfs") => Ok(Volume::Tmpfs {
+                target: long.target,
+                options: TmpfsOptions {
+                    size: long.tmpfs.as_ref().and_then(|t| t.size),
+                    mode: long.tmpfs.as_ref().and_then(|t| t.mode),
+                },
+            }),
+            Some(t) => Err(format!("Invalid volume type: {}", t)),
+        }
+    }
+}
+
+impl TryFrom<&Volume> for LongVolume {
+    type Error = String;
+
+    fn try_from(volume: &Volume) -> Result<Self, Self::Error> {
+        let volume_options = |options: &VolumeOptions| {
+            (options.nocopy || options.subpath.is_some()).then(|| LongVolumeOptions {
+                nocopy: options.nocopy,
+                subpath: options.subpath.clone(),
+            })
+        };
+
+        let long = match volume {
+            Volume::Named {
+                source,
+                target,
+                read_only,
+                options,
+            } => LongVolume {
+                typ: Some("volume".to_string()),
+                source: Some(source.clone()),
+                target: target.clone(),
+                read_only: *read_only,
+                consistency: options.consistency.clone(),
+                volume: volume_options(options),
+                ..LongVolume::default()
             },
-            VolumeInput::TopLevel(spec) => match spec {
-                VolumeSpec::Empty => Ok(Volume::Config {
-                    name: String::new(),
-                    driver: None,
-                    driver_opts: None,
-                }),
-                VolumeSpec::Full {
-                    driver,
-                    driver_opts,
-                } => Ok(Volume::Config {
-                    name: String::new(),
-                    driver,
-                    driver_opts,
+            Volume::Anonymous {
+                target,
+                read_only,
+                options,
+            } => LongVolume {
+                typ: Some("volume".to_string()),
+                target: target.clone(),
+                read_only: *read_only,
+                consistency: options.consistency.clone(),
+                volume: volume_options(options),
+                ..LongVolume::default()
+            },
+            Volume::Bind {
+                source,
+                target,
+                read_only,
+                options,
+            } => LongVolume {
+                typ: Some("bind".to_string()),
+                source: Some(source.clone()),
+                target: target.clone(),
+                read_only: *read_only,
+                consistency: options.consistency.clone(),
+                bind: (options.propagation.is_some()
+                    || options.create_host_path.is_some()
+                    || options.selinux.is_some())
+                .then_some(LongBindOptions {
+                    propagation: options.propagation,
+                    create_host_path: options.create_host_path,
+                    selinux: options.selinux,
                 }),
+                ..LongVolume::default()
+            },
+            Volume::Tmpfs { target, options } => LongVolume {
+                typ: Some("tmpfs".to_string()),
+                target: target.clone(),
+                tmpfs: (options.size.is_some() || options.mode.is_some()).then_some(
+                    LongTmpfsOptions {
+                        size: options.size,
+                        mode: options.mode,
+                    },
+                ),
+                ..LongVolume::default()
             },
+            Volume::Config { .. } => {
+                return Err("A volume definition can't be mounted into a service".to_string());
+            }
+        };
+        Ok(long)
+    }
+}
+
+/// The keys of the long syntax of service volumes
+const LONG_SYNTAX_KEYS: [&str; 8] = [
+    "type",
+    "source",
+    "target",
+    "read_only",
+    "consistency",
+    "bind",
+    "volume",
+    "tmpfs",
+];
+
+// For top-level volume definitions
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct VolumeSpec {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    driver: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    driver_opts: Option<HashMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    labels: Option<HashMap<String, String>>,
+    #[serde(
+        default,
+        deserialize_with = "scalar::deserialize",
+        skip_serializing_if = "std::ops::Not::not"
+    )]
+    external: bool,
+}
+
+impl<'de> Deserialize<'de> for Volume {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+        let value = serde_yaml::Value::deserialize(deserializer)?;
+
+        match value {
+            serde_yaml::Value::String(spec) => Volume::parse_short(&spec).map_err(Error::custom),
+            // Service volumes use the long syntax keys, and must have a target. Top-level
+            // definitions have none of them.
+            serde_yaml::Value::Mapping(ref map)
+                if LONG_SYNTAX_KEYS.iter().any(|key| map.contains_key(*key)) =>
+            {
+                let long: LongVolume = serde_yaml::from_value(value).map_err(Error::custom)?;
+                Volume::try_from(long).map_err(Error::custom)
+            }
+            serde_yaml::Value::Mapping(_) | serde_yaml::Value::Null => {
+                let spec: VolumeSpec = if value.is_null() {
+                    VolumeSpec::default()
+                } else {
+                    serde_yaml::from_value(value).map_err(Error::custom)?
+                };
+                Ok(Volume::Config {
+                    name: spec.name.unwrap_or_default(),
+                    driver: spec.driver,
+                    driver_opts: spec.driver_opts,
+                    labels: spec.labels,
+                    external: spec.external,
+                })
+            }
+            _ => Err(Error::custom("volume must be a string or mapping")),
         }
     }
 }
@@ -128,90 +614,138 @@ impl Serialize for Volume {
         S: serde::Serializer,
     {
         match self {
-            Volume::Named(name) => serializer.serialize_str(name),
-            Volume::Bind {
-                source,
-                target,
-                read_only,
-            } => {
-                if *read_only {
-                    serializer.serialize_str(&format!("{}:{}:ro", source, target))
-                } else {
-                    serializer.serialize_str(&format!("{}:{}", source, target))
-                }
-            }
             Volume::Config {
+                name,
                 driver,
                 driver_opts,
-                ..
-            } => match (driver, driver_opts) {
-                (None, None) => serializer.serialize_none(),
-                (Some(driver), Some(opts)) => {
-                    use serde::ser::SerializeMap;
-                    let mut map = serializer.serialize_map(Some(2))?;
-                    map.serialize_entry("driver", driver)?;
-                    map.serialize_entry("driver_opts", opts)?;
-                    map.end()
-                }
-                (Some(driver), None) => {
-                    use serde::ser::SerializeMap;
-                    let mut map = serializer.serialize_map(Some(1))?;
-                    map.serialize_entry("driver", driver)?;
-                    map.end()
-                }
-                (None, Some(opts)) => {
-                    use serde::ser::SerializeMap;
-                    let mut map = serializer.serialize_map(Some(1))?;
-                    map.serialize_entry("driver_opts", opts)?;
-                    map.end()
+                labels,
+                external,
+            } => {
+                let spec = VolumeSpec {
+                    name: (!name.is_empty()).then(|| name.clone()),
+                    driver: driver.clone(),
+                    driver_opts: driver_opts.clone(),
+                    labels: labels.clone(),
+                    external: *external,
+                };
+                if spec.name.is_none()
+                    && spec.driver.is_none()
+                    && spec.driver_opts.is_none()
+                    && spec.labels.is_none()
+                    && !spec.external
+                {
+                    return serializer.serialize_none();
                 }
+                spec.serialize(serializer)
+            }
+            volume => match volume.to_short() {
+                Some(short) => serializer.serialize_str(&short),
+                None => LongVolume::try_from(volume)
+                    .map_err(serde::ser::Error::custom)?
+                    .serialize(serializer),
             },
         }
     }
 }
 
+/// Deserializes a service's `volumes`, which can't mount a volume definition
+pub(crate) fn deserialize_service_volumes<'de, D>(
+    deserializer: D,
+) -> Result<Option<Vec<Volume>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::de::Error;
+    let volumes = Option::<Vec<Volume>>::deserialize(deserializer)?;
+
+    if volumes
+        .iter()
+        .flatten()
+        .any(|volume| matches!(volume, Volume::Config { .. }))
+    {
+        return Err(Error::custom(
+            "service volume must be a string or have a target",
+        ));
+    }
+    Ok(volumes)
+}
+
 #[cfg(feature = "deploy")]
-impl From<Volume> for Mount {
-    fn from(volume_type: Volume) -> Self {
-        match volume_type {
-            Volume::Named(name) => {
-                let parts: Vec<&str> = name.split(':').collect();
-                Mount {
-                    target: Some(parts[1].to_string()),
-                    source: Some(parts[0].to_string()),
-                    typ: Some(MountTypeEnum::VOLUME),
-                    ..Default::default()
-                }
-            }
+impl TryFrom<Volume> for Mount {
+    type Error = DockerError;
+
+    fn try_from(volume_type: Volume) -> Result<Self, Self::Error> {
+        let volume_options = |options: VolumeOptions| {
+            (options.nocopy || options.subpath.is_some()).then(|| MountVolumeOptions {
+                no_copy: options.nocopy.then_some(true),
+                subpath: options.subpath,
+                ..Default::default()
+            })
+        };
+
+        let mount = match volume_type {
+            Volume::Named {
+                source,
+                target,
+                read_only,
+                options,
+            } => Mount {
+                target: Some(target),
+                source: Some(source),
+                typ: Some(MountTypeEnum::VOLUME),
+                read_only: Some(read_only),
+                consistency: options.consistency.clone(),
+                volume_options: volume_options(options),
+                ..Default::default()
+            },
+            Volume::Anonymous {
+                target,
+                read_only,
+                options,
+            } => Mount {
+                target: Some(target),
+                typ: Some(MountTypeEnum::VOLUME),
+                read_only: Some(read_only),
+                consistency: options.consistency.clone(),
+                volume_options: volume_options(options),
+                ..Default::default()
+            },
             Volume::Bind {
                 source,
                 target,
                 read_only,
+                options,
             } => Mount {
                 target: Some(target),
                 source: Some(source),
                 typ: Some(MountTypeEnum::BIND),
                 read_only: Some(read_only),
+                consistency: options.consistency,
+                bind_options: (options.propagation.is_some() || options.create_host_path.is_some())
+                    .then(|| MountBindOptions {
+                        propagation: options.propagation.map(Into::into),
+                        create_mountpoint: options.create_host_path,
+                        ..Default::default()
+                    }),
                 ..Default::default()
             },
-            Volume::Config { name, .. } => Mount {
-                source: Some(name),
-                typ: Some(MountTypeEnum::VOLUME),
+            Volume::Tmpfs { target, options } => Mount {
+                target: Some(target),
+                typ: Some(MountTypeEnum::TMPFS),
+                tmpfs_options: Some(MountTmpfsOptions {
+                    size_bytes: options.size.map(ByteSize::as_i64),
+                    mode: options.mode.map(i64::from),
+                    ..Default::default()
+                }),
                 ..Default::default()
             },
-        }
-    }
-}
-
-impl Volume {
-    #[must_use]
-    pub fn matches_name(&self, name: &str) -> bool {
-        match self {
-            Volume::Named(volume_name) => volume_name.split(':').next().unwrap_or("") == name,
-            Volume::Bind { target, .. } => target == name,
-            Volume::Config {
-                name: volume_name, ..
-            } => volume_name == name,
-        }
+            Volume::Config { name, .. } => {
+                return Err(DockerError::ValidationError(format!(
+                    "Volume definition '{}' can't be mounted into a service",
+                    name
+                )));
+            }
+        };
+        Ok(mount)
     }
 }
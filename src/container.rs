@@ -1,5 +1,6 @@
 //! Utilities for spinning up and managing Docker containers
 
+use crate::config::secrets::FILES_LABEL;
 use bollard::Docker;
 use bollard::container::{
     Config, CreateContainerOptions, InspectContainerOptions, ListContainersOptions,
@@ -368,8 +369,9 @@ impl Container {
     /// # Examples
     ///
     /// ```rust,no_run
+    /// use docktopus::DockerBuilder;
     /// use docktopus::bollard::models::{PortBinding, PortMap};
-    /// use docktopus::{Container, Runtime};
+    /// use docktopus::container::Container;
     /// use std::collections::HashMap;
     ///
     /// # #[tokio::main]
@@ -384,11 +386,9 @@ impl Container {
     ///     }]),
     /// );
     ///
-    /// let runtime = Runtime::detect().await.expect("No runtime found")?;
-    /// let mut container = Container::builder()
-    ///     .image("rustlang/rust")
-    ///     .port_bindings(bindings)
-    ///     .build(runtime);
+    /// let connection = DockerBuilder::new().await?;
+    /// let mut container =
+    ///     Container::new(connection.client(), "rustlang/rust").port_bindings(bindings);
     ///
     /// // We can now start our container
     /// container.start(true).await?;
@@ -405,16 +405,15 @@ impl Container {
     /// # Examples
     ///
     /// ```rust,no_run
-    /// use docktopus::{Container, Runtime};
+    /// use docktopus::DockerBuilder;
+    /// use docktopus::container::Container;
     ///
     /// # #[tokio::main]
     /// # async fn main() -> Result<(), docktopus::container::Error> {
-    /// let runtime = Runtime::detect().await.expect("No runtime found")?;
-    /// let mut container = Container::builder()
-    ///     .image("rustlang/rust")
+    /// let connection = DockerBuilder::new().await?;
+    /// let mut container = Container::new(connection.client(), "rustlang/rust")
     ///     // Use the Sysbox runtime
-    ///     .runtime("sysbox-runc")
-    ///     .build(runtime);
+    ///     .runtime("sysbox-runc");
     ///
     /// // We can now start our container
     /// container.start(true).await?;
@@ -431,20 +430,19 @@ impl Container {
     /// # Examples
     ///
     /// ```rust,no_run
+    /// use docktopus::DockerBuilder;
     /// use docktopus::bollard::models::{RestartPolicy, RestartPolicyNameEnum};
-    /// use docktopus::{Container, Runtime};
+    /// use docktopus::container::Container;
     ///
     /// # #[tokio::main]
     /// # async fn main() -> Result<(), docktopus::container::Error> {
-    /// let runtime = Runtime::detect().await.expect("No runtime found")?;
-    /// let mut container = Container::builder()
-    ///     .image("rustlang/rust")
+    /// let connection = DockerBuilder::new().await?;
+    /// let mut container = Container::new(connection.client(), "rustlang/rust")
     ///     // Always restart the container, unless stopped manually
     ///     .restart_policy(RestartPolicy {
     ///         name: Some(RestartPolicyNameEnum::UNLESS_STOPPED),
     ///         ..Default::default()
-    ///     })
-    ///     .build(runtime);
+    ///     });
     ///
     /// // We can now start our container
     /// container.start(true).await?;
@@ -927,6 +925,9 @@ impl Container {
     /// NOTE: To remove a running container, a [`RemoveContainerOptions`] must be provided
     ///       with the `force` flag set.
     ///
+    /// The host directory holding the secrets and configs of a container deployed from a
+    /// compose file is removed as well, see [`FILES_LABEL`].
+    ///
     /// See also: [`bollard::container::RemoveContainerOptions`]
     ///
     /// # Examples
@@ -965,7 +966,21 @@ impl Container {
             return Ok(());
         };
 
+        // The secrets and configs of compose services live as long as their container
+        let files = self
+            .client
+            .inspect_container(&id, None)
+            .await
+            .ok()
+            .and_then(|container| container.config?.labels?.remove(FILES_LABEL));
+
         self.client.remove_container(&id, options).await?;
+
+        if let Some(files) = files {
+            if let Err(e) = std::fs::remove_dir_all(&files) {
+                log::warn!("Failed to remove {}: {}", files, e);
+            }
+        }
         Ok(())
     }
 
@@ -1,3 +1,5 @@
+#[cfg(feature = "deploy")]
+use crate::builder::compose::RollbackReport;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -12,16 +14,51 @@ pub enum DockerError {
     #[error("Docker API error: {0}")]
     BollardError(#[from] bollard::errors::Error),
     #[cfg(feature = "deploy")]
-    #[error("Invalid IPAM configuration")]
-    InvalidIpamConfig,
+    #[error("Invalid IPAM configuration: {0}")]
+    InvalidIpamConfig(String),
     #[cfg(feature = "deploy")]
     #[error("Container {0} is not running")]
     ContainerNotRunning(String),
     #[cfg(feature = "deploy")]
     #[error("Network creation failed: {0}")]
     NetworkCreationError(String),
+    #[cfg(feature = "deploy")]
+    #[error("Volume creation failed: {0}")]
+    VolumeCreationError(String),
     #[error("Invalid resource limit: {0}")]
     InvalidResourceLimit(String),
     #[error("Validation error: {0}")]
     ValidationError(String),
+    #[error("Invalid interpolation of {variable} at {location}: {message}")]
+    InterpolationError {
+        /// The variable, or the malformed expression
+        variable: String,
+        /// `line L, column C` in the interpolated text, or the key path of a compose value,
+        /// such as `services.app.image`
+        location: String,
+        message: String,
+    },
+    #[error("Invalid env file at line {line}: {message}")]
+    EnvFileError { line: usize, message: String },
+    #[error("Unsupported compose keys: {}", .0.join(", "))]
+    UnsupportedKeys(Vec<String>),
+    #[cfg(feature = "deploy")]
+    #[error("{0}")]
+    DeploymentFailed(Box<DeploymentFailure>),
+}
+
+/// Details of a failed compose deployment
+#[cfg(feature = "deploy")]
+#[derive(Debug, Error)]
+#[error(
+    "Compose deployment failed{}: {source} ({rollback})",
+    service.as_ref().map(|s| format!(" at service '{s}'")).unwrap_or_default()
+)]
+pub struct DeploymentFailure {
+    /// The service that was being deployed, if the failure happened while deploying one
+    pub service: Option<String>,
+    /// The underlying error
+    pub source: DockerError,
+    /// What was cleaned up (or left behind) after the failure
+    pub rollback: RollbackReport,
 }
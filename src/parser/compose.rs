@@ -1,12 +1,20 @@
+mod environment;
+mod loader;
+mod merge;
+mod schema;
 #[cfg(test)]
 mod tests;
 
-use super::env;
+pub use environment::{EnvOrigin, EnvVar};
+pub use schema::ComposeWarning;
+
 use crate::{config::compose::ComposeConfig, error::DockerError};
-use std::collections::HashMap;
-use std::fs::File;
+use environment::EnvSources;
+use loader::{Loader, UnsetVariable};
+use merge::merge_compose;
+use std::collections::{BTreeMap, HashMap};
 use std::io::Read;
-use std::path::{Path, PathBuf};
+use std::path::Path;
 
 /// Parser for Docker Compose configuration files with environment variable support
 ///
@@ -14,7 +22,35 @@ use std::path::{Path, PathBuf};
 /// - YAML parsing of Docker Compose files
 /// - Environment variable substitution with default values
 /// - Loading and parsing of .env files
+/// - YAML merge keys (`<<: *anchor`), commonly used with `x-` extension blocks
 /// - Path normalization
+/// - `include` and `extends`, and merging of override files, see [`ComposeParser::parse_from_paths()`]
+/// - Detection of unsupported keys, see [`ComposeParser::strict()`]
+///
+/// # Environment sources
+///
+/// Variables used for interpolation are collected from these sources, from the lowest to the
+/// highest precedence:
+///
+/// 1. The environment of the current process
+/// 2. The maps added with [`ComposeParser::env_vars()`], in the order they were added
+/// 3. The files added with [`ComposeParser::env_file()`], in the order they were added
+/// 4. The environment of the current process again, with [`ComposeParser::process_env()`]
+/// 5. The file set with [`ComposeParser::override_env_file()`]
+///
+/// See [`ComposeParser::env_report()`] for where each variable came from. To follow
+/// `docker compose`, where the shell takes precedence over the `.env` file:
+///
+/// ```rust,no_run
+/// use docktopus::parser::ComposeParser;
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let config = ComposeParser::new()
+///     .env_file(".env")
+///     .process_env()
+///     .parse_from_path("docker-compose.yml")?;
+/// # Ok(()) }
+/// ```
 ///
 /// # Examples
 ///
@@ -42,8 +78,8 @@ use std::path::{Path, PathBuf};
 /// ```
 #[derive(Default, Clone)]
 pub struct ComposeParser {
-    env_file_path: Option<PathBuf>,
-    env_vars: Option<HashMap<String, String>>,
+    env_sources: EnvSources,
+    strict: bool,
 }
 
 impl ComposeParser {
@@ -68,29 +104,161 @@ impl ComposeParser {
     /// # Ok(()) }
     /// ```
     pub fn parse_from_path<P: AsRef<Path>>(self, path: P) -> Result<ComposeConfig, DockerError> {
-        let mut f = File::open(path)?;
-        self.parse(&mut f)
+        self.parse_from_paths([path])
+    }
+
+    /// Parses multiple Docker Compose files, merging each one on top of the previous ones
+    ///
+    /// This is the equivalent of passing several `-f` flags to `docker compose`, such as a base
+    /// `docker-compose.yml` followed by a `docker-compose.override.yml`. Files are merged following
+    /// the compose specification:
+    ///
+    /// * Mappings are merged recursively, with values from later files taking precedence
+    /// * `environment`, `labels`, `depends_on` and `networks` are merged by key
+    /// * `volumes` are merged by container path
+    /// * `command` and `entrypoint` are replaced
+    /// * Other sequences, such as `ports`, are combined
+    ///
+    /// `include` and `extends` are resolved relative to the file that contains them.
+    ///
+    /// # Arguments
+    ///
+    /// * `paths` - Paths to the Docker Compose files, in the order they should be applied
+    ///
+    /// # Errors
+    ///
+    /// * No paths are given, or one of them does not exist
+    /// * An `include` or `extends` refers to a missing file or service, or forms a cycle
+    /// * An included resource conflicts with an existing one
+    /// * See [`ComposeParser::parse()`]
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use docktopus::parser::ComposeParser;
+    ///
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// let config = ComposeParser::new()
+    ///     .parse_from_paths(["docker-compose.yml", "docker-compose.prod.yml"])?;
+    /// # Ok(()) }
+    /// ```
+    pub fn parse_from_paths<I, P>(self, paths: I) -> Result<ComposeConfig, DockerError>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let (config, warnings) = self.parse_from_paths_with_warnings(paths)?;
+        log_warnings(warnings);
+        Ok(config)
     }
 
     /// Parses a Docker Compose file from the given reader
     ///
+    /// Unsupported keys are logged as warnings, or rejected in [strict mode](ComposeParser::strict()).
+    ///
     /// # Errors
     ///
     /// * The file is malformed
-    /// * Required environment variables are missing, see [`ComposeParser::env_file()`] and [`ComposeParser::env_vars()`]
+    /// * Required environment variables are missing, see [`ComposeParser::env_file()`] and [`ComposeParser::env_vars()`].
+    ///   Variables are required when a service's `environment` references them without a
+    ///   default, or when they use the `${VAR:?error}` syntax.
+    /// * The file contains unsupported keys and strict mode is enabled
     pub fn parse<R>(self, reader: &mut R) -> Result<ComposeConfig, DockerError>
     where
         R: Read,
     {
-        let mut env_vars = self.env_vars.unwrap_or_default();
+        let (config, warnings) = self.parse_with_warnings(reader)?;
+        log_warnings(warnings);
+        Ok(config)
+    }
+
+    /// Parses a Docker Compose file from the given path, returning any warnings alongside the config
+    ///
+    /// # Errors
+    ///
+    /// * `path` does not exist
+    /// * See [`ComposeParser::parse_with_warnings()`]
+    pub fn parse_from_path_with_warnings<P: AsRef<Path>>(
+        self,
+        path: P,
+    ) -> Result<(ComposeConfig, Vec<ComposeWarning>), DockerError> {
+        self.parse_from_paths_with_warnings([path])
+    }
 
-        if let Some(env_file_path) = self.env_file_path {
-            let env_content = std::fs::read_to_string(env_file_path).map_err(|e| {
-                DockerError::ValidationError(format!("Failed to read env file: {}", e))
-            })?;
+    /// Parses and merges multiple Docker Compose files, returning any warnings alongside the config
+    ///
+    /// # Errors
+    ///
+    /// See [`ComposeParser::parse_from_paths()`]
+    pub fn parse_from_paths_with_warnings<I, P>(
+        self,
+        paths: I,
+    ) -> Result<(ComposeConfig, Vec<ComposeWarning>), DockerError>
+    where
+        I: IntoIterator<Item = P>,
+        P: AsRef<Path>,
+    {
+        let env_vars = self.load_env_vars()?;
+        let mut loader = Loader::new(&env_vars);
 
-            env_vars.extend(env::parse_env_file(&env_content));
+        let mut value: Option<serde_yaml::Value> = None;
+        for path in paths {
+            let file = loader.load_file(path.as_ref())?;
+            value = Some(match value {
+                Some(base) => merge_compose(base, file),
+                None => file,
+            });
         }
+        let value = value.ok_or_else(|| {
+            DockerError::ValidationError("No compose files were given".to_string())
+        })?;
+
+        self.finish(value, loader.unset_variables(), &env_vars)
+    }
+
+    /// Parses a Docker Compose file from the given reader, returning any warnings alongside the config
+    ///
+    /// # Errors
+    ///
+    /// See [`ComposeParser::parse()`]
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use docktopus::parser::{ComposeParser, ComposeWarning};
+    /// # use docktopus::error::DockerError;
+    ///
+    /// # fn main() -> Result<(), DockerError> {
+    /// let compose_content = r#"version: "3"
+    /// services:
+    ///     app:
+    ///         image: nginx
+    ///         scale: 2"#;
+    ///
+    /// let (config, warnings) =
+    ///     ComposeParser::new().parse_with_warnings(&mut compose_content.as_bytes())?;
+    /// assert!(config.services.contains_key("app"));
+    /// assert_eq!(
+    ///     warnings,
+    ///     vec![ComposeWarning::UnsupportedKey("services.app.scale".to_string())]
+    /// );
+    ///
+    /// // Strict mode rejects the file instead
+    /// let result = ComposeParser::new()
+    ///     .strict(true)
+    ///     .parse(&mut compose_content.as_bytes());
+    /// assert!(matches!(result, Err(DockerError::UnsupportedKeys(_))));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn parse_with_warnings<R>(
+        self,
+        reader: &mut R,
+    ) -> Result<(ComposeConfig, Vec<ComposeWarning>), DockerError>
+    where
+        R: Read,
+    {
+        let env_vars = self.load_env_vars()?;
 
         let mut config_bytes = Vec::new();
         reader.read_to_end(&mut config_bytes)?;
@@ -98,14 +266,88 @@ impl ComposeParser {
         let compose = String::from_utf8(config_bytes).map_err(|e| {
             DockerError::ValidationError(format!("Failed to read compose file: {}", e))
         })?;
-        let processed_content = env::substitute_env_vars(&compose, &env_vars);
 
-        let config: ComposeConfig =
-            serde_yaml::from_str(&processed_content).map_err(DockerError::YamlError)?;
+        // Without a file path, references to other files are relative to the working directory
+        let mut loader = Loader::new(&env_vars);
+        let value = loader.load_str(&compose, &std::env::current_dir()?)?;
 
-        validate_required_env_vars(&config, &env_vars)?;
+        self.finish(value, loader.unset_variables(), &env_vars)
+    }
 
-        Ok(config)
+    /// Collects the variables used for substitution from every source
+    fn load_env_vars(&self) -> Result<HashMap<String, String>, DockerError> {
+        Ok(self
+            .env_report()?
+            .into_iter()
+            .map(|(key, var)| (key, var.value))
+            .collect())
+    }
+
+    /// Resolves the variables used for interpolation, along with the source each one came from
+    ///
+    /// # Errors
+    ///
+    /// An env file can't be read or is malformed
+    ///
+    /// # Examples
+    ///
+    /// ```rust
+    /// use docktopus::parser::{ComposeParser, EnvOrigin};
+    /// use std::collections::HashMap;
+    /// use std::fs::write;
+    /// use tempfile::NamedTempFile;
+    /// # use docktopus::error::DockerError;
+    ///
+    /// # fn main() -> Result<(), DockerError> {
+    /// let env_file = NamedTempFile::new()?;
+    /// write(env_file.path(), b"VERSION=1.21\nPORT=8080")?;
+    ///
+    /// let report = ComposeParser::new()
+    ///     .env_file(env_file.path())
+    ///     .env_vars(HashMap::from([
+    ///         ("PORT".to_string(), "9090".to_string()),
+    ///         ("DEBUG".to_string(), "true".to_string()),
+    ///     ]))
+    ///     .env_report()?;
+    ///
+    /// // The env file takes precedence over the map
+    /// assert_eq!(report["PORT"].value, "8080");
+    /// assert_eq!(
+    ///     report["PORT"].origin,
+    ///     EnvOrigin::File(env_file.path().to_path_buf())
+    /// );
+    /// assert_eq!(report["DEBUG"].value, "true");
+    /// assert_eq!(report["DEBUG"].origin, EnvOrigin::Vars(0));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn env_report(&self) -> Result<BTreeMap<String, EnvVar>, DockerError> {
+        environment::resolve(&self.env_sources)
+    }
+
+    /// Checks and deserializes a fully loaded compose file
+    fn finish(
+        &self,
+        mut value: serde_yaml::Value,
+        unset: &[UnsetVariable],
+        env_vars: &HashMap<String, String>,
+    ) -> Result<(ComposeConfig, Vec<ComposeWarning>), DockerError> {
+        validate_required_env_vars(unset)?;
+        resolve_bare_environment_keys(&mut value, env_vars);
+
+        let unsupported = schema::unsupported_keys(&value);
+        if self.strict && !unsupported.is_empty() {
+            return Err(DockerError::UnsupportedKeys(unsupported));
+        }
+
+        let config: ComposeConfig =
+            serde_yaml::from_value(value).map_err(DockerError::YamlError)?;
+
+        let warnings = unsupported
+            .into_iter()
+            .map(ComposeWarning::UnsupportedKey)
+            .collect();
+        Ok((config, warnings))
     }
 }
 
@@ -114,12 +356,31 @@ impl ComposeParser {
     #[must_use]
     pub fn new() -> Self {
         Self {
-            env_file_path: None,
-            env_vars: None,
+            env_sources: EnvSources::default(),
+            strict: false,
         }
     }
 
-    /// Set an env file for environment variable substitution
+    /// Reject compose files containing keys that are not supported
+    ///
+    /// By default, unsupported keys are ignored and reported as [`ComposeWarning`]s, so a file
+    /// may deploy differently than it would with `docker compose`. In strict mode, parsing fails
+    /// with [`DockerError::UnsupportedKeys`] listing the YAML path of every unsupported key.
+    ///
+    /// Extension keys starting with `x-` are always allowed.
+    #[must_use]
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Add an env file as a source of variables for substitution
+    ///
+    /// Can be called multiple times, later files take precedence over earlier ones. Env files
+    /// take precedence over the maps added with [`ComposeParser::env_vars()`], whatever the order
+    /// they were added in. References to variables in the file are resolved against the variables
+    /// defined earlier in the file, then against the sources with a lower precedence, see
+    /// [environment sources](ComposeParser#environment-sources).
     ///
     /// # Arguments
     ///
@@ -175,14 +436,42 @@ impl ComposeParser {
     /// ```
     #[must_use]
     pub fn env_file<P: AsRef<Path>>(mut self, path: P) -> Self {
-        self.env_file_path = Some(path.as_ref().to_path_buf());
+        self.env_sources.files.push(path.as_ref().to_path_buf());
         self
     }
 
-    /// Parses a Docker Compose file with environment variables from a [`HashMap`]
+    /// Let the environment of the current process take precedence over env files and maps
+    ///
+    /// The process environment is always used, with the lowest precedence by default. This
+    /// follows `docker compose`, where the shell takes precedence over the `.env` file. The
+    /// override file still takes precedence over it, see
+    /// [environment sources](ComposeParser#environment-sources).
+    #[must_use]
+    pub fn process_env(mut self) -> Self {
+        self.env_sources.process_precedence = true;
+        self
+    }
+
+    /// Set an env file that takes precedence over every other source, like `--env-file`
+    ///
+    /// Unlike [`ComposeParser::env_file()`], there is a single override file, and it is always
+    /// applied last, see [environment sources](ComposeParser#environment-sources).
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - Path to the environment variables file
+    #[must_use]
+    pub fn override_env_file<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.env_sources.override_file = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Add environment variables from a [`HashMap`] as a source of variables for substitution
     ///
     /// This method is useful when you want to provide environment variables
-    /// programmatically rather than from a file.
+    /// programmatically rather than from a file. Can be called multiple times, later maps take
+    /// precedence over earlier ones. Env files take precedence over maps, see
+    /// [environment sources](ComposeParser#environment-sources).
     ///
     /// # Arguments
     ///
@@ -238,32 +527,86 @@ impl ComposeParser {
     /// ```
     #[must_use]
     pub fn env_vars(mut self, vars: HashMap<String, String>) -> Self {
-        self.env_vars = Some(vars);
+        self.env_sources.maps.push(vars);
         self
     }
 }
 
-/// Validates that all required environment variables are present
-fn validate_required_env_vars(
-    config: &ComposeConfig,
+fn log_warnings(warnings: Vec<ComposeWarning>) {
+    for warning in warnings {
+        log::warn!("{}", warning);
+    }
+}
+
+/// Gives the bare keys of service environments, `- KEY` or `KEY:`, their value from `env_vars`
+///
+/// Like `docker compose` passing them through from the shell, keys that aren't defined are left
+/// out of the environment.
+fn resolve_bare_environment_keys(
+    value: &mut serde_yaml::Value,
     env_vars: &HashMap<String, String>,
-) -> Result<(), DockerError> {
-    let mut required_vars = std::collections::HashSet::new();
-
-    // Collect all required environment variables from the compose file
-    for service in config.services.values() {
-        if let Some(env) = &service.environment {
-            for (key, value) in env {
-                if value.contains("${") && !value.contains(":-") {
-                    required_vars.insert(key.clone());
-                }
+) {
+    use serde_yaml::Value;
+
+    let Some(Value::Mapping(services)) = value.get_mut("services") else {
+        return;
+    };
+    for service in services.values_mut() {
+        match service.get_mut("environment") {
+            Some(Value::Mapping(environment)) => {
+                environment.retain(|key, value| {
+                    if !value.is_null() {
+                        return true;
+                    }
+                    match key.as_str().and_then(|key| env_vars.get(key)) {
+                        Some(defined) => {
+                            *value = Value::String(defined.clone());
+                            true
+                        }
+                        None => false,
+                    }
+                });
+            }
+            Some(Value::Sequence(environment)) => {
+                environment.retain_mut(|item| match item {
+                    Value::String(item) if !item.contains('=') => match env_vars.get(item.trim()) {
+                        Some(value) => {
+                            *item = format!("{}={}", item.trim(), value);
+                            true
+                        }
+                        None => false,
+                    },
+                    _ => true,
+                });
             }
+            _ => {}
         }
     }
+}
+
+/// Validates that the variables referenced by service environments are set
+///
+/// Unset variables referenced without a default, such as `${VAR}`, are replaced with an empty
+/// string. In a service's `environment` this is an error, elsewhere it is only logged.
+fn validate_required_env_vars(unset: &[UnsetVariable]) -> Result<(), DockerError> {
+    let mut missing_vars = Vec::new();
 
-    // Check if all required variables are present
-    let env_keys: std::collections::HashSet<_> = env_vars.keys().cloned().collect();
-    let missing_vars: Vec<_> = required_vars.difference(&env_keys).collect();
+    for variable in unset {
+        let in_environment = variable.path.len() > 3
+            && variable.path[0] == "services"
+            && variable.path[2] == "environment";
+        if in_environment {
+            if !missing_vars.contains(&&variable.name) {
+                missing_vars.push(&variable.name);
+            }
+        } else {
+            log::warn!(
+                "The {} variable is not set, defaulting to an empty string in {}",
+                variable.name,
+                variable.path.join(".")
+            );
+        }
+    }
 
     if !missing_vars.is_empty() {
         return Err(DockerError::ValidationError(format!(
@@ -0,0 +1,121 @@
+//! Layered sources of the variables used to interpolate compose files, see
+//! [`ComposeParser`](super::ComposeParser)
+
+use crate::error::DockerError;
+use crate::parser::env;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+
+/// The sources of variables added to the parser
+#[derive(Debug, Default, Clone)]
+pub(crate) struct EnvSources {
+    pub(crate) files: Vec<PathBuf>,
+    pub(crate) maps: Vec<HashMap<String, String>>,
+    /// Whether the process environment takes precedence over the files and maps
+    pub(crate) process_precedence: bool,
+    pub(crate) override_file: Option<PathBuf>,
+}
+
+/// Where a variable used for interpolation was defined
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnvOrigin {
+    /// The process environment
+    Process,
+    /// An env file, see [`env_file()`](super::ComposeParser::env_file)
+    File(PathBuf),
+    /// The map given to the `n`th call of [`env_vars()`](super::ComposeParser::env_vars),
+    /// counting from 0
+    Vars(usize),
+    /// The override env file, see
+    /// [`override_env_file()`](super::ComposeParser::override_env_file)
+    OverrideFile(PathBuf),
+}
+
+impl Display for EnvOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EnvOrigin::Process => write!(f, "process environment"),
+            EnvOrigin::File(path) => write!(f, "env file {}", path.display()),
+            EnvOrigin::Vars(index) => write!(f, "variables #{}", index),
+            EnvOrigin::OverrideFile(path) => write!(f, "override env file {}", path.display()),
+        }
+    }
+}
+
+/// A variable used for interpolation, and the source it came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvVar {
+    pub value: String,
+    pub origin: EnvOrigin,
+}
+
+/// Collects the variables of every source, from the lowest to the highest precedence
+///
+/// The process environment comes first, followed by the maps and the files in the order they were
+/// added. With `process_precedence`, the process environment is applied again after them. The
+/// override file is applied last. References in env files are resolved against the variables
+/// defined earlier in the same file, then against the variables collected before it.
+pub(crate) fn resolve(sources: &EnvSources) -> Result<BTreeMap<String, EnvVar>, DockerError> {
+    let mut vars = BTreeMap::new();
+    insert(&mut vars, process_vars(), &EnvOrigin::Process);
+
+    for (index, map) in sources.maps.iter().enumerate() {
+        let map = map.iter().map(|(key, value)| (key.clone(), value.clone()));
+        insert(&mut vars, map, &EnvOrigin::Vars(index));
+    }
+
+    for path in &sources.files {
+        let file = load_file(path, &vars)?;
+        insert(&mut vars, file, &EnvOrigin::File(path.clone()));
+    }
+
+    if sources.process_precedence {
+        insert(&mut vars, process_vars(), &EnvOrigin::Process);
+    }
+
+    if let Some(path) = &sources.override_file {
+        let file = load_file(path, &vars)?;
+        insert(&mut vars, file, &EnvOrigin::OverrideFile(path.clone()));
+    }
+
+    Ok(vars)
+}
+
+/// The variables of the process environment, leaving out the ones that aren't valid unicode and
+/// can't be interpolated
+fn process_vars() -> impl Iterator<Item = (String, String)> {
+    std::env::vars_os()
+        .filter_map(|(key, value)| Some((key.into_string().ok()?, value.into_string().ok()?)))
+}
+
+fn load_file(
+    path: &Path,
+    vars: &BTreeMap<String, EnvVar>,
+) -> Result<HashMap<String, String>, DockerError> {
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        DockerError::ValidationError(format!("Failed to read env file {}: {}", path.display(), e))
+    })?;
+
+    let lookup = vars
+        .iter()
+        .map(|(key, var)| (key.clone(), var.value.clone()))
+        .collect();
+    env::parse_env_file_with_lookup(&content, &lookup)
+}
+
+fn insert(
+    vars: &mut BTreeMap<String, EnvVar>,
+    source: impl IntoIterator<Item = (String, String)>,
+    origin: &EnvOrigin,
+) {
+    for (key, value) in source {
+        vars.insert(
+            key,
+            EnvVar {
+                value,
+                origin: origin.clone(),
+            },
+        );
+    }
+}
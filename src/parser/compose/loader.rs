@@ -0,0 +1,484 @@
+//! Loading of compose files that reference other files through `include` and `extends`
+
+use super::merge::{apply_merge_keys, merge_compose, merge_service};
+use crate::config::volume::is_bind_source;
+use crate::error::DockerError;
+use crate::parser::env;
+use serde_yaml::{Mapping, Value};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Top-level sections whose entries are brought in by `include`
+const INCLUDED_SECTIONS: [&str; 3] = ["services", "networks", "volumes"];
+
+/// A reference to an unset variable without a default, replaced with an empty string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct UnsetVariable {
+    /// YAML path of the value containing the reference
+    pub(crate) path: Vec<String>,
+    pub(crate) name: String,
+}
+
+/// Loads compose files, resolving `include` and `extends` relative to each file
+pub(crate) struct Loader<'a> {
+    env_vars: &'a HashMap<String, String>,
+    /// The files currently being loaded, used to detect cycles
+    stack: Vec<PathBuf>,
+    unset: Vec<UnsetVariable>,
+}
+
+impl<'a> Loader<'a> {
+    pub(crate) fn new(env_vars: &'a HashMap<String, String>) -> Self {
+        Self {
+            env_vars,
+            stack: Vec::new(),
+            unset: Vec::new(),
+        }
+    }
+
+    /// The unset variables referenced by the loaded files
+    pub(crate) fn unset_variables(&self) -> &[UnsetVariable] {
+        &self.unset
+    }
+
+    /// Loads the compose file at `path`, with `include` and `extends` resolved
+    pub(crate) fn load_file(&mut self, path: &Path) -> Result<Value, DockerError> {
+        let env_vars = self.env_vars;
+        self.load_file_with_env(path, env_vars)
+    }
+
+    /// Loads compose file contents, resolving relative references against `dir`
+    pub(crate) fn load_str(&mut self, content: &str, dir: &Path) -> Result<Value, DockerError> {
+        let env_vars = self.env_vars;
+        self.load_str_with_env(content, dir, env_vars)
+    }
+
+    fn load_file_with_env(
+        &mut self,
+        path: &Path,
+        env_vars: &HashMap<String, String>,
+    ) -> Result<Value, DockerError> {
+        let path = std::path::absolute(path)?;
+        if self.stack.contains(&path) {
+            return Err(DockerError::ValidationError(format!(
+                "Circular reference to compose file {}",
+                path.display()
+            )));
+        }
+
+        let content = std::fs::read_to_string(&path)?;
+        let dir = path.parent().unwrap_or(Path::new("/")).to_path_buf();
+
+        self.stack.push(path);
+        let result = self.load_str_with_env(&content, &dir, env_vars);
+        self.stack.pop();
+        result
+    }
+
+    fn load_str_with_env(
+        &mut self,
+        content: &str,
+        dir: &Path,
+        env_vars: &HashMap<String, String>,
+    ) -> Result<Value, DockerError> {
+        // Values are interpolated after parsing, so their content can't change the structure
+        let mut compose: Value = serde_yaml::from_str(content)?;
+        self.interpolate(&mut compose, &mut Vec::new(), env_vars)?;
+        apply_merge_keys(&mut compose)?;
+
+        if let Value::Mapping(compose) = &mut compose {
+            if let Some(include) = compose.remove("include") {
+                self.resolve_include(compose, include, dir, env_vars)?;
+            }
+            self.resolve_extends(compose, dir)?;
+        }
+
+        Ok(compose)
+    }
+
+    /// Substitutes variables in the string scalars of `value`, leaving mapping keys untouched
+    fn interpolate(
+        &mut self,
+        value: &mut Value,
+        path: &mut Vec<String>,
+        env_vars: &HashMap<String, String>,
+    ) -> Result<(), DockerError> {
+        match value {
+            Value::String(s) if s.contains('$') => {
+                // Positions within the value mean nothing to the user, report the value's key
+                let (result, unset) = env::interpolate(s, env_vars).map_err(|e| match e {
+                    DockerError::InterpolationError {
+                        variable, message, ..
+                    } => DockerError::InterpolationError {
+                        variable,
+                        location: path.join("."),
+                        message,
+                    },
+                    e => e,
+                })?;
+                self.unset
+                    .extend(unset.into_iter().map(|name| UnsetVariable {
+                        path: path.clone(),
+                        name,
+                    }));
+                // Interpolated values stay strings, typed fields accept them as such
+                *value = Value::String(result);
+            }
+            Value::Sequence(sequence) => {
+                for (index, item) in sequence.iter_mut().enumerate() {
+                    path.push(index.to_string());
+                    self.interpolate(item, path, env_vars)?;
+                    path.pop();
+                }
+            }
+            Value::Mapping(mapping) => {
+                for (key, item) in mapping.iter_mut() {
+                    path.push(yaml_key(key));
+                    self.interpolate(item, path, env_vars)?;
+                    path.pop();
+                }
+            }
+            Value::Tagged(tagged) => self.interpolate(&mut tagged.value, path, env_vars)?,
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Adds the resources of every included file to `compose`
+    ///
+    /// Included resources must not conflict with the ones defined in `compose`.
+    fn resolve_include(
+        &mut self,
+        compose: &mut Mapping,
+        include: Value,
+        dir: &Path,
+        env_vars: &HashMap<String, String>,
+    ) -> Result<(), DockerError> {
+        let Value::Sequence(entries) = include else {
+            return Err(DockerError::ValidationError(
+                "include must be a sequence".to_string(),
+            ));
+        };
+
+        for entry in entries {
+            let included = self.load_include_entry(entry, dir, env_vars)?;
+            let Value::Mapping(mut included) = included else {
+                continue;
+            };
+
+            for section in INCLUDED_SECTIONS {
+                let Some(Value::Mapping(resources)) = included.remove(section) else {
+                    continue;
+                };
+
+                let target = compose
+                    .entry(Value::String(section.to_string()))
+                    .or_insert_with(|| Value::Mapping(Mapping::new()));
+                if target.is_null() {
+                    *target = Value::Mapping(Mapping::new());
+                }
+                let Value::Mapping(target) = target else {
+                    continue;
+                };
+
+                for (name, resource) in resources {
+                    if target.contains_key(&name) {
+                        return Err(DockerError::ValidationError(format!(
+                            "Included {} '{}' conflicts with an existing definition",
+                            section.trim_end_matches('s'),
+                            name.as_str().unwrap_or_default()
+                        )));
+                    }
+                    target.insert(name, resource);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Loads a single `include` entry, either a path or `{ path, project_directory, env_file }`
+    fn load_include_entry(
+        &mut self,
+        entry: Value,
+        dir: &Path,
+        env_vars: &HashMap<String, String>,
+    ) -> Result<Value, DockerError> {
+        let invalid = || DockerError::ValidationError("Invalid include entry".to_string());
+
+        let (paths, project_directory, env_files) = match entry {
+            Value::String(path) => (vec![path], None, Vec::new()),
+            Value::Mapping(mut entry) => {
+                let paths = match entry.remove("path") {
+                    Some(Value::String(path)) => vec![path],
+                    Some(paths @ Value::Sequence(_)) => {
+                        serde_yaml::from_value(paths).map_err(|_| invalid())?
+                    }
+                    _ => return Err(invalid()),
+                };
+                let project_directory = match entry.remove("project_directory") {
+                    Some(Value::String(directory)) => Some(dir.join(directory)),
+                    None => None,
+                    Some(_) => return Err(invalid()),
+                };
+                let env_files = match entry.remove("env_file") {
+                    Some(Value::String(env_file)) => vec![env_file],
+                    Some(env_files @ Value::Sequence(_)) => {
+                        serde_yaml::from_value(env_files).map_err(|_| invalid())?
+                    }
+                    None => Vec::new(),
+                    Some(_) => return Err(invalid()),
+                };
+                (paths, project_directory, env_files)
+            }
+            _ => return Err(invalid()),
+        };
+
+        // Variables from the include's env files take precedence for the included files
+        let mut include_env = env_vars.clone();
+        for env_file in env_files {
+            let env_path = dir.join(&env_file);
+            let content = std::fs::read_to_string(&env_path).map_err(|e| {
+                DockerError::ValidationError(format!(
+                    "Failed to read env file {}: {}",
+                    env_path.display(),
+                    e
+                ))
+            })?;
+            let vars = env::parse_env_file_with_lookup(&content, &include_env)?;
+            include_env.extend(vars);
+        }
+
+        // Multiple paths are merged like override files
+        let mut included: Option<Value> = None;
+        for path in &paths {
+            let loaded = self.load_file_with_env(&dir.join(path), &include_env)?;
+            included = Some(match included {
+                Some(base) => merge_compose(base, loaded),
+                None => loaded,
+            });
+        }
+        let Some(mut included) = included else {
+            return Err(invalid());
+        };
+
+        // Relative paths in the included files are relative to the first file's directory
+        let project_directory = match project_directory {
+            Some(directory) => directory,
+            None => dir
+                .join(&paths[0])
+                .parent()
+                .map(Path::to_path_buf)
+                .unwrap_or_default(),
+        };
+        if let Some(Value::Mapping(services)) = included.get_mut("services") {
+            for service in services.values_mut() {
+                rebase_service_paths(service, &project_directory)?;
+            }
+        }
+
+        Ok(included)
+    }
+
+    /// Replaces every service's `extends` with the merged definition of the extended service
+    fn resolve_extends(&mut self, compose: &mut Mapping, dir: &Path) -> Result<(), DockerError> {
+        let Some(Value::Mapping(services)) = compose.get("services") else {
+            return Ok(());
+        };
+
+        let mut resolved = Mapping::new();
+        for name in services.keys() {
+            let name = name.as_str().unwrap_or_default().to_string();
+            self.resolve_service(services, &name, dir, &mut resolved, &mut HashSet::new())?;
+        }
+
+        compose.insert(
+            Value::String("services".to_string()),
+            Value::Mapping(resolved),
+        );
+        Ok(())
+    }
+
+    fn resolve_service(
+        &mut self,
+        services: &Mapping,
+        name: &str,
+        dir: &Path,
+        resolved: &mut Mapping,
+        visiting: &mut HashSet<String>,
+    ) -> Result<Value, DockerError> {
+        if let Some(service) = resolved.get(name) {
+            return Ok(service.clone());
+        }
+        if !visiting.insert(name.to_string()) {
+            return Err(DockerError::ValidationError(format!(
+                "Service '{}' extends itself",
+                name
+            )));
+        }
+
+        let mut service = services.get(name).cloned().ok_or_else(|| {
+            DockerError::ValidationError(format!("Cannot extend undefined service '{}'", name))
+        })?;
+
+        let extends = match &mut service {
+            Value::Mapping(service) => service.remove("extends"),
+            _ => None,
+        };
+        if let Some(extends) = extends {
+            let (base_name, file) = match extends {
+                Value::String(base_name) => (base_name, None),
+                Value::Mapping(extends) => {
+                    let base_name = extends.get("service").and_then(Value::as_str);
+                    let file = extends.get("file").and_then(Value::as_str);
+                    match base_name {
+                        Some(base_name) => (base_name.to_string(), file.map(ToString::to_string)),
+                        None => {
+                            return Err(DockerError::ValidationError(format!(
+                                "Service '{}' extends without a service name",
+                                name
+                            )));
+                        }
+                    }
+                }
+                _ => {
+                    return Err(DockerError::ValidationError(format!(
+                        "Service '{}' has an invalid extends",
+                        name
+                    )));
+                }
+            };
+
+            let base = match file {
+                // Services from other files are fully resolved in the context of that file
+                Some(file) => {
+                    let path = dir.join(file);
+                    let unset = self.unset.len();
+                    let other = self.load_file(&path)?;
+
+                    // Only the variables of the extended service apply, now to this one
+                    let other_unset = self.unset.split_off(unset);
+                    self.unset.extend(
+                        other_unset
+                            .into_iter()
+                            .filter(|unset| {
+                                unset.path.len() > 1
+                                    && unset.path[0] == "services"
+                                    && unset.path[1] == base_name
+                            })
+                            .map(|mut unset| {
+                                unset.path[1] = name.to_string();
+                                unset
+                            }),
+                    );
+
+                    let mut base = other
+                        .get("services")
+                        .and_then(|services| services.get(base_name.as_str()))
+                        .cloned()
+                        .ok_or_else(|| {
+                            DockerError::ValidationError(format!(
+                                "Service '{}' not found in {}",
+                                base_name,
+                                path.display()
+                            ))
+                        })?;
+                    let other_dir = path.parent().unwrap_or(dir);
+                    if other_dir != dir {
+                        rebase_service_paths(&mut base, other_dir)?;
+                    }
+                    base
+                }
+                None => self.resolve_service(services, &base_name, dir, resolved, visiting)?,
+            };
+
+            service = merge_service(base, service);
+        }
+
+        visiting.remove(name);
+        resolved.insert(Value::String(name.to_string()), service.clone());
+        Ok(service)
+    }
+}
+
+fn yaml_key(key: &Value) -> String {
+    match key {
+        Value::String(s) => s.clone(),
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Makes the relative paths of a service defined in another directory absolute
+fn rebase_service_paths(service: &mut Value, dir: &Path) -> Result<(), DockerError> {
+    let dir = std::path::absolute(dir)?;
+    let rebase = |path: &str| -> String {
+        if Path::new(path).is_absolute() || path.starts_with('~') {
+            path.to_string()
+        } else {
+            dir.join(path).to_string_lossy().into_owned()
+        }
+    };
+    let is_relative = |path: &str| {
+        is_bind_source(path) && !Path::new(path).is_absolute() && !path.starts_with('~')
+    };
+
+    let Value::Mapping(service) = service else {
+        return Ok(());
+    };
+
+    match service.get_mut("build") {
+        Some(Value::String(context)) => *context = rebase(context),
+        Some(Value::Mapping(build)) => {
+            if let Some(Value::String(context)) = build.get_mut("context") {
+                *context = rebase(context);
+            }
+        }
+        _ => {}
+    }
+
+    match service.get_mut("env_file") {
+        Some(Value::String(path)) => *path = rebase(path),
+        Some(Value::Sequence(env_files)) => {
+            for env_file in env_files {
+                match env_file {
+                    Value::String(path) => *path = rebase(path),
+                    Value::Mapping(env_file) => {
+                        if let Some(Value::String(path)) = env_file.get_mut("path") {
+                            *path = rebase(path);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+        _ => {}
+    }
+
+    // Only bind mounts have paths, named volumes are left alone
+    if let Some(Value::Sequence(volumes)) = service.get_mut("volumes") {
+        for volume in volumes {
+            match volume {
+                Value::String(volume) => {
+                    if let Some((source, rest)) = volume.split_once(':') {
+                        if is_relative(source) {
+                            *volume = format!("{}:{}", rebase(source), rest);
+                        }
+                    }
+                }
+                Value::Mapping(volume) => {
+                    if let Some(Value::String(source)) = volume.get_mut("source") {
+                        if is_relative(source) {
+                            *source = rebase(source);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Ok(())
+}
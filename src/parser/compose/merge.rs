@@ -0,0 +1,251 @@
+//! Merging of compose files, following the compose specification's merge rules
+//!
+//! These rules apply both when layering override files on top of a base file, and when a
+//! service `extends` another one:
+//!
+//! * Mappings are merged recursively, with values from the override taking precedence
+//! * Scalars are replaced
+//! * `command` and `entrypoint` are replaced as a whole
+//! * `volumes` are merged by their target path, `environment`, `labels`, `depends_on` and
+//!   `networks` are merged by key, even when written as lists
+//! * Other sequences, such as `ports`, are appended, skipping duplicates
+//! * `!reset` removes a value and `!override` replaces it without merging
+
+use crate::error::DockerError;
+use serde_yaml::value::TaggedValue;
+use serde_yaml::{Mapping, Value};
+
+/// Merges an override compose file on top of a base compose file
+pub(crate) fn merge_compose(base: Value, override_: Value) -> Value {
+    merge_mapping_with(base, override_, |key, base, override_| match key {
+        "services" => merge_entries(base, override_, merge_service),
+        _ => merge_value(base, override_),
+    })
+}
+
+/// Merges an override service definition on top of a base service definition
+pub(crate) fn merge_service(base: Value, override_: Value) -> Value {
+    merge_mapping_with(base, override_, |key, base, override_| match key {
+        "command" | "entrypoint" => override_,
+        "volumes" => merge_sequence_by(base, override_, volume_target),
+        // A bare `KEY` in `environment` has no value until the parser looks it up in its
+        // variables, once every file is merged
+        "environment" => merge_value(
+            key_value_list_to_mapping(base, &Value::Null),
+            key_value_list_to_mapping(override_, &Value::Null),
+        ),
+        "labels" | "sysctls" => {
+            let empty = Value::String(String::new());
+            merge_value(
+                key_value_list_to_mapping(base, &empty),
+                key_value_list_to_mapping(override_, &empty),
+            )
+        }
+        "depends_on" | "networks" => {
+            merge_value(name_list_to_mapping(base), name_list_to_mapping(override_))
+        }
+        "build" => merge_value(build_to_mapping(base), build_to_mapping(override_)),
+        _ => merge_value(base, override_),
+    })
+}
+
+/// Merges two values using the generic rules
+pub(crate) fn merge_value(base: Value, override_: Value) -> Value {
+    match (base, override_) {
+        (Value::Mapping(base), Value::Mapping(override_)) => Value::Mapping(merge_mappings(
+            base,
+            override_,
+            &mut |_, base, override_| merge_value(base, override_),
+        )),
+        (Value::Sequence(mut base), Value::Sequence(override_)) => {
+            for item in override_ {
+                if !base.contains(&item) {
+                    base.push(item);
+                }
+            }
+            Value::Sequence(base)
+        }
+        (_, override_) => strip_override_tag(override_),
+    }
+}
+
+/// Merges two mappings whose values are merged with `merge`, such as the `services` section
+fn merge_entries(base: Value, override_: Value, merge: fn(Value, Value) -> Value) -> Value {
+    merge_mapping_with(base, override_, |_, base, override_| merge(base, override_))
+}
+
+fn merge_mapping_with<F>(base: Value, override_: Value, mut merge: F) -> Value
+where
+    F: FnMut(&str, Value, Value) -> Value,
+{
+    match (base, override_) {
+        (Value::Mapping(base), Value::Mapping(override_)) => {
+            Value::Mapping(merge_mappings(base, override_, &mut merge))
+        }
+        (_, override_) => strip_override_tag(override_),
+    }
+}
+
+fn merge_mappings<F>(mut base: Mapping, override_: Mapping, merge: &mut F) -> Mapping
+where
+    F: FnMut(&str, Value, Value) -> Value,
+{
+    for (key, value) in override_ {
+        if is_tagged(&value, "reset") {
+            base.remove(&key);
+            continue;
+        }
+        if is_tagged(&value, "override") {
+            base.insert(key, strip_override_tag(value));
+            continue;
+        }
+
+        let merged = match base.remove(&key) {
+            Some(base_value) if !base_value.is_null() => {
+                merge(key.as_str().unwrap_or_default(), base_value, value)
+            }
+            _ => value,
+        };
+        base.insert(key, merged);
+    }
+    base
+}
+
+/// Merges two sequences, replacing base items that share a key with an override item
+fn merge_sequence_by(base: Value, override_: Value, key: fn(&Value) -> Option<String>) -> Value {
+    let (mut base, override_) = match (base, override_) {
+        (Value::Sequence(base), Value::Sequence(override_)) => (base, override_),
+        (_, override_) => return strip_override_tag(override_),
+    };
+
+    for item in override_ {
+        match key(&item) {
+            Some(item_key) => {
+                base.retain(|existing| key(existing).as_ref() != Some(&item_key));
+                base.push(item);
+            }
+            None if !base.contains(&item) => base.push(item),
+            None => {}
+        }
+    }
+    Value::Sequence(base)
+}
+
+/// The container path of a service volume, in either the short or the long syntax
+fn volume_target(volume: &Value) -> Option<String> {
+    match volume {
+        Value::String(volume) => {
+            let mut parts = volume.split(':');
+            let first = parts.next()?;
+            Some(parts.next().unwrap_or(first).to_string())
+        }
+        Value::Mapping(volume) => volume.get("target")?.as_str().map(ToString::to_string),
+        _ => None,
+    }
+}
+
+/// Converts a `KEY=VALUE` list into a mapping, leaving mappings untouched
+///
+/// Entries without a `=` are given the `bare` value.
+fn key_value_list_to_mapping(value: Value, bare: &Value) -> Value {
+    let Value::Sequence(items) = value else {
+        return value;
+    };
+
+    let mut mapping = Mapping::new();
+    for item in items {
+        let Some(item) = item.as_str() else {
+            continue;
+        };
+        let (key, value) = match item.split_once('=') {
+            Some((key, value)) => (key, Value::String(value.trim().to_string())),
+            None => (item, bare.clone()),
+        };
+        mapping.insert(Value::String(key.trim().to_string()), value);
+    }
+    Value::Mapping(mapping)
+}
+
+/// Converts a list of names, such as the short form of `depends_on`, into a mapping
+fn name_list_to_mapping(value: Value) -> Value {
+    let Value::Sequence(items) = value else {
+        return value;
+    };
+
+    Value::Mapping(items.into_iter().map(|name| (name, Value::Null)).collect())
+}
+
+/// Converts the short form of `build`, a context path, into a mapping
+fn build_to_mapping(value: Value) -> Value {
+    match value {
+        Value::String(context) => {
+            let mut mapping = Mapping::new();
+            mapping.insert(Value::String("context".to_string()), Value::String(context));
+            Value::Mapping(mapping)
+        }
+        value => value,
+    }
+}
+
+fn is_tagged(value: &Value, tag: &str) -> bool {
+    matches!(value, Value::Tagged(tagged) if tagged.tag == tag)
+}
+
+fn strip_override_tag(value: Value) -> Value {
+    match value {
+        Value::Tagged(tagged) if tagged.tag == "override" => {
+            let TaggedValue { value, .. } = *tagged;
+            value
+        }
+        value => value,
+    }
+}
+
+/// Resolves YAML merge keys (`<<: *anchor`), including merges of blocks that merge others
+///
+/// Keys defined in the mapping itself take precedence over merged ones, and earlier entries of
+/// a merge sequence take precedence over later ones.
+pub(crate) fn apply_merge_keys(value: &mut Value) -> Result<(), DockerError> {
+    match value {
+        Value::Mapping(mapping) => {
+            // Merged blocks are resolved first, so chains of merges are fully applied
+            for value in mapping.values_mut() {
+                apply_merge_keys(value)?;
+            }
+
+            let merged = match mapping.remove("<<") {
+                None => return Ok(()),
+                Some(Value::Mapping(merged)) => vec![merged],
+                Some(Value::Sequence(merged)) => merged
+                    .into_iter()
+                    .map(|merged| match merged {
+                        Value::Mapping(merged) => Ok(merged),
+                        _ => Err(DockerError::ValidationError(
+                            "Merge key sequences may only contain mappings".to_string(),
+                        )),
+                    })
+                    .collect::<Result<_, _>>()?,
+                Some(_) => {
+                    return Err(DockerError::ValidationError(
+                        "Merge key value must be a mapping or a sequence of mappings".to_string(),
+                    ));
+                }
+            };
+
+            for merged in merged {
+                for (key, value) in merged {
+                    mapping.entry(key).or_insert(value);
+                }
+            }
+        }
+        Value::Sequence(sequence) => {
+            for value in sequence {
+                apply_merge_keys(value)?;
+            }
+        }
+        Value::Tagged(tagged) => apply_merge_keys(&mut tagged.value)?,
+        _ => {}
+    }
+
+    Ok(())
+}
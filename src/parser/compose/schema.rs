@@ -0,0 +1,325 @@
+//! The set of compose keys understood by [`ComposeConfig`](crate::config::ComposeConfig)
+//!
+//! Serde silently ignores unknown fields, so the raw YAML is checked against this schema to
+//! find keys that would otherwise be dropped. Keep it in sync when adding fields to the config.
+
+use serde_yaml::Value;
+use std::fmt::Display;
+
+/// A non-fatal problem found while parsing a compose file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ComposeWarning {
+    /// A key that is not supported and was ignored, with its YAML path
+    UnsupportedKey(String),
+}
+
+impl Display for ComposeWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ComposeWarning::UnsupportedKey(path) => write!(f, "Unsupported key: {}", path),
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Schema {
+    /// A value that is not inspected further
+    Any,
+    /// A mapping with a fixed set of keys
+    Keys(&'static [(&'static str, Schema)]),
+    /// A mapping with arbitrary keys, whose values follow the schema
+    Map(&'static Schema),
+    /// A sequence, whose items follow the schema
+    List(&'static Schema),
+}
+
+const COMPOSE: Schema = Schema::Keys(&[
+    ("version", Schema::Any),
+    ("services", Schema::Map(&SERVICE)),
+    ("volumes", Schema::Map(&VOLUME)),
+    ("networks", Schema::Map(&NETWORK)),
+    ("secrets", Schema::Map(&FILE_OBJECT)),
+    ("configs", Schema::Map(&FILE_OBJECT)),
+]);
+
+const VOLUME: Schema = Schema::Keys(&[
+    ("name", Schema::Any),
+    ("driver", Schema::Any),
+    ("driver_opts", Schema::Any),
+    ("labels", Schema::Any),
+    ("external", Schema::Any),
+]);
+
+const NETWORK: Schema = Schema::Keys(&[
+    ("name", Schema::Any),
+    ("driver", Schema::Any),
+    ("driver_opts", Schema::Any),
+    (
+        "ipam",
+        Schema::Keys(&[
+            ("driver", Schema::Any),
+            (
+                "config",
+                Schema::List(&Schema::Keys(&[
+                    ("subnet", Schema::Any),
+                    ("ip_range", Schema::Any),
+                    ("gateway", Schema::Any),
+                    ("aux_addresses", Schema::Any),
+                ])),
+            ),
+            ("options", Schema::Any),
+        ]),
+    ),
+    ("internal", Schema::Any),
+    ("attachable", Schema::Any),
+    ("enable_ipv6", Schema::Any),
+    ("external", Schema::Any),
+    ("labels", Schema::Any),
+]);
+
+const FILE_OBJECT: Schema = Schema::Keys(&[
+    ("file", Schema::Any),
+    ("environment", Schema::Any),
+    ("content", Schema::Any),
+    ("external", Schema::Any),
+    ("name", Schema::Any),
+]);
+
+const FILE_OBJECT_REFERENCE: Schema = Schema::Keys(&[
+    ("source", Schema::Any),
+    ("target", Schema::Any),
+    ("uid", Schema::Any),
+    ("gid", Schema::Any),
+    ("mode", Schema::Any),
+]);
+
+const SERVICE: Schema = Schema::Keys(&[
+    ("image", Schema::Any),
+    (
+        "build",
+        Schema::Keys(&[("context", Schema::Any), ("dockerfile", Schema::Any)]),
+    ),
+    ("command", Schema::Any),
+    ("environment", Schema::Any),
+    (
+        "env_file",
+        Schema::List(&Schema::Keys(&[
+            ("path", Schema::Any),
+            ("required", Schema::Any),
+            ("format", Schema::Any),
+        ])),
+    ),
+    (
+        "volumes",
+        Schema::List(&Schema::Keys(&[
+            ("type", Schema::Any),
+            ("source", Schema::Any),
+            ("target", Schema::Any),
+            ("read_only", Schema::Any),
+            ("consistency", Schema::Any),
+            (
+                "bind",
+                Schema::Keys(&[
+                    ("propagation", Schema::Any),
+                    ("create_host_path", Schema::Any),
+                    ("selinux", Schema::Any),
+                ]),
+            ),
+            (
+                "volume",
+                Schema::Keys(&[("nocopy", Schema::Any), ("subpath", Schema::Any)]),
+            ),
+            (
+                "tmpfs",
+                Schema::Keys(&[("size", Schema::Any), ("mode", Schema::Any)]),
+            ),
+        ])),
+    ),
+    (
+        "depends_on",
+        Schema::Map(&Schema::Keys(&[
+            ("condition", Schema::Any),
+            ("restart", Schema::Any),
+            ("required", Schema::Any),
+        ])),
+    ),
+    ("links", Schema::Any),
+    ("external_links", Schema::Any),
+    (
+        "ports",
+        Schema::List(&Schema::Keys(&[
+            ("target", Schema::Any),
+            ("published", Schema::Any),
+            ("host_ip", Schema::Any),
+            ("protocol", Schema::Any),
+            ("mode", Schema::Any),
+        ])),
+    ),
+    ("expose", Schema::Any),
+    (
+        "networks",
+        Schema::Map(&Schema::Keys(&[
+            ("aliases", Schema::Any),
+            ("ipv4_address", Schema::Any),
+            ("ipv6_address", Schema::Any),
+            ("priority", Schema::Any),
+        ])),
+    ),
+    ("network_mode", Schema::Any),
+    (
+        "requirements",
+        Schema::Keys(&[
+            ("min_memory_gb", Schema::Any),
+            ("min_disk_gb", Schema::Any),
+            ("min_bandwidth_mbps", Schema::Any),
+            ("required_ports", Schema::Any),
+            ("data_directory", Schema::Any),
+            ("cpu_limit", Schema::Any),
+            ("memory_limit", Schema::Any),
+            ("memory_swap", Schema::Any),
+            ("memory_reservation", Schema::Any),
+            ("cpu_shares", Schema::Any),
+            ("cpuset_cpus", Schema::Any),
+        ]),
+    ),
+    ("deploy", Schema::Keys(&[("resources", RESOURCES)])),
+    ("mem_limit", Schema::Any),
+    ("mem_reservation", Schema::Any),
+    ("memswap_limit", Schema::Any),
+    ("cpus", Schema::Any),
+    ("cpu_shares", Schema::Any),
+    ("cpuset", Schema::Any),
+    (
+        "ulimits",
+        Schema::Map(&Schema::Keys(&[
+            ("soft", Schema::Any),
+            ("hard", Schema::Any),
+        ])),
+    ),
+    ("pids_limit", Schema::Any),
+    ("shm_size", Schema::Any),
+    ("sysctls", Schema::Any),
+    ("cap_add", Schema::Any),
+    ("cap_drop", Schema::Any),
+    ("privileged", Schema::Any),
+    ("read_only", Schema::Any),
+    ("tmpfs", Schema::Any),
+    ("devices", Schema::Any),
+    ("security_opt", Schema::Any),
+    ("working_dir", Schema::Any),
+    ("entrypoint", Schema::Any),
+    ("hostname", Schema::Any),
+    ("stop_signal", Schema::Any),
+    ("stop_grace_period", Schema::Any),
+    ("init", Schema::Any),
+    ("extra_hosts", Schema::Any),
+    (
+        "healthcheck",
+        Schema::Keys(&[
+            ("endpoint", Schema::Any),
+            ("method", Schema::Any),
+            ("expected_status", Schema::Any),
+            ("body", Schema::Any),
+            ("interval", Schema::Any),
+            ("timeout", Schema::Any),
+            ("retries", Schema::Any),
+        ]),
+    ),
+    ("restart", Schema::Any),
+    ("user", Schema::Any),
+    ("labels", Schema::Any),
+    ("platform", Schema::Any),
+    ("secrets", Schema::List(&FILE_OBJECT_REFERENCE)),
+    ("configs", Schema::List(&FILE_OBJECT_REFERENCE)),
+    ("profiles", Schema::Any),
+]);
+
+const RESOURCES: Schema = Schema::Keys(&[
+    (
+        "limits",
+        Schema::Keys(&[
+            ("cpus", Schema::Any),
+            ("memory", Schema::Any),
+            ("pids", Schema::Any),
+        ]),
+    ),
+    (
+        "reservations",
+        Schema::Keys(&[
+            ("cpus", Schema::Any),
+            ("memory", Schema::Any),
+            (
+                "devices",
+                Schema::List(&Schema::Keys(&[
+                    ("capabilities", Schema::Any),
+                    ("driver", Schema::Any),
+                    ("count", Schema::Any),
+                    ("device_ids", Schema::Any),
+                    ("options", Schema::Any),
+                ])),
+            ),
+        ]),
+    ),
+]);
+
+/// Finds the keys of a compose file that are not supported, returning their YAML paths
+///
+/// Extension keys starting with `x-` are allowed anywhere.
+pub(crate) fn unsupported_keys(compose: &Value) -> Vec<String> {
+    let mut unsupported = Vec::new();
+    walk(compose, COMPOSE, "", &mut unsupported);
+    unsupported
+}
+
+fn walk(value: &Value, schema: Schema, path: &str, unsupported: &mut Vec<String>) {
+    match (schema, value) {
+        (Schema::Keys(known), Value::Mapping(map)) => {
+            for (key, value) in map {
+                let key = key_to_string(key);
+                if key.starts_with("x-") {
+                    continue;
+                }
+
+                let path = join(path, &key);
+                match known.iter().find(|(name, _)| *name == key) {
+                    Some((_, schema)) => walk(value, *schema, &path, unsupported),
+                    None => unsupported.push(path),
+                }
+            }
+        }
+        (Schema::Map(schema), Value::Mapping(map)) => {
+            for (key, value) in map {
+                walk(
+                    value,
+                    *schema,
+                    &join(path, &key_to_string(key)),
+                    unsupported,
+                );
+            }
+        }
+        (Schema::List(schema), Value::Sequence(seq)) => {
+            for (index, item) in seq.iter().enumerate() {
+                walk(item, *schema, &format!("{}[{}]", path, index), unsupported);
+            }
+        }
+        // Short forms (such as a list of names instead of a mapping) have no keys to check
+        _ => {}
+    }
+}
+
+fn key_to_string(key: &Value) -> String {
+    match key {
+        Value::String(s) => s.clone(),
+        Value::Number(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        _ => format!("{:?}", key),
+    }
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
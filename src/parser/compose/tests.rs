@@ -1,12 +1,20 @@
 #![allow(clippy::literal_string_with_formatting_args)]
 
-use crate::parser::ComposeParser;
+use crate::config::{
+    BindPropagation, ByteSize, DependencyCondition, DeviceCount, EnvFile, EnvFileFormat,
+    ExposedPort, FileObjectReference, NetworkMode, PortMapping, PortProtocol, PortRange,
+    SelinuxRelabel, ServiceDependency, ServiceNetworkConfig, TmpfsOptions, Ulimit,
+};
+use crate::config::{HealthCheck, Method, SystemRequirements};
+use crate::error::DockerError;
 use crate::parser::env;
+use crate::parser::{ComposeParser, ComposeWarning};
 use crate::test_fixtures::{get_local_reth_compose, get_reth_archive_compose};
 use crate::{ComposeConfig, Service, Volume};
 use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
+use std::time::Duration;
 use tempfile::NamedTempFile;
 
 #[test]
@@ -53,6 +61,7 @@ fn test_compose_parsing() {
             source,
             target,
             read_only,
+            ..
         } => {
             assert_eq!(source, "./data");
             assert_eq!(target, "/data");
@@ -79,10 +88,10 @@ fn test_reth_archive_compose_parsing() {
     assert_eq!(
         reth.ports,
         Some(vec![
-            "8543:8543".to_string(),
-            "8544:8544".to_string(),
-            "30304:30304/tcp".to_string(),
-            "30304:30304/udp".to_string(),
+            "8543:8543".parse().unwrap(),
+            "8544:8544".parse().unwrap(),
+            "30304:30304/tcp".parse().unwrap(),
+            "30304:30304/udp".parse().unwrap(),
         ])
     );
 
@@ -95,8 +104,8 @@ fn test_reth_archive_compose_parsing() {
     assert_eq!(
         nimbus.ports,
         Some(vec![
-            "9001:9001/tcp".to_string(),
-            "9001:9001/udp".to_string(),
+            "9001:9001/tcp".parse().unwrap(),
+            "9001:9001/udp".parse().unwrap(),
         ])
     );
 
@@ -108,14 +117,20 @@ fn test_reth_archive_compose_parsing() {
     // Test reth service volumes
     let reth_volumes = reth.volumes.as_ref().unwrap();
     assert_eq!(reth_volumes.len(), 2);
-    assert!(matches!(&reth_volumes[0], Volume::Named(name) if name == "reth_data:/data"));
-    assert!(matches!(&reth_volumes[1], Volume::Named(name) if name == "reth_jwt:/jwt:ro"));
+    assert_eq!(&reth_volumes[0], &Volume::named("reth_data", "/data"));
+    assert_eq!(
+        &reth_volumes[1],
+        &Volume::named("reth_jwt", "/jwt").read_only(true)
+    );
 
     // Test nimbus service volumes
     let nimbus_volumes = nimbus.volumes.as_ref().unwrap();
     assert_eq!(nimbus_volumes.len(), 2);
-    assert!(matches!(&nimbus_volumes[0], Volume::Named(name) if name == "nimbus_data:/data"));
-    assert!(matches!(&nimbus_volumes[1], Volume::Named(name) if name == "reth_jwt:/jwt/reth:ro"));
+    assert_eq!(&nimbus_volumes[0], &Volume::named("nimbus_data", "/data"));
+    assert_eq!(
+        &nimbus_volumes[1],
+        &Volume::named("reth_jwt", "/jwt/reth").read_only(true)
+    );
 }
 
 #[test]
@@ -129,19 +144,22 @@ fn test_local_reth_compose_parsing() {
     // Test reth service
     let reth = config.services.get("reth").unwrap();
     assert_eq!(reth.image, None); // Local build, no image specified
-    assert_eq!(reth.ports, Some(vec!["9000:9000".to_string()]));
+    assert_eq!(reth.ports, Some(vec!["9000:9000".parse().unwrap()]));
     assert!(reth.build.is_some());
     let build = reth.build.as_ref().unwrap();
     assert_eq!(build.context, "./reth");
     assert_eq!(build.dockerfile, Some("Dockerfile".to_string()));
-    assert!(reth.command.is_some());
+    // Folded string commands are split into arguments
+    let command = reth.command.as_ref().unwrap();
+    assert_eq!(command[0], "/reth/target/release/reth");
+    assert_eq!(command[1], "node");
     assert!(reth.volumes.is_some());
     assert_eq!(reth.restart, Some("always".to_string()));
 
     // Test prometheus service
     let prometheus = config.services.get("prometheus").unwrap();
     assert_eq!(prometheus.image, Some("prom/prometheus".to_string()));
-    assert_eq!(prometheus.ports, Some(vec!["9090:9090".to_string()]));
+    assert_eq!(prometheus.ports, Some(vec!["9090:9090".parse().unwrap()]));
     assert!(prometheus.volumes.is_some());
     assert_eq!(prometheus.restart, Some("always".to_string()));
     assert_eq!(prometheus.user, Some("root".to_string()));
@@ -151,7 +169,7 @@ fn test_local_reth_compose_parsing() {
     // Test grafana service
     let grafana = config.services.get("grafana").unwrap();
     assert_eq!(grafana.image, Some("grafana/grafana".to_string()));
-    assert_eq!(grafana.ports, Some(vec!["3000:3000".to_string()]));
+    assert_eq!(grafana.ports, Some(vec!["3000:3000".parse().unwrap()]));
     assert!(grafana.volumes.is_some());
     assert_eq!(grafana.restart, Some("always".to_string()));
     assert_eq!(grafana.user, Some("472".to_string()));
@@ -170,16 +188,14 @@ fn test_volume_validation() {
         version: "3.8".to_string(),
         services: HashMap::new(),
         volumes: HashMap::new(),
+        networks: HashMap::new(),
+        ..ComposeConfig::default()
     };
 
     let service = Service {
         volumes: Some(vec![
-            Volume::Named("test-data:/data".to_string()),
-            Volume::Bind {
-                source: PathBuf::from("/host").to_string_lossy().to_string(),
-                target: "/container".to_string(),
-                read_only: false,
-            },
+            Volume::named("test-data", "/data"),
+            Volume::bind(PathBuf::from("/host").to_string_lossy(), "/container"),
         ]),
         ..Default::default()
     };
@@ -202,11 +218,8 @@ fn test_volume_validation() {
 // Sync tests that don't need Docker cleanup
 #[test]
 fn test_volume_serialization() {
-    let volume = Volume::Bind {
-        source: PathBuf::from("/host").to_string_lossy().to_string(),
-        target: "/container".to_string(),
-        read_only: true,
-    };
+    let volume =
+        Volume::bind(PathBuf::from("/host").to_string_lossy(), "/container").read_only(true);
 
     let service = Service {
         volumes: Some(vec![volume]),
@@ -219,16 +232,275 @@ fn test_volume_serialization() {
     assert_eq!(service.volumes, deserialized.volumes);
 }
 
+#[test]
+fn test_volume_syntax() {
+    let yaml = r"
+version: '3'
+services:
+  app:
+    image: alpine
+    volumes:
+      - data
+      - cache:/cache:ro,nocopy
+      - /var/log:/logs:ro,z,rslave
+      - type: volume
+        source: data
+        target: /data
+        read_only: true
+        volume:
+          nocopy: true
+          subpath: app
+      - type: bind
+        source: /etc/app
+        target: /etc/app
+        bind:
+          create_host_path: true
+          selinux: Z
+      - type: tmpfs
+        target: /tmp
+        tmpfs:
+          size: 64m
+          mode: 01777
+";
+    let config = ComposeParser::new().parse(&mut yaml.as_bytes()).unwrap();
+    let volumes = config.services["app"].volumes.clone().unwrap();
+
+    assert_eq!(volumes[0], Volume::anonymous("data"));
+    match &volumes[1] {
+        Volume::Named {
+            source,
+            target,
+            read_only,
+            options,
+        } => {
+            assert_eq!((source.as_str(), target.as_str()), ("cache", "/cache"));
+            assert!(read_only);
+            assert!(options.nocopy);
+        }
+        _ => panic!("Expected named volume"),
+    }
+    match &volumes[2] {
+        Volume::Bind {
+            read_only, options, ..
+        } => {
+            assert!(read_only);
+            assert_eq!(options.selinux, Some(SelinuxRelabel::Shared));
+            assert_eq!(options.propagation, Some(BindPropagation::Rslave));
+        }
+        _ => panic!("Expected bind mount"),
+    }
+    match &volumes[3] {
+        Volume::Named {
+            read_only, options, ..
+        } => {
+            assert!(read_only);
+            assert!(options.nocopy);
+            assert_eq!(options.subpath.as_deref(), Some("app"));
+        }
+        _ => panic!("Expected named volume"),
+    }
+    match &volumes[4] {
+        Volume::Bind { options, .. } => {
+            assert_eq!(options.create_host_path, Some(true));
+            assert_eq!(options.selinux, Some(SelinuxRelabel::Private));
+        }
+        _ => panic!("Expected bind mount"),
+    }
+    assert_eq!(
+        volumes[5],
+        Volume::Tmpfs {
+            target: "/tmp".to_string(),
+            options: TmpfsOptions {
+                size: Some(ByteSize(64 * 1024 * 1024)),
+                mode: Some(0o1777),
+            },
+        }
+    );
+
+    // Volumes with options are written in the long syntax, and read back unchanged
+    let serialized = serde_yaml::to_string(&volumes).unwrap();
+    let deserialized: Vec<Volume> = serde_yaml::from_str(&serialized).unwrap();
+    assert_eq!(deserialized, volumes);
+
+    // Flags that don't apply to the kind of mount are rejected, and the long syntax needs a target
+    for invalid in [
+        "data:/data:z",
+        "/host:/data:nocopy",
+        "a:b:c:d",
+        "data:/data:bogus",
+        "{ source: ./x }",
+        "{ type: bind, source: ./x }",
+        "{ read_only: true }",
+    ] {
+        assert!(
+            serde_yaml::from_str::<Volume>(invalid).is_err(),
+            "{} should be invalid",
+            invalid
+        );
+    }
+
+    // Services can't mount a volume definition, which has no target
+    for volumes in ["[{}]", "[~]", "[{ driver: local }]"] {
+        let content = format!(
+            "version: '3'\nservices:\n  app:\n    image: alpine\n    volumes: {}\n",
+            volumes
+        );
+        assert!(
+            ComposeParser::new().parse(&mut content.as_bytes()).is_err(),
+            "{} should be invalid",
+            volumes
+        );
+    }
+}
+
+#[test]
+fn test_bind_source_classification() {
+    let parse = |spec: &str| serde_yaml::from_str::<Volume>(spec).unwrap();
+
+    for spec in [
+        "./data:/data",
+        "../data:/data",
+        ".data:/data",
+        "data/dir:/x",
+        "/mnt/data:/data",
+    ] {
+        let (source, _) = spec.split_once(':').unwrap();
+        assert_eq!(
+            parse(spec),
+            Volume::bind(source, spec.rsplit(':').next().unwrap())
+        );
+    }
+    assert_eq!(parse("data:/data"), Volume::named("data", "/data"));
+
+    // The home directory is expanded when it is known
+    if let Ok(home) = std::env::var("HOME") {
+        let home = home.trim_end_matches('/');
+        assert_eq!(
+            parse("~/keys:/keys:ro"),
+            Volume::bind(format!("{}/keys", home), "/keys").read_only(true)
+        );
+        assert_eq!(
+            parse("{ type: bind, source: ~/keys, target: /keys }"),
+            Volume::bind(format!("{}/keys", home), "/keys")
+        );
+    }
+}
+
+#[test]
+fn test_volume_definitions() {
+    let yaml = r"
+version: '3'
+services:
+  app:
+    image: alpine
+    volumes:
+      - data:/data
+      - logs:/logs
+volumes:
+  data:
+    name: app-data
+    driver: local
+    driver_opts:
+      type: none
+      o: bind
+      device: ./data
+    labels:
+      tier: storage
+  shared:
+    external: true
+";
+    let mut config = ComposeParser::new().parse(&mut yaml.as_bytes()).unwrap();
+
+    match &config.volumes["data"] {
+        Volume::Config {
+            name,
+            driver,
+            driver_opts,
+            labels,
+            external,
+        } => {
+            assert_eq!(name, "app-data");
+            assert_eq!(driver.as_deref(), Some("local"));
+            assert_eq!(driver_opts.as_ref().unwrap()["device"], "./data");
+            assert_eq!(labels.as_ref().unwrap()["tier"], "storage");
+            assert!(!external);
+        }
+        _ => panic!("Expected volume definition"),
+    }
+    assert!(matches!(
+        &config.volumes["shared"],
+        Volume::Config { external: true, .. }
+    ));
+
+    // Volumes only referenced by services get a default definition
+    config.collect_volumes();
+    assert!(matches!(
+        &config.volumes["logs"],
+        Volume::Config { name, driver: None, external: false, .. } if name.is_empty()
+    ));
+
+    let serialized = config.to_compose_yaml().unwrap();
+    let reparsed = ComposeParser::new()
+        .parse(&mut serialized.as_bytes())
+        .unwrap();
+    assert_eq!(reparsed.volumes, config.volumes);
+
+    // Volume definitions can't be mounted into a service
+    let definition = config.volumes["data"].clone();
+    config
+        .services
+        .get_mut("app")
+        .unwrap()
+        .volumes
+        .as_mut()
+        .unwrap()
+        .push(definition);
+    assert!(config.to_compose_yaml().is_err());
+}
+
+#[cfg(feature = "deploy")]
+#[test]
+fn test_volume_to_mount() {
+    use bollard::service::{Mount, MountBindOptionsPropagationEnum, MountTypeEnum};
+
+    let mount = Mount::try_from(Volume::anonymous("/data")).unwrap();
+    assert_eq!(mount.typ, Some(MountTypeEnum::VOLUME));
+    assert_eq!(mount.target.as_deref(), Some("/data"));
+    assert_eq!(mount.source, None);
+
+    let mount = Mount::try_from(Volume::named("data", "/data").read_only(true)).unwrap();
+    assert_eq!(mount.source.as_deref(), Some("data"));
+    assert_eq!(mount.read_only, Some(true));
+
+    let volume: Volume = serde_yaml::from_str("/host:/data:rshared").unwrap();
+    let mount = Mount::try_from(volume).unwrap();
+    assert_eq!(mount.typ, Some(MountTypeEnum::BIND));
+    assert_eq!(
+        mount.bind_options.unwrap().propagation,
+        Some(MountBindOptionsPropagationEnum::RSHARED)
+    );
+
+    let volume: Volume =
+        serde_yaml::from_str("{ type: tmpfs, target: /tmp, tmpfs: { size: 1k, mode: 0700 } }")
+            .unwrap();
+    let tmpfs = Mount::try_from(volume).unwrap().tmpfs_options.unwrap();
+    assert_eq!(tmpfs.size_bytes, Some(1024));
+    assert_eq!(tmpfs.mode, Some(0o700));
+
+    // Volume definitions have no target to mount them at
+    let definition: Volume = serde_yaml::from_str("{ driver: local }").unwrap();
+    assert!(Mount::try_from(definition).is_err());
+}
+
 #[test]
 fn test_service_deployment() {
     let mut config = ComposeConfig::default();
     let service = Service {
         image: Some("nginx:latest".to_string()),
-        volumes: Some(vec![crate::Volume::Bind {
-            source: PathBuf::from("/host/data").to_string_lossy().to_string(),
-            target: "/container/data".to_string(),
-            read_only: false,
-        }]),
+        volumes: Some(vec![crate::Volume::bind(
+            PathBuf::from("/host/data").to_string_lossy(),
+            "/container/data",
+        )]),
         ..Default::default()
     };
 
@@ -260,7 +532,7 @@ fn test_full_compose_parsing() {
         assert_eq!(web_service.image.as_deref().unwrap(), "nginx:1.21");
         assert_eq!(
             web_service.ports.as_ref().unwrap().first().unwrap(),
-            "8080:80"
+            &PortMapping::new(8080, 80)
         );
     } else {
         panic!("Web service not found in parsed config");
@@ -349,11 +621,11 @@ services:
     env_vars.insert("VERSION".to_string(), "1.21".to_string());
     env_vars.insert("DEBUG".to_string(), "true".to_string());
 
-    let processed = env::substitute_env_vars(content, &env_vars);
+    let processed = env::substitute_env_vars(content, &env_vars).unwrap();
     let mut config = ComposeParser::new()
         .parse(&mut processed.as_bytes())
         .unwrap();
-    config.resolve_env(&env_vars);
+    config.resolve_env(&env_vars).unwrap();
 
     let app1 = config.services.get("app1").unwrap();
     assert_eq!(app1.image.as_deref(), Some("nginx:1.21"));
@@ -364,3 +636,1992 @@ services:
         panic!("app1 environment should be Some");
     }
 }
+
+#[test]
+fn test_resolve_env() {
+    let content = r#"version: "3"
+services:
+    app:
+        image: nginx
+        environment:
+            NESTED: "$${A:-$${B:-fallback}}"
+            ESCAPED: "$$$$HOME"
+            HOST: "$${PATH}"
+        volumes:
+            - "./$${DIR}:/data"
+"#;
+    let mut config = ComposeParser::new().parse(&mut content.as_bytes()).unwrap();
+
+    // Uses the same rules as the parser, without falling back to the process environment
+    let env_vars = HashMap::from([("DIR".to_string(), "data".to_string())]);
+    config.resolve_env(&env_vars).unwrap();
+    let app = &config.services["app"];
+    let env = app.environment.as_ref().unwrap();
+    assert_eq!(env.get("NESTED").map(String::as_str), Some("fallback"));
+    assert_eq!(env.get("ESCAPED").map(String::as_str), Some("$HOME"));
+    assert_eq!(env.get("HOST").map(String::as_str), Some(""));
+    assert!(matches!(
+        &app.volumes.as_ref().unwrap()[0],
+        Volume::Bind { source, .. } if source == "./data"
+    ));
+
+    let mut config = ComposeParser::new().parse(&mut content.as_bytes()).unwrap();
+    let service = config.services.get_mut("app").unwrap();
+    service.environment =
+        Some(HashMap::from([("A".to_string(), "${A:?required}".to_string())]).into());
+    assert!(config.resolve_env(&HashMap::new()).is_err());
+}
+
+#[test]
+fn test_interpolation_after_parsing() {
+    // ${COMMENTED} would fail if comments were interpolated
+    let content = r#"version: "3"
+services:
+    app:
+        # image: ${COMMENTED:?not interpolated}
+        image: "nginx:${VERSION}"
+        command: ["echo", "$MESSAGE"]
+        privileged: ${PRIVILEGED:-false}
+        cpu_shares: ${SHARES}
+        labels:
+            ${LABEL}: value
+        environment:
+            URL: ${URL}
+            NOTE: ${NOTE}
+            MULTILINE: ${MULTILINE}
+            ESCAPED: $$URL"#;
+
+    let env_vars: HashMap<String, String> = [
+        ("VERSION", "1.21"),
+        ("MESSAGE", "key: value"),
+        ("SHARES", "512"),
+        ("LABEL", "unused"),
+        ("URL", "http://db:5432/app?ssl=true"),
+        ("NOTE", "text # not a comment"),
+        ("MULTILINE", "first\nsecond: 2"),
+    ]
+    .into_iter()
+    .map(|(key, value)| (key.to_string(), value.to_string()))
+    .collect();
+
+    let config = ComposeParser::new()
+        .env_vars(env_vars)
+        .parse(&mut content.as_bytes())
+        .unwrap();
+
+    let app = config.services.get("app").unwrap();
+    assert_eq!(app.image.as_deref(), Some("nginx:1.21"));
+    assert_eq!(
+        app.command,
+        Some(vec!["echo".to_string(), "key: value".to_string()])
+    );
+    assert_eq!(app.privileged, Some(false));
+    assert_eq!(app.cpu_shares, Some(512));
+    assert!(app.labels.as_ref().unwrap().contains_key("${LABEL}"));
+
+    let env = app.environment.as_ref().unwrap();
+    assert_eq!(
+        env.get("URL").map(String::as_str),
+        Some("http://db:5432/app?ssl=true")
+    );
+    assert_eq!(
+        env.get("NOTE").map(String::as_str),
+        Some("text # not a comment")
+    );
+    assert_eq!(
+        env.get("MULTILINE").map(String::as_str),
+        Some("first\nsecond: 2")
+    );
+    assert_eq!(env.get("ESCAPED").map(String::as_str), Some("$URL"));
+}
+
+#[test]
+fn test_interpolated_typed_fields() {
+    let content = r#"version: "3"
+services:
+    app:
+        image: alpine
+        cpu_shares: ${SHARES}
+        pids_limit: ${PIDS}
+        privileged: ${TRUE}
+        read_only: ${TRUE}
+        init: ${FALSE}
+        cpus: ${CPUS}
+        ulimits:
+            nproc: ${LIMIT}
+            nofile:
+                soft: ${LIMIT}
+                hard: ${HARD}
+        deploy:
+            resources:
+                limits:
+                    pids: ${PIDS}
+                    memory: ${MEMORY}
+                reservations:
+                    devices:
+                        - capabilities: [gpu]
+                          count: ${COUNT}
+        depends_on:
+            db:
+                condition: service_started
+                required: ${FALSE}
+                restart: ${TRUE}
+        ports:
+            - target: ${PORT}
+              published: ${PORT}
+        volumes:
+            - type: volume
+              source: data
+              target: /data
+              read_only: ${TRUE}
+              volume:
+                  nocopy: ${TRUE}
+            - type: bind
+              source: /etc/app
+              target: /etc/app
+              bind:
+                  create_host_path: ${TRUE}
+            - type: tmpfs
+              target: /tmp
+              tmpfs:
+                  size: ${MEMORY}
+                  mode: ${MODE}
+        env_file:
+            - path: ./app.env
+              required: ${FALSE}
+        networks:
+            backend:
+                priority: ${PRIORITY}
+        secrets:
+            - source: key
+              mode: ${MODE}
+        configs:
+            - source: conf
+              mode: ${MODE}
+        healthcheck:
+            endpoint: http://localhost
+            method: Get
+            expected_status: ${STATUS}
+            interval: ${NANOS}
+            timeout: ${NANOS}
+            retries: ${RETRIES}
+        requirements:
+            min_memory_gb: ${GB}
+            min_disk_gb: ${GB}
+            min_bandwidth_mbps: ${MBPS}
+            required_ports:
+                - ${PORT}
+            data_directory: ${NUMERIC}
+            cpu_limit: ${CPUS}
+            memory_limit: ${NUMERIC}
+            cpu_shares: ${SHARES}
+    db:
+        image: postgres
+networks:
+    backend:
+        internal: ${TRUE}
+        attachable: ${TRUE}
+        enable_ipv6: ${FALSE}
+        external: ${FALSE}
+volumes:
+    data:
+        external: ${FALSE}
+secrets:
+    key:
+        content: secret
+        external: ${FALSE}
+configs:
+    conf:
+        content: setting
+"#;
+
+    let env_vars: HashMap<String, String> = [
+        ("SHARES", "512"),
+        ("PIDS", "100"),
+        ("TRUE", "true"),
+        ("FALSE", "false"),
+        ("CPUS", "1.5"),
+        ("LIMIT", "1024"),
+        ("HARD", "2048"),
+        ("MEMORY", "64m"),
+        ("COUNT", "2"),
+        ("PORT", "8080"),
+        ("MODE", "0440"),
+        ("PRIORITY", "10"),
+        ("STATUS", "200"),
+        ("NANOS", "1000000000"),
+        ("RETRIES", "3"),
+        ("GB", "4"),
+        ("MBPS", "100"),
+        ("NUMERIC", "512"),
+    ]
+    .into_iter()
+    .map(|(key, value)| (key.to_string(), value.to_string()))
+    .collect();
+
+    let config = ComposeParser::new()
+        .env_vars(env_vars)
+        .parse(&mut content.as_bytes())
+        .unwrap();
+    let app = &config.services["app"];
+
+    assert_eq!(app.cpu_shares, Some(512));
+    assert_eq!(app.pids_limit, Some(100));
+    assert_eq!(app.privileged, Some(true));
+    assert_eq!(app.read_only, Some(true));
+    assert_eq!(app.init, Some(false));
+    assert_eq!(app.cpus, Some(1.5));
+
+    let ulimits = app.ulimits.as_ref().unwrap();
+    assert_eq!(ulimits["nproc"], Ulimit::Single(1024));
+    assert_eq!(
+        ulimits["nofile"],
+        Ulimit::SoftHard {
+            soft: 1024,
+            hard: 2048
+        }
+    );
+
+    let resources = app.deploy.as_ref().unwrap().resources.as_ref().unwrap();
+    let limits = resources.limits.as_ref().unwrap();
+    assert_eq!(limits.pids, Some(100));
+    assert_eq!(limits.memory, Some(ByteSize(64 * 1024 * 1024)));
+    let devices = resources
+        .reservations
+        .as_ref()
+        .unwrap()
+        .devices
+        .as_ref()
+        .unwrap();
+    assert_eq!(devices[0].count, Some(DeviceCount::Count(2)));
+
+    let db = app.depends_on.as_ref().unwrap().get("db").unwrap();
+    assert!(!db.required);
+    assert!(db.restart);
+
+    let port = &app.ports.as_ref().unwrap()[0];
+    assert_eq!(port.target, PortRange::single(8080));
+    assert_eq!(port.published, Some(PortRange::single(8080)));
+
+    let volumes = app.volumes.as_ref().unwrap();
+    match &volumes[0] {
+        Volume::Named {
+            read_only, options, ..
+        } => {
+            assert!(read_only);
+            assert!(options.nocopy);
+        }
+        _ => panic!("Expected named volume"),
+    }
+    match &volumes[1] {
+        Volume::Bind { options, .. } => assert_eq!(options.create_host_path, Some(true)),
+        _ => panic!("Expected bind mount"),
+    }
+    assert_eq!(
+        volumes[2],
+        Volume::Tmpfs {
+            target: "/tmp".to_string(),
+            options: TmpfsOptions {
+                size: Some(ByteSize(64 * 1024 * 1024)),
+                mode: Some(0o440),
+            },
+        }
+    );
+
+    assert!(!app.env_file.as_ref().unwrap()[0].required);
+    let networks = app.networks.as_ref().unwrap();
+    assert_eq!(networks.get("backend").unwrap().priority, Some(10));
+    assert_eq!(app.secrets.as_ref().unwrap()[0].mode, Some(0o440));
+    assert_eq!(app.configs.as_ref().unwrap()[0].mode, Some(0o440));
+
+    let health = app.healthcheck.as_ref().unwrap();
+    assert_eq!(health.expected_status, 200);
+    assert_eq!(health.interval, Duration::from_secs(1));
+    assert_eq!(health.timeout, Duration::from_secs(1));
+    assert_eq!(health.retries, 3);
+
+    let requirements = app.requirements.as_ref().unwrap();
+    assert_eq!(requirements.min_memory_gb, 4);
+    assert_eq!(requirements.min_disk_gb, 4);
+    assert_eq!(requirements.min_bandwidth_mbps, 100);
+    assert_eq!(requirements.required_ports, vec![8080]);
+    assert_eq!(requirements.cpu_limit, Some(1.5));
+    assert_eq!(requirements.cpu_shares, Some(512));
+    // Numeric-looking values of string fields stay strings
+    assert_eq!(requirements.data_directory, "512");
+    assert_eq!(requirements.memory_limit.as_deref(), Some("512"));
+
+    let network = &config.networks["backend"];
+    assert!(network.internal && network.attachable);
+    assert!(!network.enable_ipv6 && !network.external);
+    assert!(matches!(
+        config.volumes["data"],
+        Volume::Config {
+            external: false,
+            ..
+        }
+    ));
+    assert!(!config.secrets["key"].external);
+}
+
+#[test]
+fn test_missing_environment_variables() {
+    let parse = |content: &str| ComposeParser::new().parse(&mut content.as_bytes());
+
+    // Only references without a default in a service's environment are required
+    let result = parse(
+        r#"version: "3"
+services:
+    app:
+        image: "nginx:${VERSION}"
+        environment:
+            PORT: ${PORT:-8080}
+            TOKEN: "Bearer ${TOKEN}"
+            SECRET: $SECRET"#,
+    );
+    match result {
+        Err(DockerError::ValidationError(message)) => {
+            assert!(message.contains("TOKEN"), "{}", message);
+            assert!(message.contains("SECRET"), "{}", message);
+            assert!(!message.contains("VERSION"), "{}", message);
+            assert!(!message.contains("PORT"), "{}", message);
+        }
+        other => panic!("Expected a validation error, got {:?}", other.map(|_| ())),
+    }
+
+    let config = parse(
+        r#"version: "3"
+services:
+    app:
+        image: "nginx:${VERSION}"
+        environment:
+            - PORT=${PORT:-8080}"#,
+    )
+    .unwrap();
+    assert_eq!(config.services["app"].image.as_deref(), Some("nginx:"));
+
+    // Errors point at the value containing the expression
+    match parse("services:\n  app:\n    image: ${VERSION:?is required}") {
+        Err(DockerError::InterpolationError {
+            variable, location, ..
+        }) => {
+            assert_eq!(variable, "VERSION");
+            assert_eq!(location, "services.app.image");
+        }
+        other => panic!(
+            "Expected an interpolation error, got {:?}",
+            other.map(|_| ())
+        ),
+    }
+
+    // The key path identifies the value wherever it is in the file
+    let error = parse(
+        r#"version: "3"
+services:
+    app:
+        image: nginx
+        environment:
+            URL: "http://${HOST:-localhost}"
+            TOKEN: "Bearer ${TOKEN:?must be set}"
+        volumes:
+            - "${DATA:?must be set}:/data""#,
+    )
+    .map(|_| ())
+    .unwrap_err();
+    assert!(matches!(
+        &error,
+        DockerError::InterpolationError { variable, location, .. }
+            if variable == "TOKEN" && location == "services.app.environment.TOKEN"
+    ));
+    assert!(
+        error
+            .to_string()
+            .starts_with("Invalid interpolation of TOKEN at services.app.environment.TOKEN"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn test_env_sources() {
+    use crate::parser::{EnvOrigin, EnvVar};
+
+    // $HOME in the fixture comes from the process environment, which is always used
+    let home = std::env::var("HOME").unwrap();
+    let config = ComposeParser::new()
+        .parse_from_path(get_local_reth_compose())
+        .unwrap();
+    let reth = &config.services["reth"];
+    assert_eq!(
+        reth.volumes.as_ref().unwrap()[1],
+        Volume::named("rethlogs", format!("{}/rethlogs", home))
+    );
+    assert_eq!(reth.command.as_ref().unwrap().last(), Some(&home));
+
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path().join("base.env");
+    let local = dir.path().join("local.env");
+    let overrides = dir.path().join("override.env");
+    fs::write(&base, "VERSION=1.0\nPORT=8080\nNAME=base\nHOME=/base").unwrap();
+    fs::write(&local, "PORT=9090\nURL=http://localhost/${VERSION}").unwrap();
+    fs::write(&overrides, "NAME=override").unwrap();
+
+    let parser = ComposeParser::new()
+        .override_env_file(&overrides)
+        .env_file(&base)
+        .process_env()
+        .env_vars(HashMap::from([("NAME".to_string(), "vars".to_string())]))
+        .env_file(&local)
+        .env_vars(HashMap::from([("VERSION".to_string(), "2.0".to_string())]));
+    let report = parser.env_report().unwrap();
+
+    let var = |value: &str, origin: EnvOrigin| EnvVar {
+        value: value.to_string(),
+        origin,
+    };
+    assert_eq!(report["HOME"], var(&home, EnvOrigin::Process));
+    assert_eq!(report["PORT"], var("9090", EnvOrigin::File(local.clone())));
+    assert_eq!(
+        report["URL"],
+        var("http://localhost/1.0", EnvOrigin::File(local))
+    );
+    // Env files take precedence over maps, whatever the order they were added in
+    assert_eq!(report["VERSION"], var("1.0", EnvOrigin::File(base.clone())));
+    assert_eq!(
+        report["NAME"],
+        var("override", EnvOrigin::OverrideFile(overrides))
+    );
+
+    let content = r#"version: "3"
+services:
+    app:
+        image: "app:${VERSION}"
+        environment:
+            NAME: ${NAME}
+            PORT: ${PORT}"#;
+    let config = parser.parse(&mut content.as_bytes()).unwrap();
+    let app = &config.services["app"];
+    assert_eq!(app.image.as_deref(), Some("app:1.0"));
+    let env = app.environment.as_ref().unwrap();
+    assert_eq!(env.get("NAME").map(String::as_str), Some("override"));
+    assert_eq!(env.get("PORT").map(String::as_str), Some("9090"));
+
+    // References in a file see its own definitions before the ones of earlier sources
+    let redefined = dir.path().join("redefined.env");
+    fs::write(&redefined, "A=2\nB=$A").unwrap();
+    let vars = HashMap::from([("A".to_string(), "1".to_string())]);
+    for parser in [
+        ComposeParser::new()
+            .env_vars(vars.clone())
+            .env_file(&redefined),
+        ComposeParser::new()
+            .env_vars(vars)
+            .override_env_file(&redefined),
+    ] {
+        let report = parser.env_report().unwrap();
+        assert_eq!(report["A"].value, "2");
+        assert_eq!(report["B"].value, "2");
+    }
+
+    // Without `process_env()`, the process environment has the lowest precedence
+    let report = ComposeParser::new()
+        .env_vars(HashMap::from([("PORT".to_string(), "7070".to_string())]))
+        .env_file(&base)
+        .env_report()
+        .unwrap();
+    assert_eq!(report["HOME"], var("/base", EnvOrigin::File(base.clone())));
+    assert_eq!(report["PORT"], var("8080", EnvOrigin::File(base)));
+
+    // Env files are read when parsing
+    let missing = ComposeParser::new().env_file(dir.path().join("missing.env"));
+    assert!(missing.parse(&mut content.as_bytes()).is_err());
+}
+
+#[test]
+fn test_service_levels() {
+    let mut config = ComposeConfig::default();
+    for (name, deps) in [
+        ("db", vec![]),
+        ("cache", vec![]),
+        ("api", vec!["db", "cache"]),
+        ("worker", vec!["db"]),
+        ("web", vec!["api"]),
+    ] {
+        let service = Service {
+            image: Some("alpine:latest".to_string()),
+            depends_on: Some(deps.into_iter().map(String::from).collect()),
+            ..Default::default()
+        };
+        config.services.insert(name.to_string(), service);
+    }
+
+    let levels = config.resolve_service_levels().unwrap();
+    assert_eq!(
+        levels,
+        vec![
+            vec!["cache".to_string(), "db".to_string()],
+            vec!["api".to_string(), "worker".to_string()],
+            vec!["web".to_string()],
+        ]
+    );
+
+    // Circular dependencies are still rejected
+    config.services.get_mut("db").unwrap().depends_on = Some(vec!["web".to_string()].into());
+    assert!(config.resolve_service_levels().is_err());
+}
+
+#[test]
+fn test_depends_on_and_links_parsing() {
+    let content = r#"
+version: "3.8"
+services:
+  db:
+    image: postgres
+  cache:
+    image: redis
+  migrate:
+    image: migrate
+    depends_on:
+      - db
+  api:
+    image: api
+    depends_on:
+      db:
+        condition: service_healthy
+        restart: true
+      migrate:
+        condition: service_completed_successfully
+      metrics:
+        condition: service_started
+        required: false
+    links:
+      - cache:redis
+    external_links:
+      - legacy_db:legacy
+"#;
+
+    let config: ComposeConfig = serde_yaml::from_str(content).unwrap();
+
+    let migrate = config.services.get("migrate").unwrap();
+    let depends_on = migrate.depends_on.as_ref().unwrap();
+    assert_eq!(depends_on.get("db"), Some(&ServiceDependency::default()));
+
+    let api = config.services.get("api").unwrap();
+    let depends_on = api.depends_on.as_ref().unwrap();
+    assert_eq!(depends_on.len(), 3);
+
+    let db = depends_on.get("db").unwrap();
+    assert_eq!(db.condition, DependencyCondition::ServiceHealthy);
+    assert!(db.restart);
+    assert!(db.required);
+
+    let migrate = depends_on.get("migrate").unwrap();
+    assert_eq!(
+        migrate.condition,
+        DependencyCondition::ServiceCompletedSuccessfully
+    );
+
+    let metrics = depends_on.get("metrics").unwrap();
+    assert_eq!(metrics.condition, DependencyCondition::ServiceStarted);
+    assert!(!metrics.required);
+
+    assert_eq!(api.links, Some(vec!["cache:redis".to_string()]));
+    assert_eq!(
+        api.external_links,
+        Some(vec!["legacy_db:legacy".to_string()])
+    );
+
+    // Links count as dependencies, the missing optional `metrics` service is ignored
+    let mut dependencies: Vec<_> = api.dependency_names().collect();
+    dependencies.sort_unstable();
+    assert_eq!(dependencies, vec!["cache", "db", "metrics", "migrate"]);
+
+    let levels = config.resolve_service_levels().unwrap();
+    assert_eq!(levels.last().unwrap(), &vec!["api".to_string()]);
+}
+
+#[test]
+fn test_undefined_required_dependency() {
+    let content = r#"
+version: "3.8"
+services:
+  api:
+    image: api
+    depends_on:
+      db:
+        condition: service_healthy
+"#;
+
+    let config: ComposeConfig = serde_yaml::from_str(content).unwrap();
+    assert!(config.resolve_service_order().is_err());
+}
+
+#[test]
+fn test_networks_parsing() {
+    let content = r#"
+version: "3.8"
+services:
+  node:
+    image: node
+    networks:
+      internal:
+        aliases:
+          - sequencer
+        ipv4_address: 172.28.0.10
+        priority: 100
+      public:
+  explorer:
+    image: explorer
+    networks:
+      - public
+networks:
+  internal:
+    driver: bridge
+    internal: true
+    ipam:
+      driver: default
+      config:
+        - subnet: 172.28.0.0/16
+          gateway: 172.28.0.1
+  public:
+    attachable: true
+    driver_opts:
+      com.docker.network.bridge.name: public0
+    labels:
+      tier: public
+  shared:
+    external: true
+    name: shared-net
+"#;
+
+    let config: ComposeConfig = serde_yaml::from_str(content).unwrap();
+    assert_eq!(config.networks.len(), 3);
+
+    let internal = config.networks.get("internal").unwrap();
+    assert!(internal.internal);
+    assert_eq!(internal.driver.as_deref(), Some("bridge"));
+    let pools = internal.ipam.as_ref().unwrap().config.as_ref().unwrap();
+    assert_eq!(pools[0].subnet.as_deref(), Some("172.28.0.0/16"));
+    assert_eq!(pools[0].gateway.as_deref(), Some("172.28.0.1"));
+
+    let public = config.networks.get("public").unwrap();
+    assert!(public.attachable);
+    assert_eq!(
+        public
+            .labels
+            .as_ref()
+            .unwrap()
+            .get("tier")
+            .map(String::as_str),
+        Some("public")
+    );
+
+    let shared = config.networks.get("shared").unwrap();
+    assert!(shared.external);
+    assert_eq!(shared.name.as_deref(), Some("shared-net"));
+
+    let node = config.services.get("node").unwrap();
+    let networks = node.networks.as_ref().unwrap();
+    assert_eq!(
+        networks.get("internal"),
+        Some(&ServiceNetworkConfig {
+            aliases: Some(vec!["sequencer".to_string()]),
+            ipv4_address: Some("172.28.0.10".to_string()),
+            ipv6_address: None,
+            priority: Some(100),
+        })
+    );
+    assert_eq!(
+        networks.get("public"),
+        Some(&ServiceNetworkConfig::default())
+    );
+
+    // The highest priority network comes first
+    let ordered: Vec<_> = networks
+        .by_priority()
+        .into_iter()
+        .map(|(name, _)| name.as_str())
+        .collect();
+    assert_eq!(ordered, vec!["internal", "public"]);
+
+    let explorer = config.services.get("explorer").unwrap();
+    let networks: Vec<_> = explorer.networks.as_ref().unwrap().names().collect();
+    assert_eq!(networks, vec!["public"]);
+}
+
+#[test]
+fn test_network_mode_parsing() {
+    let content = r#"
+version: "3.8"
+services:
+  node:
+    image: node
+  metrics:
+    image: metrics
+    network_mode: "service:node"
+  host:
+    image: host
+    network_mode: host
+  isolated:
+    image: isolated
+    network_mode: none
+  sidecar:
+    image: sidecar
+    network_mode: "container:abc123"
+"#;
+
+    let config: ComposeConfig = serde_yaml::from_str(content).unwrap();
+    let mode = |name: &str| config.services[name].network_mode.clone();
+    assert_eq!(mode("node"), None);
+    assert_eq!(
+        mode("metrics"),
+        Some(NetworkMode::Service("node".to_string()))
+    );
+    assert_eq!(mode("host"), Some(NetworkMode::Host));
+    assert_eq!(mode("isolated"), Some(NetworkMode::None));
+    assert_eq!(
+        mode("sidecar"),
+        Some(NetworkMode::Container("abc123".to_string()))
+    );
+
+    // Sharing a service's network namespace orders it before the dependent service
+    let order = config.resolve_service_order().unwrap();
+    let position = |name: &str| order.iter().position(|s| s == name).unwrap();
+    assert!(position("node") < position("metrics"));
+
+    let serialized = serde_yaml::to_string(&config.services["metrics"]).unwrap();
+    assert!(serialized.contains("network_mode: service:node"));
+
+    let invalid = r#"
+version: "3.8"
+services:
+  metrics:
+    image: metrics
+    network_mode: "service:missing"
+"#;
+    let config: ComposeConfig = serde_yaml::from_str(invalid).unwrap();
+    assert!(config.resolve_service_order().is_err());
+}
+
+#[test]
+fn test_port_mapping_parsing() {
+    let content = r#"
+version: "3.8"
+services:
+  node:
+    image: node
+    ports:
+      - "3000"
+      - 3001
+      - "8000-8001:9000-9001"
+      - "127.0.0.1:8545:8545"
+      - "127.0.0.1::8546"
+      - "[::1]:6001:6001"
+      - "30303:30303/udp"
+      - "9090-9099:80"
+      - target: 9000
+        published: "19000"
+        host_ip: 127.0.0.1
+        protocol: udp
+        mode: host
+    expose:
+      - "7000"
+      - 7001
+      - "7100-7101/udp"
+"#;
+
+    let config: ComposeConfig = serde_yaml::from_str(content).unwrap();
+    let node = &config.services["node"];
+    let ports = node.ports.as_ref().unwrap();
+    assert_eq!(ports.len(), 9);
+
+    assert_eq!(ports[0].target, PortRange::single(3000));
+    assert_eq!(ports[0].published, None);
+    assert_eq!(ports[1].target, PortRange::single(3001));
+    assert_eq!(
+        ports[2].published,
+        Some(PortRange {
+            start: 8000,
+            end: 8001
+        })
+    );
+    assert_eq!(ports[3].host_ip.as_deref(), Some("127.0.0.1"));
+    assert_eq!(ports[4].published, None);
+    assert_eq!(ports[4].host_ip.as_deref(), Some("127.0.0.1"));
+    assert_eq!(ports[5].host_ip.as_deref(), Some("::1"));
+    assert_eq!(ports[6].protocol, PortProtocol::Udp);
+    assert_eq!(ports[8].protocol, PortProtocol::Udp);
+    assert_eq!(ports[8].mode.as_deref(), Some("host"));
+
+    // Short syntax round trips through its string form
+    let short: Vec<String> = ports[..8].iter().map(ToString::to_string).collect();
+    assert_eq!(
+        short,
+        [
+            "3000",
+            "3001",
+            "8000-8001:9000-9001",
+            "127.0.0.1:8545:8545",
+            "127.0.0.1::8546",
+            "[::1]:6001:6001",
+            "30303:30303/udp",
+            "9090-9099:80",
+        ]
+    );
+    let serialized = serde_yaml::to_string(&ports).unwrap();
+    let reparsed: Vec<PortMapping> = serde_yaml::from_str(&serialized).unwrap();
+    assert_eq!(&reparsed, ports);
+
+    let bindings = ports[2].port_bindings();
+    assert_eq!(bindings.len(), 2);
+    assert_eq!(bindings[1].0, "9001/tcp");
+    assert_eq!(bindings[1].1.host_port.as_deref(), Some("8001"));
+    let bindings = ports[7].port_bindings();
+    assert_eq!(bindings.len(), 1);
+    assert_eq!(bindings[0].1.host_port.as_deref(), Some("9090-9099"));
+
+    let expose = node.expose.as_ref().unwrap();
+    let exposed: Vec<String> = expose
+        .iter()
+        .flat_map(ExposedPort::container_ports)
+        .collect();
+    assert_eq!(exposed, ["7000/tcp", "7001/tcp", "7100/udp", "7101/udp"]);
+
+    for invalid in ["abc", "70000:80", "8000-8001:80-82", "80/quic", "9-1:80"] {
+        assert!(
+            invalid.parse::<PortMapping>().is_err(),
+            "{invalid} should be rejected"
+        );
+    }
+}
+
+#[test]
+fn test_runtime_options_parsing() {
+    let content = r#"
+version: "3.8"
+services:
+  node:
+    image: node
+    entrypoint: ["/usr/bin/tini", "--"]
+    working_dir: /data
+    hostname: node-1
+    stop_signal: SIGINT
+    stop_grace_period: 1m30s
+    init: true
+    privileged: false
+    read_only: true
+    mem_limit: 512m
+    memswap_limit: 1g
+    cpus: "1.5"
+    cpuset: "0,1"
+    pids_limit: 100
+    shm_size: 64m
+    cap_add: [NET_ADMIN]
+    cap_drop: [ALL]
+    security_opt:
+      - no-new-privileges:true
+    tmpfs: /run:size=64m
+    devices:
+      - /dev/fuse
+      - /dev/ttyUSB0:/dev/ttyS0:rw
+    sysctls:
+      net.core.somaxconn: 1024
+    ulimits:
+      nproc: 65535
+      nofile:
+        soft: 20000
+        hard: 40000
+    extra_hosts:
+      - "host.docker.internal=host-gateway"
+      - "seed:10.0.0.2"
+    deploy:
+      resources:
+        limits:
+          cpus: 2
+          memory: 1G
+        reservations:
+          memory: 256M
+          devices:
+            - capabilities: [gpu]
+              count: all
+"#;
+
+    let config: ComposeConfig = serde_yaml::from_str(content).unwrap();
+    let node = &config.services["node"];
+
+    assert_eq!(
+        node.entrypoint,
+        Some(vec!["/usr/bin/tini".to_string(), "--".to_string()])
+    );
+    assert_eq!(node.working_dir.as_deref(), Some("/data"));
+    assert_eq!(node.stop_grace_period, Some(Duration::from_secs(90)));
+    assert_eq!(node.init, Some(true));
+    assert_eq!(node.read_only, Some(true));
+    assert_eq!(node.mem_limit, Some(ByteSize(512 * 1024 * 1024)));
+    assert_eq!(node.cpus, Some(1.5));
+    assert_eq!(node.pids_limit, Some(100));
+    assert_eq!(node.tmpfs, Some(vec!["/run:size=64m".to_string()]));
+
+    let devices = node.devices.as_ref().unwrap();
+    assert_eq!(devices[0].container_path, "/dev/fuse");
+    assert_eq!(devices[0].permissions, "rwm");
+    assert_eq!(devices[1].container_path, "/dev/ttyS0");
+    assert_eq!(devices[1].permissions, "rw");
+
+    assert_eq!(node.sysctls.as_ref().unwrap()["net.core.somaxconn"], "1024");
+    let ulimits = node.ulimits.as_ref().unwrap();
+    assert_eq!(ulimits["nproc"], Ulimit::Single(65535));
+    assert_eq!(ulimits["nofile"].soft(), 20000);
+    assert_eq!(ulimits["nofile"].hard(), 40000);
+    assert_eq!(
+        node.extra_hosts,
+        Some(vec![
+            "host.docker.internal:host-gateway".to_string(),
+            "seed:10.0.0.2".to_string()
+        ])
+    );
+
+    let resources = node.deploy.as_ref().unwrap().resources.as_ref().unwrap();
+    let limits = resources.limits.as_ref().unwrap();
+    assert_eq!(limits.cpus, Some(2.0));
+    assert_eq!(limits.memory, Some(ByteSize(1024 * 1024 * 1024)));
+    let reservations = resources.reservations.as_ref().unwrap();
+    assert_eq!(reservations.memory, Some(ByteSize(256 * 1024 * 1024)));
+    assert_eq!(
+        reservations.devices.as_ref().unwrap()[0].count,
+        Some(DeviceCount::All)
+    );
+
+    // Serialized services parse back to the same values
+    let serialized = serde_yaml::to_string(node).unwrap();
+    let reparsed: Service = serde_yaml::from_str(&serialized).unwrap();
+    assert_eq!(reparsed.stop_grace_period, node.stop_grace_period);
+    assert_eq!(reparsed.devices, node.devices);
+    assert_eq!(reparsed.deploy, node.deploy);
+
+    let invalid = r#"
+version: "3.8"
+services:
+  node:
+    image: node
+    devices:
+      - /dev/fuse:/dev/fuse:rwx
+"#;
+    assert!(serde_yaml::from_str::<ComposeConfig>(invalid).is_err());
+}
+
+#[test]
+fn test_unsupported_keys() {
+    let content = r#"
+version: "3.8"
+x-common: &common
+  image: node
+services:
+  node:
+    image: node
+    scale: 2
+    x-notes: ignored
+    ports:
+      - target: 80
+        app_protocol: http
+    deploy:
+      replicas: 2
+      resources:
+        limits:
+          memory: 1g
+          gpus: 1
+    networks:
+      internal:
+        mac_address: 02:42:ac:11:65:43
+networks:
+  internal:
+    driver_opts:
+      anything: goes
+    enable_ipv4: true
+name: stack
+"#;
+
+    let (config, warnings) = ComposeParser::new()
+        .parse_with_warnings(&mut content.as_bytes())
+        .unwrap();
+    assert!(config.services.contains_key("node"));
+
+    let mut paths: Vec<String> = warnings
+        .into_iter()
+        .map(|ComposeWarning::UnsupportedKey(path)| path)
+        .collect();
+    paths.sort();
+    assert_eq!(
+        paths,
+        [
+            "name",
+            "networks.internal.enable_ipv4",
+            "services.node.deploy.replicas",
+            "services.node.deploy.resources.limits.gpus",
+            "services.node.networks.internal.mac_address",
+            "services.node.ports[0].app_protocol",
+            "services.node.scale",
+        ]
+    );
+
+    let result = ComposeParser::new()
+        .strict(true)
+        .parse(&mut content.as_bytes());
+    let Err(DockerError::UnsupportedKeys(keys)) = result else {
+        panic!("Strict mode should reject unsupported keys");
+    };
+    assert_eq!(keys.len(), 7);
+}
+
+#[test]
+fn test_strict_long_volume_syntax() {
+    let content = r#"
+version: "3.8"
+services:
+  node:
+    image: node
+    volumes:
+      - type: bind
+        source: ./data
+        target: /data
+        consistency: cached
+        bind:
+          propagation: rslave
+          create_host_path: true
+          selinux: z
+      - type: volume
+        source: cache
+        target: /cache
+        volume:
+          nocopy: true
+          subpath: node
+      - type: tmpfs
+        target: /tmp
+        tmpfs:
+          size: 64m
+          mode: 1777
+        bind:
+          unknown: true
+"#;
+
+    let result = ComposeParser::new()
+        .strict(true)
+        .parse(&mut content.as_bytes());
+    let Err(DockerError::UnsupportedKeys(keys)) = result else {
+        panic!("Strict mode should reject unsupported keys");
+    };
+    assert_eq!(keys, ["services.node.volumes[2].bind.unknown"]);
+
+    let content = content.replace("        bind:\n          unknown: true\n", "");
+    let config = ComposeParser::new()
+        .strict(true)
+        .parse(&mut content.as_bytes())
+        .unwrap();
+    assert_eq!(config.services["node"].volumes.as_ref().unwrap().len(), 3);
+}
+
+#[test]
+fn test_unsupported_keys_in_fixtures() {
+    let content = std::fs::read(get_local_reth_compose()).unwrap();
+    ComposeParser::new()
+        .strict(true)
+        .parse(&mut content.as_slice())
+        .unwrap();
+
+    // Containers are always named after their services
+    let content = std::fs::read(get_reth_archive_compose()).unwrap();
+    let (_, warnings) = ComposeParser::new()
+        .parse_with_warnings(&mut content.as_slice())
+        .unwrap();
+    assert!(warnings.contains(&ComposeWarning::UnsupportedKey(
+        "services.reth.container_name".to_string()
+    )));
+}
+
+#[test]
+fn test_restart_user_and_entrypoint() {
+    use bollard::service::RestartPolicyNameEnum;
+
+    let content = r#"
+version: "3.8"
+services:
+  node:
+    image: node
+    user: "1000:1000"
+    restart: on-failure:5
+    entrypoint: /bin/sh -c 'exec node --port "8545"'
+    command: --verbose
+  sidecar:
+    image: sidecar
+    restart: unless-stopped
+    entrypoint: ["/bin/sidecar"]
+"#;
+
+    let config: ComposeConfig = serde_yaml::from_str(content).unwrap();
+    let node = &config.services["node"];
+    assert_eq!(node.user.as_deref(), Some("1000:1000"));
+    assert_eq!(
+        node.entrypoint,
+        Some(vec![
+            "/bin/sh".to_string(),
+            "-c".to_string(),
+            "exec node --port \"8545\"".to_string(),
+        ])
+    );
+    assert_eq!(node.command, Some(vec!["--verbose".to_string()]));
+
+    let policy = node.restart_policy().unwrap().unwrap();
+    assert_eq!(policy.name, Some(RestartPolicyNameEnum::ON_FAILURE));
+    assert_eq!(policy.maximum_retry_count, Some(5));
+
+    let sidecar = &config.services["sidecar"];
+    assert_eq!(sidecar.entrypoint, Some(vec!["/bin/sidecar".to_string()]));
+    let policy = sidecar.restart_policy().unwrap().unwrap();
+    assert_eq!(policy.name, Some(RestartPolicyNameEnum::UNLESS_STOPPED));
+
+    for invalid in ["sometimes", "on-failure:", "on-failure:x", "always:3"] {
+        let service = Service {
+            restart: Some(invalid.to_string()),
+            ..Service::default()
+        };
+        assert!(
+            service.restart_policy().is_err(),
+            "{invalid} should be rejected"
+        );
+    }
+
+    let unbalanced = r#"
+version: "3.8"
+services:
+  node:
+    image: node
+    entrypoint: /bin/sh -c 'unterminated
+"#;
+    assert!(serde_yaml::from_str::<ComposeConfig>(unbalanced).is_err());
+}
+
+#[test]
+fn test_service_env_files() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(
+        dir.path().join("common.env"),
+        "NETWORK=mainnet\nLOG_LEVEL=info\nRPC_PORT=8545\n",
+    )
+    .unwrap();
+    fs::write(dir.path().join("node.env"), "LOG_LEVEL=debug\n").unwrap();
+    fs::write(dir.path().join("raw.env"), "PASSWORD=\"quoted\"\n").unwrap();
+
+    let content = r#"
+version: "3.8"
+services:
+  node:
+    image: node
+    env_file:
+      - common.env
+      - path: node.env
+      - path: missing.env
+        required: false
+      - path: raw.env
+        format: raw
+    environment:
+      RPC_PORT: "9545"
+  single:
+    image: single
+    env_file: common.env
+  broken:
+    image: broken
+    env_file: missing.env
+"#;
+
+    let config: ComposeConfig = serde_yaml::from_str(content).unwrap();
+    let node = &config.services["node"];
+    let env_files = node.env_file.as_ref().unwrap();
+    assert_eq!(env_files.len(), 4);
+    assert!(!env_files[2].required);
+    assert_eq!(env_files[3].format, EnvFileFormat::Raw);
+
+    let env = node.resolved_environment(dir.path()).unwrap().unwrap();
+    assert_eq!(env.get("NETWORK").map(String::as_str), Some("mainnet"));
+    // Later env files override earlier ones, and `environment` overrides both
+    assert_eq!(env.get("LOG_LEVEL").map(String::as_str), Some("debug"));
+    assert_eq!(env.get("RPC_PORT").map(String::as_str), Some("9545"));
+    assert_eq!(env.get("PASSWORD").map(String::as_str), Some("\"quoted\""));
+
+    let single = &config.services["single"];
+    assert_eq!(single.env_file, Some(vec![EnvFile::new("common.env")]));
+    let env = single.resolved_environment(dir.path()).unwrap().unwrap();
+    assert_eq!(env.get("RPC_PORT").map(String::as_str), Some("8545"));
+
+    let broken = &config.services["broken"];
+    assert!(broken.resolved_environment(dir.path()).is_err());
+
+    // Short and long forms are preserved when serializing
+    let serialized = serde_yaml::to_string(&node.env_file).unwrap();
+    let reparsed: Vec<EnvFile> = serde_yaml::from_str(&serialized).unwrap();
+    assert_eq!(&reparsed, env_files);
+}
+
+#[test]
+fn test_multi_file_overrides() {
+    let dir = tempfile::tempdir().unwrap();
+    let base = dir.path().join("docker-compose.yml");
+    fs::write(
+        &base,
+        r#"
+version: "3.8"
+services:
+  node:
+    image: node:1.0
+    command: ["node", "--dev"]
+    environment:
+      - LOG_LEVEL=info
+      - NETWORK=testnet
+    ports:
+      - "8545:8545"
+    volumes:
+      - data:/data
+      - ./config:/config
+    depends_on:
+      - db
+    labels:
+      tier: backend
+  db:
+    image: postgres
+    environment:
+      POSTGRES_DB: node
+"#,
+    )
+    .unwrap();
+
+    let prod = dir.path().join("docker-compose.prod.yml");
+    fs::write(
+        &prod,
+        r#"
+services:
+  node:
+    image: node:2.0
+    command: ["node", "--release"]
+    environment:
+      NETWORK: mainnet
+    ports:
+      - "8545:8545"
+      - "9000:9000"
+    volumes:
+      - /mnt/data:/data
+    depends_on:
+      cache:
+        condition: service_started
+    labels: !reset null
+  db: !override
+    image: postgres:16
+  cache:
+    image: redis
+"#,
+    )
+    .unwrap();
+
+    let config = ComposeParser::new()
+        .parse_from_paths([&base, &prod])
+        .unwrap();
+    assert_eq!(config.services.len(), 3);
+
+    let node = &config.services["node"];
+    // Scalars and commands are replaced
+    assert_eq!(node.image.as_deref(), Some("node:2.0"));
+    assert_eq!(
+        node.command,
+        Some(vec!["node".to_string(), "--release".to_string()])
+    );
+
+    // Environment is merged by key, even when the base uses the list syntax
+    let env = node.environment.as_ref().unwrap();
+    assert_eq!(env.get("LOG_LEVEL").map(String::as_str), Some("info"));
+    assert_eq!(env.get("NETWORK").map(String::as_str), Some("mainnet"));
+
+    // Ports are combined without duplicates
+    assert_eq!(
+        node.ports,
+        Some(vec![
+            PortMapping::new(8545, 8545),
+            PortMapping::new(9000, 9000)
+        ])
+    );
+
+    // Volumes are merged by their target
+    let volumes = node.volumes.as_ref().unwrap();
+    assert_eq!(volumes.len(), 2);
+    assert!(volumes.iter().any(|v| matches!(
+        v,
+        Volume::Bind { source, target, .. } if source == "./config" && target == "/config"
+    )));
+    assert!(volumes.iter().any(|v| matches!(
+        v,
+        Volume::Bind { source, target, .. } if source == "/mnt/data" && target == "/data"
+    )));
+
+    let dependencies: Vec<_> = node.depends_on.as_ref().unwrap().names().collect();
+    assert_eq!(dependencies, vec!["cache", "db"]);
+    assert!(node.labels.is_none());
+
+    // `!override` replaces the service instead of merging it
+    let db = &config.services["db"];
+    assert_eq!(db.image.as_deref(), Some("postgres:16"));
+    assert!(db.environment.is_none());
+
+    // A single path behaves like `parse_from_path`
+    let config = ComposeParser::new().parse_from_path(&base).unwrap();
+    assert_eq!(config.services["node"].image.as_deref(), Some("node:1.0"));
+
+    let empty: [&str; 0] = [];
+    assert!(ComposeParser::new().parse_from_paths(empty).is_err());
+}
+
+#[test]
+fn test_merge_bare_environment_keys() {
+    use super::merge::merge_compose;
+
+    let base: serde_yaml::Value = serde_yaml::from_str(
+        r"
+services:
+  node:
+    environment:
+      - TOKEN=default
+      - HOST_ONLY
+      - LOG_LEVEL=info
+    labels:
+      - tier=backend
+",
+    )
+    .unwrap();
+    let override_: serde_yaml::Value = serde_yaml::from_str(
+        r"
+services:
+  node:
+    environment:
+      - TOKEN
+      - PASSED_THROUGH
+      - LOG_LEVEL=debug
+    labels:
+      - monitored
+",
+    )
+    .unwrap();
+
+    let merged = merge_compose(base, override_);
+    let node = &merged["services"]["node"];
+    let expected: serde_yaml::Value = serde_yaml::from_str(
+        "{ TOKEN: null, HOST_ONLY: null, LOG_LEVEL: debug, PASSED_THROUGH: null }",
+    )
+    .unwrap();
+    assert_eq!(node["environment"], expected);
+    let expected: serde_yaml::Value =
+        serde_yaml::from_str("{ tier: backend, monitored: '' }").unwrap();
+    assert_eq!(node["labels"], expected);
+
+    // Keys without a value are not set, like bare keys in a list
+    let service: Service = serde_yaml::from_value(node.clone()).unwrap();
+    let environment = service.environment.unwrap();
+    assert_eq!(
+        environment.get("LOG_LEVEL").map(String::as_str),
+        Some("debug")
+    );
+    assert!(!environment.contains_key("TOKEN"));
+    assert!(!environment.contains_key("PASSED_THROUGH"));
+}
+
+#[test]
+fn test_bare_environment_keys() {
+    let content = r"
+version: '3'
+services:
+  list:
+    image: alpine
+    environment:
+      - TOKEN
+      - UNDEFINED
+      - LOG_LEVEL=info
+  mapping:
+    image: alpine
+    environment:
+      TOKEN:
+      UNDEFINED:
+      LOG_LEVEL: info
+";
+    let config = ComposeParser::new()
+        .env_vars(HashMap::from([("TOKEN".to_string(), "secret".to_string())]))
+        .parse(&mut content.as_bytes())
+        .unwrap();
+
+    for name in ["list", "mapping"] {
+        let environment = config.services[name].environment.as_ref().unwrap();
+        assert_eq!(environment.get("TOKEN").map(String::as_str), Some("secret"));
+        assert_eq!(
+            environment.get("LOG_LEVEL").map(String::as_str),
+            Some("info")
+        );
+        assert!(!environment.contains_key("UNDEFINED"));
+    }
+}
+
+#[test]
+fn test_include() {
+    let dir = tempfile::tempdir().unwrap();
+    let shared = dir.path().join("shared");
+    fs::create_dir(&shared).unwrap();
+    fs::write(
+        shared.join("db.yml"),
+        r#"
+services:
+  db:
+    image: "postgres:${PG_VERSION:-15}"
+    volumes:
+      - ./init:/docker-entrypoint-initdb.d
+      - db-data:/var/lib/postgresql/data
+volumes:
+  db-data: {}
+"#,
+    )
+    .unwrap();
+    fs::write(shared.join("db.env"), "PG_VERSION=16\n").unwrap();
+
+    let main = dir.path().join("docker-compose.yml");
+    fs::write(
+        &main,
+        r#"
+version: "3.8"
+include:
+  - path: shared/db.yml
+    env_file: shared/db.env
+services:
+  app:
+    image: app
+    depends_on:
+      - db
+"#,
+    )
+    .unwrap();
+
+    let config = ComposeParser::new().parse_from_path(&main).unwrap();
+    assert_eq!(config.services.len(), 2);
+    assert!(config.volumes.contains_key("db-data"));
+
+    let db = &config.services["db"];
+    assert_eq!(db.image.as_deref(), Some("postgres:16"));
+    // Relative paths are resolved against the included file's directory
+    let init = std::path::absolute(shared.join("init")).unwrap();
+    assert!(db.volumes.as_ref().unwrap().iter().any(|v| matches!(
+        v,
+        Volume::Bind { source, .. } if std::path::Path::new(source) == init
+    )));
+
+    // Included resources may not redefine existing ones
+    let conflicting = dir.path().join("conflicting.yml");
+    fs::write(
+        &conflicting,
+        r#"
+version: "3.8"
+include:
+  - shared/db.yml
+services:
+  db:
+    image: mysql
+"#,
+    )
+    .unwrap();
+    assert!(ComposeParser::new().parse_from_path(&conflicting).is_err());
+
+    // Including a file that includes itself is an error
+    let cyclic = dir.path().join("cyclic.yml");
+    fs::write(&cyclic, "include:\n  - cyclic.yml\nservices: {}\n").unwrap();
+    assert!(ComposeParser::new().parse_from_path(&cyclic).is_err());
+}
+
+#[test]
+fn test_extends() {
+    let dir = tempfile::tempdir().unwrap();
+    let common = dir.path().join("common");
+    fs::create_dir(&common).unwrap();
+    fs::write(
+        common.join("services.yml"),
+        r"
+services:
+  base:
+    image: node
+    env_file: node.env
+    environment:
+      LOG_LEVEL: info
+    cap_add:
+      - NET_ADMIN
+",
+    )
+    .unwrap();
+
+    let main = dir.path().join("docker-compose.yml");
+    fs::write(
+        &main,
+        r#"
+version: "3.8"
+services:
+  node:
+    extends:
+      file: common/services.yml
+      service: base
+    environment:
+      NETWORK: mainnet
+  archive:
+    extends: node
+    command: ["node", "--archive"]
+    environment:
+      LOG_LEVEL: debug
+"#,
+    )
+    .unwrap();
+
+    let config = ComposeParser::new().parse_from_path(&main).unwrap();
+
+    let node = &config.services["node"];
+    assert_eq!(node.image.as_deref(), Some("node"));
+    assert_eq!(node.cap_add, Some(vec!["NET_ADMIN".to_string()]));
+    let env = node.environment.as_ref().unwrap();
+    assert_eq!(env.get("LOG_LEVEL").map(String::as_str), Some("info"));
+    assert_eq!(env.get("NETWORK").map(String::as_str), Some("mainnet"));
+    // Paths from the other file are resolved against its directory
+    let env_file = std::path::absolute(common.join("node.env")).unwrap();
+    assert_eq!(
+        node.env_file,
+        Some(vec![EnvFile::new(env_file.to_string_lossy())])
+    );
+
+    // Services extending services that extend others get every layer
+    let archive = &config.services["archive"];
+    assert_eq!(archive.image.as_deref(), Some("node"));
+    assert_eq!(
+        archive.command,
+        Some(vec!["node".to_string(), "--archive".to_string()])
+    );
+    let env = archive.environment.as_ref().unwrap();
+    assert_eq!(env.get("LOG_LEVEL").map(String::as_str), Some("debug"));
+    assert_eq!(env.get("NETWORK").map(String::as_str), Some("mainnet"));
+
+    let cyclic = r#"
+version: "3.8"
+services:
+  a:
+    extends: b
+  b:
+    extends: a
+"#;
+    assert!(ComposeParser::new().parse(&mut cyclic.as_bytes()).is_err());
+
+    let missing = r#"
+version: "3.8"
+services:
+  a:
+    extends: missing
+"#;
+    assert!(ComposeParser::new().parse(&mut missing.as_bytes()).is_err());
+}
+
+#[test]
+fn test_anchors_and_extensions() {
+    let content = r#"
+version: "3.8"
+x-logging: &logging
+  driver: json-file
+x-defaults: &defaults
+  restart: unless-stopped
+  environment:
+    LOG_LEVEL: info
+  x-team: infra
+x-node: &node
+  <<: *defaults
+  image: node
+services:
+  node:
+    <<: *node
+    environment:
+      LOG_LEVEL: debug
+    x-logging: *logging
+  archive:
+    <<: [*node, *defaults]
+    command: ["node", "--archive"]
+"#;
+
+    let (config, warnings) = ComposeParser::new()
+        .parse_with_warnings(&mut content.as_bytes())
+        .unwrap();
+    assert!(warnings.is_empty());
+
+    // Merge keys are resolved, including merges of blocks that merge others
+    let node = &config.services["node"];
+    assert_eq!(node.image.as_deref(), Some("node"));
+    assert_eq!(node.restart.as_deref(), Some("unless-stopped"));
+    // Keys of the service itself take precedence over merged ones
+    let env = node.environment.as_ref().unwrap();
+    assert_eq!(env.get("LOG_LEVEL").map(String::as_str), Some("debug"));
+
+    let archive = &config.services["archive"];
+    assert_eq!(archive.image.as_deref(), Some("node"));
+    let env = archive.environment.as_ref().unwrap();
+    assert_eq!(env.get("LOG_LEVEL").map(String::as_str), Some("info"));
+
+    // Extension fields are kept on both the config and its services
+    assert_eq!(
+        config.extensions.keys().collect::<Vec<_>>(),
+        vec!["x-defaults", "x-logging", "x-node"]
+    );
+    assert_eq!(
+        config.extensions["x-logging"]["driver"].as_str(),
+        Some("json-file")
+    );
+    assert_eq!(node.extensions["x-team"].as_str(), Some("infra"));
+    assert_eq!(
+        node.extensions["x-logging"]["driver"].as_str(),
+        Some("json-file")
+    );
+    assert_eq!(archive.extensions["x-team"].as_str(), Some("infra"));
+
+    // Only extension fields are kept, unsupported keys are not
+    let content = r#"
+version: "3.8"
+services:
+  app:
+    image: nginx
+    scale: 2
+    x-owner: web
+"#;
+    let config: ComposeConfig = serde_yaml::from_str(content).unwrap();
+    let app = &config.services["app"];
+    assert_eq!(app.extensions.len(), 1);
+    assert_eq!(app.extensions["x-owner"].as_str(), Some("web"));
+
+    // Extension fields are serialized back as-is
+    let serialized = serde_yaml::to_string(&config).unwrap();
+    let reparsed: ComposeConfig = serde_yaml::from_str(&serialized).unwrap();
+    assert_eq!(reparsed.services["app"].extensions, app.extensions);
+
+    let invalid = r#"
+version: "3.8"
+services:
+  app:
+    <<: nginx
+"#;
+    assert!(ComposeParser::new().parse(&mut invalid.as_bytes()).is_err());
+}
+
+#[test]
+fn test_profiles() {
+    let content = r#"
+version: "3.8"
+services:
+  node:
+    image: node
+  db:
+    image: postgres
+    profiles: [storage]
+  explorer:
+    image: explorer
+    profiles: [debug]
+    depends_on:
+      - node
+      - db
+  metrics:
+    image: prometheus
+    profiles: [debug, monitoring]
+"#;
+
+    let config: ComposeConfig = serde_yaml::from_str(content).unwrap();
+    assert_eq!(
+        config.services["metrics"].profiles,
+        Some(vec!["debug".to_string(), "monitoring".to_string()])
+    );
+    assert!(config.services["node"].is_enabled::<&str>(&[]));
+    assert!(!config.services["metrics"].is_enabled::<&str>(&[]));
+    assert!(config.services["metrics"].is_enabled(&["monitoring"]));
+
+    let selected = |profiles: &[&str], services: &[&str]| {
+        config
+            .select_services(profiles, services)
+            .unwrap()
+            .into_iter()
+            .collect::<Vec<_>>()
+    };
+
+    // Services in inactive profiles are skipped
+    assert_eq!(selected(&[], &[]), vec!["node"]);
+    assert_eq!(selected(&["monitoring"], &[]), vec!["metrics", "node"]);
+    assert_eq!(
+        selected(&["debug", "storage"], &[]),
+        vec!["db", "explorer", "metrics", "node"]
+    );
+    // Requested services are selected regardless of profiles, along with their dependencies
+    assert_eq!(
+        selected(&["storage"], &["explorer"]),
+        vec!["db", "explorer", "node"]
+    );
+    assert_eq!(
+        selected(&[], &["explorer", "db"]),
+        vec!["db", "explorer", "node"]
+    );
+    assert_eq!(selected(&["monitoring"], &["db"]), vec!["db"]);
+
+    assert!(config.select_services::<&str>(&[], &["missing"]).is_err());
+}
+
+#[test]
+fn test_profiles_inactive_dependency() {
+    let content = r#"
+version: "3.8"
+services:
+  db:
+    image: postgres
+    profiles: [storage]
+  cache:
+    image: redis
+    profiles: [cache]
+  explorer:
+    image: explorer
+    profiles: [debug]
+    depends_on:
+      db:
+        condition: service_started
+      cache:
+        condition: service_started
+        required: false
+"#;
+
+    let config: ComposeConfig = serde_yaml::from_str(content).unwrap();
+
+    // Dependencies in inactive profiles are not started implicitly
+    let err = config.select_services(&["debug"], &[]).unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("'explorer' depends on service 'db'")
+    );
+    assert!(config.select_services::<&str>(&[], &["explorer"]).is_err());
+
+    // Optional dependencies in inactive profiles are skipped
+    let selected = config
+        .select_services(&["debug"], &["explorer", "db"])
+        .unwrap();
+    assert_eq!(
+        selected.into_iter().collect::<Vec<_>>(),
+        vec!["db", "explorer"]
+    );
+}
+
+#[test]
+fn test_secrets_and_configs_parsing() {
+    let content = r#"
+version: "3.8"
+services:
+  validator:
+    image: validator
+    secrets:
+      - validator_key
+      - source: jwt
+        target: /jwt/secret
+        uid: "1000"
+        gid: "1000"
+        mode: 0440
+    configs:
+      - node_config
+secrets:
+  validator_key:
+    file: ./keys/validator.key
+  jwt:
+    environment: JWT_SECRET
+  external_key:
+    external: true
+    name: shared_key
+configs:
+  node_config:
+    content: |
+      log_level = "info"
+"#;
+
+    let (config, warnings) = ComposeParser::new()
+        .parse_with_warnings(&mut content.as_bytes())
+        .unwrap();
+    assert!(warnings.is_empty());
+
+    assert_eq!(
+        config.secrets["validator_key"].file.as_deref(),
+        Some("./keys/validator.key")
+    );
+    assert_eq!(
+        config.secrets["jwt"].environment.as_deref(),
+        Some("JWT_SECRET")
+    );
+    assert!(config.secrets["external_key"].external);
+    assert_eq!(
+        config.configs["node_config"].content.as_deref(),
+        Some("log_level = \"info\"\n")
+    );
+
+    let validator = &config.services["validator"];
+    let secrets = validator.secrets.as_ref().unwrap();
+    assert_eq!(secrets[0], FileObjectReference::new("validator_key"));
+    assert_eq!(secrets[1].target.as_deref(), Some("/jwt/secret"));
+    assert_eq!(secrets[1].uid.as_deref(), Some("1000"));
+    assert_eq!(secrets[1].mode, Some(0o440));
+    assert_eq!(
+        validator.configs,
+        Some(vec![FileObjectReference::new("node_config")])
+    );
+
+    let dir = tempfile::tempdir().unwrap();
+    assert_eq!(
+        config.configs["node_config"]
+            .load("node_config", dir.path())
+            .unwrap(),
+        b"log_level = \"info\"\n"
+    );
+    assert!(
+        config.secrets["external_key"]
+            .load("external_key", dir.path())
+            .is_err()
+    );
+}
+
+#[test]
+fn test_to_compose_yaml() {
+    let mut config = ComposeParser::new()
+        .parse_from_path(get_reth_archive_compose())
+        .unwrap();
+    config.collect_volumes();
+
+    let service_name = config.services.keys().next().unwrap().clone();
+    let service = config.services.get_mut(&service_name).unwrap();
+    service.requirements = Some(SystemRequirements {
+        min_memory_gb: 16,
+        min_disk_gb: 500,
+        min_bandwidth_mbps: 100,
+        required_ports: vec![8545],
+        data_directory: "/data".to_string(),
+        cpu_limit: Some(4.0),
+        memory_limit: Some("8G".to_string()),
+        memory_swap: None,
+        memory_reservation: None,
+        cpu_shares: None,
+        cpuset_cpus: None,
+    });
+
+    let yaml = config.to_compose_yaml().unwrap();
+    // The output is deterministic
+    assert_eq!(yaml, config.to_compose_yaml().unwrap());
+    assert!(yaml.starts_with("version:"));
+    assert!(!yaml.contains(": null"));
+
+    let value: serde_yaml::Value = serde_yaml::from_str(&yaml).unwrap();
+    let service = &value["services"][service_name.as_str()];
+    assert!(service.get("requirements").is_none());
+    assert_eq!(service["cpus"].as_f64(), Some(4.0));
+    assert_eq!(service["mem_limit"].as_str(), Some("8G"));
+    assert_eq!(service["x-requirements"]["min_disk_gb"].as_u64(), Some(500));
+
+    // Top-level volumes are definitions, not service volume strings
+    for (name, volume) in value["volumes"].as_mapping().unwrap() {
+        assert!(volume.is_mapping(), "volume {:?} is not a mapping", name);
+    }
+
+    // The output parses back to the same services
+    let reparsed = ComposeParser::new().parse(&mut yaml.as_bytes()).unwrap();
+    assert_eq!(reparsed.services.len(), config.services.len());
+    let original = &config.services[&service_name];
+    let service = &reparsed.services[&service_name];
+    assert_eq!(service.image, original.image);
+    assert_eq!(service.command, original.command);
+    assert_eq!(service.ports, original.ports);
+    assert_eq!(service.cpus, Some(4.0));
+    assert!(service.extensions.contains_key("x-requirements"));
+
+    // Health checks use the compose syntax, with durations as strings
+    config.services.get_mut(&service_name).unwrap().healthcheck = Some(HealthCheck {
+        endpoint: "http://localhost:8545".to_string(),
+        method: Method::Get,
+        expected_status: 200,
+        body: None,
+        interval: Duration::from_secs(30),
+        timeout: Duration::from_millis(1500),
+        retries: 3,
+    });
+    let yaml = config.to_compose_yaml().unwrap();
+    let value: serde_yaml::Value = serde_yaml::from_str(&yaml).unwrap();
+    let healthcheck = &value["services"][service_name.as_str()]["healthcheck"];
+    assert_eq!(healthcheck["test"][0].as_str(), Some("CMD-SHELL"));
+    assert_eq!(healthcheck["interval"].as_str(), Some("30s"));
+    assert_eq!(healthcheck["timeout"].as_str(), Some("1500ms"));
+    assert_eq!(healthcheck["retries"].as_u64(), Some(3));
+}
+
+#[cfg(feature = "deploy")]
+#[test]
+fn test_network_ipam_validation() {
+    use crate::config::{NetworkConfig, NetworkIpam, NetworkIpamPool};
+
+    let network = |enable_ipv6: bool, pools: Vec<NetworkIpamPool>| NetworkConfig {
+        enable_ipv6,
+        ipam: Some(NetworkIpam {
+            config: Some(pools),
+            ..NetworkIpam::default()
+        }),
+        ..NetworkConfig::default()
+    };
+    let pool = |subnet: &str| NetworkIpamPool {
+        subnet: Some(subnet.to_string()),
+        ..NetworkIpamPool::default()
+    };
+
+    let valid = network(
+        true,
+        vec![
+            NetworkIpamPool {
+                ip_range: Some("172.30.5.0/24".to_string()),
+                gateway: Some("172.30.0.1".to_string()),
+                aux_addresses: Some(HashMap::from([(
+                    "router".to_string(),
+                    "172.30.0.2".to_string(),
+                )])),
+                ..pool("172.30.0.0/16")
+            },
+            pool("fd00:30::/64"),
+        ],
+    );
+    assert!(valid.validate().is_ok());
+
+    let invalid = [
+        network(false, vec![pool("172.30.0.0/33")]),
+        network(false, vec![pool("not-a-subnet")]),
+        network(
+            false,
+            vec![NetworkIpamPool {
+                gateway: Some("10.0.0.1".to_string()),
+                ..pool("172.30.0.0/16")
+            }],
+        ),
+        network(
+            false,
+            vec![NetworkIpamPool {
+                ip_range: Some("172.31.0.0/24".to_string()),
+                ..pool("172.30.0.0/16")
+            }],
+        ),
+        network(
+            false,
+            vec![NetworkIpamPool {
+                gateway: Some("172.30.0.1".to_string()),
+                ..NetworkIpamPool::default()
+            }],
+        ),
+        network(false, vec![pool("172.30.0.0/16"), pool("172.30.1.0/24")]),
+        network(false, vec![pool("fd00:30::/64")]),
+    ];
+    for config in invalid {
+        assert!(
+            matches!(config.validate(), Err(DockerError::InvalidIpamConfig(_))),
+            "{:?} should be invalid",
+            config.ipam
+        );
+    }
+}
@@ -1,93 +1,418 @@
-use regex::Regex;
+use crate::error::DockerError;
 use std::collections::HashMap;
-use std::sync::LazyLock;
+
+/// Parses an environment file into a [`HashMap`] of key-value pairs
+///
+/// Variables referenced in values are resolved against the earlier variables of the file, see
+/// [`parse_env_file_with_lookup`] to also resolve them against other variables.
+///
+/// # Errors
+///
+/// See [`parse_env_file_with_lookup`]
+pub fn parse_env_file(content: &str) -> Result<HashMap<String, String>, DockerError> {
+    parse_env_file_with_lookup(content, &HashMap::new())
+}
 
 /// Parses an environment file into a [`HashMap`] of key-value pairs
 ///
 /// # Format
 ///
-/// The following formats are supported:
-/// - Comments (lines starting with #)
-/// - Empty lines (ignored)
-/// - KEY=value format
-/// - Quoted values (quotes are stripped)
+/// The file follows the compose rules:
+/// - Empty lines and lines starting with `#` are ignored
+/// - Each line is a `KEY=value` pair, optionally preceded by `export`
+/// - A `KEY` without a value takes its value from `lookup`, and is skipped if it isn't there
+/// - Unquoted values are trimmed, and a `#` preceded by a space starts a comment
+/// - Single-quoted values are used literally, only `\'` is unescaped
+/// - Double-quoted values support the `\n`, `\r`, `\t`, `\\`, `\"` and `\$` escapes
+/// - Quoted values can span multiple lines, and may be followed by a comment
+/// - Unquoted and double-quoted values are interpolated like compose files, see
+///   [`substitute_env_vars`]. Variables defined earlier in the file take precedence over the
+///   ones from `lookup`.
 ///
 /// Variable names must:
 /// - Start with a letter or underscore
 /// - Contain only alphanumeric characters and underscores
-pub fn parse_env_file(content: &str) -> HashMap<String, String> {
+///
+/// # Errors
+///
+/// Returns `DockerError::EnvFileError` with the line number if a variable name is invalid, a
+/// quoted value is not terminated or is followed by other characters, or a value can't be
+/// interpolated.
+pub fn parse_env_file_with_lookup(
+    content: &str,
+    lookup: &HashMap<String, String>,
+) -> Result<HashMap<String, String>, DockerError> {
     let mut vars = HashMap::new();
-    let valid_key = Regex::new(r"^[a-zA-Z_][a-zA-Z0-9_]*$").unwrap();
-
-    for line in content.lines() {
-        let line = line.trim();
+    // The variables used for interpolation, where the file's own definitions take precedence
+    let mut scope = lookup.clone();
+    let lines: Vec<&str> = content.lines().collect();
+    let mut index = 0;
+
+    while index < lines.len() {
+        let line_number = index + 1;
+        let error = |message: String| DockerError::EnvFileError {
+            line: line_number,
+            message,
+        };
+
+        let line = lines[index].trim();
+        index += 1;
         if line.is_empty() || line.starts_with('#') {
             continue;
         }
 
-        if let Some((key, value)) = line.split_once('=') {
-            let key = key.trim();
-            if valid_key.is_match(key) {
-                vars.insert(key.to_string(), value.trim().trim_matches('"').to_string());
+        let line = line
+            .strip_prefix("export")
+            .filter(|rest| rest.starts_with([' ', '\t']))
+            .map_or(line, str::trim_start);
+
+        // A `=` in the comment after a bare key doesn't make it an assignment
+        let uncommented = strip_comment(line);
+        let Some((key, value)) = line.split_once('=').filter(|_| uncommented.contains('=')) else {
+            let key = uncommented;
+            if !is_valid_name(key) {
+                return Err(error(format!("invalid variable name '{}'", key)));
+            }
+            if let Some(value) = lookup.get(key) {
+                vars.insert(key.to_string(), value.clone());
+            }
+            continue;
+        };
+
+        let key = key.trim();
+        if !is_valid_name(key) {
+            return Err(error(format!("invalid variable name '{}'", key)));
+        }
+
+        let value = value.trim_start();
+        let template = match value.chars().next() {
+            Some(quote @ ('\'' | '"')) => {
+                let mut raw = String::new();
+                let mut rest = &value[1..];
+                let rest = loop {
+                    if let Some(close) = closing_quote(rest, quote) {
+                        raw.push_str(&rest[..close]);
+                        break &rest[close + 1..];
+                    }
+                    raw.push_str(rest);
+                    raw.push('\n');
+                    rest = lines
+                        .get(index)
+                        .ok_or_else(|| error(format!("unterminated quoted value for {}", key)))?;
+                    index += 1;
+                };
+
+                let rest = rest.trim();
+                if !rest.is_empty() && !rest.starts_with('#') {
+                    return Err(error(format!(
+                        "unexpected characters after the quoted value of {}",
+                        key
+                    )));
+                }
+
+                if quote == '\'' {
+                    let value = raw.replace("\\'", "'");
+                    scope.insert(key.to_string(), value.clone());
+                    vars.insert(key.to_string(), value);
+                    continue;
+                }
+                unescape(&raw)
+            }
+            _ => strip_comment(value).to_string(),
+        };
+
+        let value = substitute_env_vars(&template, &scope).map_err(|e| match e {
+            DockerError::InterpolationError { message, .. } => {
+                error(format!("{} in {}", message, key))
+            }
+            e => e,
+        })?;
+        scope.insert(key.to_string(), value.clone());
+        vars.insert(key.to_string(), value);
+    }
+
+    Ok(vars)
+}
+
+/// Removes a trailing comment, which starts with a `#` preceded by whitespace
+fn strip_comment(value: &str) -> &str {
+    let comment = value
+        .char_indices()
+        .find(|&(pos, c)| c == '#' && value[..pos].ends_with([' ', '\t']))
+        .map_or(value.len(), |(pos, _)| pos);
+    value[..comment].trim_end()
+}
+
+/// Finds the unescaped `quote` closing a quoted value
+fn closing_quote(value: &str, quote: char) -> Option<usize> {
+    let mut escaped = false;
+    for (pos, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            c if c == quote => return Some(pos),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Resolves the escapes of a double-quoted value, keeping escaped `$` out of interpolation
+fn unescape(raw: &str) -> String {
+    let mut result = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => result.push('\n'),
+            Some('r') => result.push('\r'),
+            Some('t') => result.push('\t'),
+            Some('$') => result.push_str("$$"),
+            Some(c @ ('\\' | '"')) => result.push(c),
+            Some(c) => {
+                result.push('\\');
+                result.push(c);
             }
+            None => result.push('\\'),
         }
     }
 
-    vars
+    result
+}
+
+fn is_valid_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name_end(name.as_bytes(), 0, name.len()) == name.len()
 }
 
 /// Substitutes environment variables in a string
 ///
-/// Supports the following formats:
-/// - ${VAR}
-/// - ${VAR:-default}
-/// - $VAR
-pub fn substitute_env_vars(content: &str, env_vars: &HashMap<String, String>) -> String {
-    static VAR_DEFAULT_SYNTAX: LazyLock<Regex> =
-        LazyLock::new(|| Regex::new(r"\$\{([^{}:]+):-([^{}]*)\}").unwrap());
-    static VAR_CURLY_SYNTAX: LazyLock<Regex> =
-        LazyLock::new(|| Regex::new(r"\$\{([^{}]+)\}").unwrap());
-    static VAR_SYNTAX: LazyLock<Regex> =
-        LazyLock::new(|| Regex::new(r"\$([a-zA-Z_][a-zA-Z0-9_]*)").unwrap());
-
-    let mut result = content.to_string();
-
-    // Handle ${VAR:-default} syntax
-    result = VAR_DEFAULT_SYNTAX
-        .replace_all(&result, |caps: &regex::Captures<'_>| {
-            let var_name = caps.get(1).unwrap().as_str();
-            let default_value = caps.get(2).unwrap().as_str();
-            match env_vars.get(var_name) {
-                Some(value) if value.is_empty() => default_value.to_string(),
-                Some(value) => value.to_string(),
-                None => default_value.to_string(),
+/// Follows the compose interpolation rules, in a single pass so substituted values are never
+/// interpolated again:
+/// - `$VAR` and `${VAR}` are replaced with the value of `VAR`, or nothing if it is unset
+/// - `${VAR:-default}` uses `default` if `VAR` is unset or empty, `${VAR-default}` only if unset
+/// - `${VAR:?error}` fails if `VAR` is unset or empty, `${VAR?error}` only if unset
+/// - `${VAR:+replacement}` uses `replacement` if `VAR` is set and not empty, `${VAR+replacement}`
+///   if it is set
+/// - `$$` is a literal `$`
+///
+/// Defaults, replacements and error messages can themselves contain variables, such as
+/// `${A:-${B:-c}}`.
+///
+/// # Errors
+///
+/// Returns `DockerError::InterpolationError` with the variable and its location if a required
+/// variable is missing, or an expression is malformed.
+pub fn substitute_env_vars(
+    content: &str,
+    env_vars: &HashMap<String, String>,
+) -> Result<String, DockerError> {
+    interpolate(content, env_vars).map(|(result, _)| result)
+}
+
+/// Substitutes environment variables in a string, see [`substitute_env_vars`]
+///
+/// Also returns the names of the unset variables that were referenced without a default, such
+/// as `$VAR` or `${VAR}`, and replaced with an empty string.
+///
+/// # Errors
+///
+/// See [`substitute_env_vars`]
+pub(crate) fn interpolate(
+    content: &str,
+    env_vars: &HashMap<String, String>,
+) -> Result<(String, Vec<String>), DockerError> {
+    let mut interpolation = Interpolation {
+        content,
+        env_vars,
+        unset: Vec::new(),
+    };
+    let mut result = String::with_capacity(content.len());
+    interpolation.interpolate(0, content.len(), &mut result)?;
+    Ok((result, interpolation.unset))
+}
+
+/// The modifier of a `${VAR<modifier>word}` expression
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Modifier {
+    Default,
+    Required,
+    Replacement,
+}
+
+struct Interpolation<'a> {
+    content: &'a str,
+    env_vars: &'a HashMap<String, String>,
+    /// Unset variables referenced without a default
+    unset: Vec<String>,
+}
+
+impl Interpolation<'_> {
+    /// Interpolates `content[start..end]` into `out`
+    fn interpolate(
+        &mut self,
+        start: usize,
+        end: usize,
+        out: &mut String,
+    ) -> Result<(), DockerError> {
+        let bytes = self.content.as_bytes();
+        let mut pos = start;
+        let mut literal_start = start;
+
+        while pos < end {
+            if bytes[pos] != b'$' {
+                pos += 1;
+                continue;
             }
-        })
-        .to_string();
-
-    // Handle ${VAR} syntax
-    result = VAR_CURLY_SYNTAX
-        .replace_all(&result, |caps: &regex::Captures<'_>| {
-            let var_name = caps.get(1).unwrap().as_str();
-            env_vars
-                .get(var_name)
-                .map_or("", String::as_str)
-                .to_string()
-        })
-        .to_string();
-
-    // Handle $VAR syntax
-    result = VAR_SYNTAX
-        .replace_all(&result, |caps: &regex::Captures<'_>| {
-            let var_name = caps.get(1).unwrap().as_str();
-            env_vars
-                .get(var_name)
-                .map_or("", String::as_str)
-                .to_string()
-        })
-        .to_string();
+            out.push_str(&self.content[literal_start..pos]);
+
+            let next = if pos + 1 < end {
+                Some(bytes[pos + 1])
+            } else {
+                None
+            };
+            pos = match next {
+                Some(b'$') => {
+                    out.push('$');
+                    pos + 2
+                }
+                Some(b'{') => {
+                    let close = self.find_closing(pos + 2, end).ok_or_else(|| {
+                        self.error(pos, &self.content[pos..end], "missing closing brace")
+                    })?;
+                    self.expand(pos, close, out)?;
+                    close + 1
+                }
+                Some(c) if c.is_ascii_alphabetic() || c == b'_' => {
+                    let name_end = name_end(bytes, pos + 1, end);
+                    self.push_value(&self.content[pos + 1..name_end], out);
+                    name_end
+                }
+                // A lone `$` is kept as is
+                _ => {
+                    out.push('$');
+                    pos + 1
+                }
+            };
+            literal_start = pos;
+        }
 
-    result
+        out.push_str(&self.content[literal_start..end]);
+        Ok(())
+    }
+
+    /// Finds the `}` closing a `${` whose content starts at `start`
+    fn find_closing(&self, start: usize, end: usize) -> Option<usize> {
+        let bytes = self.content.as_bytes();
+        let mut depth = 0;
+        let mut pos = start;
+
+        while pos < end {
+            match bytes[pos] {
+                b'$' if pos + 1 < end && bytes[pos + 1] == b'$' => pos += 1,
+                b'$' if pos + 1 < end && bytes[pos + 1] == b'{' => {
+                    depth += 1;
+                    pos += 1;
+                }
+                b'}' if depth == 0 => return Some(pos),
+                b'}' => depth -= 1,
+                _ => {}
+            }
+            pos += 1;
+        }
+
+        None
+    }
+
+    /// Expands the `${...}` expression starting at `dollar` and ending at `close`
+    fn expand(&mut self, dollar: usize, close: usize, out: &mut String) -> Result<(), DockerError> {
+        let bytes = self.content.as_bytes();
+        let expression = &self.content[dollar..=close];
+        let name_start = dollar + 2;
+        let name_end = name_end(bytes, name_start, close);
+        let name = &self.content[name_start..name_end];
+        if name.is_empty() || bytes[name_start].is_ascii_digit() {
+            return Err(self.error(dollar, expression, "invalid variable name"));
+        }
+
+        if name_end == close {
+            self.push_value(name, out);
+            return Ok(());
+        }
+
+        let value = self.env_vars.get(name).map(String::as_str);
+
+        let (colon, operator) = match bytes[name_end] {
+            b':' if name_end + 1 < close => (true, bytes[name_end + 1]),
+            operator => (false, operator),
+        };
+        let modifier = match operator {
+            b'-' => Modifier::Default,
+            b'?' => Modifier::Required,
+            b'+' => Modifier::Replacement,
+            _ => return Err(self.error(dollar, expression, "invalid interpolation format")),
+        };
+        let word_start = name_end + usize::from(colon) + 1;
+
+        // With a colon, empty variables count as unset
+        let value = value.filter(|value| !colon || !value.is_empty());
+        match (modifier, value) {
+            (Modifier::Default | Modifier::Required, Some(value)) => out.push_str(value),
+            (Modifier::Default, None) | (Modifier::Replacement, Some(_)) => {
+                self.interpolate(word_start, close, out)?;
+            }
+            (Modifier::Replacement, None) => {}
+            (Modifier::Required, None) => {
+                let mut message = String::new();
+                self.interpolate(word_start, close, &mut message)?;
+                let message = if message.is_empty() {
+                    format!("required variable {} is missing a value", name)
+                } else {
+                    format!("required variable {} is missing a value: {}", name, message)
+                };
+                return Err(self.error(dollar, name, &message));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Pushes the value of a variable referenced without a modifier, recording it if unset
+    fn push_value(&mut self, name: &str, out: &mut String) {
+        match self.env_vars.get(name) {
+            Some(value) => out.push_str(value),
+            None => self.unset.push(name.to_string()),
+        }
+    }
+
+    fn error(&self, offset: usize, variable: &str, message: &str) -> DockerError {
+        let before = &self.content[..offset];
+        let line = before.matches('\n').count() + 1;
+        let column = before
+            .rfind('\n')
+            .map_or(before, |newline| &before[newline + 1..])
+            .chars()
+            .count()
+            + 1;
+
+        DockerError::InterpolationError {
+            variable: variable.to_string(),
+            location: format!("line {}, column {}", line, column),
+            message: message.to_string(),
+        }
+    }
+}
+
+/// The end of the variable name starting at `start`
+fn name_end(bytes: &[u8], start: usize, end: usize) -> usize {
+    bytes[start..end]
+        .iter()
+        .position(|c| !(c.is_ascii_alphanumeric() || *c == b'_'))
+        .map_or(end, |len| start + len)
 }
 
 #[cfg(test)]
@@ -114,7 +439,7 @@ mod tests {
             value: $SIMPLE_VAR
         ";
 
-        let result = substitute_env_vars(content, &env_vars);
+        let result = substitute_env_vars(content, &env_vars).unwrap();
 
         assert!(result.contains("ethereumoptimism/l2geth:v1.0.0"));
         assert!(result.contains("something:default"));
@@ -137,7 +462,7 @@ mod tests {
               - SIMPLE=$HOST:$PORT
         "#;
 
-        let result = substitute_env_vars(content, &env_vars);
+        let result = substitute_env_vars(content, &env_vars).unwrap();
 
         assert!(result.contains("8545:8545"));
         assert!(result.contains("http://localhost:8545"));
@@ -157,7 +482,7 @@ mod tests {
         let temp_file = NamedTempFile::new().unwrap();
         fs::write(&temp_file, env_content).unwrap();
 
-        let vars = parse_env_file(env_content);
+        let vars = parse_env_file(env_content).unwrap();
 
         assert_eq!(vars.get("EMPTY").unwrap(), "");
         assert_eq!(vars.get("QUOTED").unwrap(), "quoted value");
@@ -185,7 +510,7 @@ mod tests {
               - COMBINED=${VERSION:-0.0.1}-${MEMORY:-256M}
         ";
 
-        let result = substitute_env_vars(content, &env_vars);
+        let result = substitute_env_vars(content, &env_vars).unwrap();
 
         assert!(result.contains("myapp:1.0"));
         assert!(result.contains("memory: 1G"));
@@ -196,23 +521,82 @@ mod tests {
 
     #[test]
     fn test_invalid_env_file() {
-        let env_content = r"
-        VALID_KEY=value
-        INVALID+KEY=value
-        123INVALID=value
-        _VALID=value
-        ALSO-INVALID=value
-        ";
-        let vars = parse_env_file(env_content);
-
+        let line = |content: &str| match parse_env_file(content) {
+            Err(DockerError::EnvFileError { line, .. }) => line,
+            other => panic!("Expected an env file error, got {:?}", other),
+        };
+
+        assert_eq!(line("VALID_KEY=value\nINVALID+KEY=value"), 2);
+        assert_eq!(line("\n\n123INVALID=value"), 3);
+        assert_eq!(line("_VALID=value\n# ALSO-INVALID\nALSO-INVALID=value"), 3);
+        assert_eq!(line("KEY='unterminated\nvalue"), 1);
+        assert_eq!(line("A=1\nKEY=\"value\" trailing"), 2);
+        assert_eq!(line("A=1\n\nKEY=${MISSING:?is required}"), 3);
+
+        let vars = parse_env_file("VALID_KEY=value\n_VALID=value").unwrap();
         assert!(vars.contains_key("VALID_KEY"));
         assert!(vars.contains_key("_VALID"));
-        assert!(!vars.contains_key("INVALID+KEY"));
-        assert!(!vars.contains_key("123INVALID"));
-        assert!(!vars.contains_key("ALSO-INVALID"));
         assert_eq!(vars.len(), 2);
     }
 
+    #[test]
+    fn test_env_file_quoting() {
+        let env_content = r#"
+export EXPORTED=value
+SINGLE='$HOME \t stays ${literal}'
+SINGLE_ESCAPE='Let\'s go'
+DOUBLE="line\nbreak\ttab \"quoted\" \$ESCAPED"
+JSON="{\"hello\": \"json\"}"
+COMMENTED=value # a comment
+NOT_COMMENTED=value#not a comment
+QUOTED_COMMENT="value # not a comment" # a comment
+MULTILINE="first
+second"
+SINGLE_MULTILINE='first
+  second'
+REFERENCE=${EXPORTED}-${SINGLE_ESCAPE}
+DOUBLE_REFERENCE="${EXPORTED:-unused} and ${UNSET:-default}"
+FROM_LOOKUP=${HOST_VAR}
+OVERRIDDEN=file
+USES_OVERRIDDEN=$OVERRIDDEN
+HOST_VAR
+COMMENTED_HOST_VAR # default=1
+MISSING_FROM_LOOKUP
+"#;
+
+        let lookup = HashMap::from([
+            ("HOST_VAR".to_string(), "host".to_string()),
+            ("OVERRIDDEN".to_string(), "lookup".to_string()),
+            ("COMMENTED_HOST_VAR".to_string(), "host".to_string()),
+        ]);
+        let vars = parse_env_file_with_lookup(env_content, &lookup).unwrap();
+        let get = |key: &str| vars.get(key).map(String::as_str);
+
+        assert_eq!(get("EXPORTED"), Some("value"));
+        assert_eq!(get("SINGLE"), Some("$HOME \\t stays ${literal}"));
+        assert_eq!(get("SINGLE_ESCAPE"), Some("Let's go"));
+        assert_eq!(get("DOUBLE"), Some("line\nbreak\ttab \"quoted\" $ESCAPED"));
+        assert_eq!(get("JSON"), Some(r#"{"hello": "json"}"#));
+        assert_eq!(get("COMMENTED"), Some("value"));
+        assert_eq!(get("NOT_COMMENTED"), Some("value#not a comment"));
+        assert_eq!(get("QUOTED_COMMENT"), Some("value # not a comment"));
+        assert_eq!(get("MULTILINE"), Some("first\nsecond"));
+        assert_eq!(get("SINGLE_MULTILINE"), Some("first\n  second"));
+        assert_eq!(get("REFERENCE"), Some("value-Let's go"));
+        assert_eq!(get("DOUBLE_REFERENCE"), Some("value and default"));
+        assert_eq!(get("FROM_LOOKUP"), Some("host"));
+        assert_eq!(get("OVERRIDDEN"), Some("file"));
+        assert_eq!(get("USES_OVERRIDDEN"), Some("file"));
+        assert_eq!(get("HOST_VAR"), Some("host"));
+        assert_eq!(get("COMMENTED_HOST_VAR"), Some("host"));
+        assert_eq!(get("MISSING_FROM_LOOKUP"), None);
+
+        // Later definitions replace earlier ones for the following references, whatever their quoting
+        let vars = parse_env_file("A=1\nA='2'\nB=$A\nA=\"3\"\nC=$A").unwrap();
+        assert_eq!(vars.get("B").map(String::as_str), Some("2"));
+        assert_eq!(vars.get("C").map(String::as_str), Some("3"));
+    }
+
     #[test]
     fn test_empty_and_missing_variables() {
         let mut env_vars = HashMap::new();
@@ -228,7 +612,7 @@ mod tests {
               - WITH_DEFAULT=${UNDEFINED:-default_value}
         ";
 
-        let result = substitute_env_vars(content, &env_vars);
+        let result = substitute_env_vars(content, &env_vars).unwrap();
 
         assert!(
             result.contains("test:default"),
@@ -253,8 +637,83 @@ mod tests {
         TEST2=${OTHER_VAR:-default}
         ";
 
-        let result = substitute_env_vars(content, &env_vars);
+        let result = substitute_env_vars(content, &env_vars).unwrap();
         assert!(result.contains("TEST1="));
         assert!(result.contains("TEST2=default"));
     }
+
+    #[test]
+    fn test_interpolation_modifiers() {
+        let env_vars = HashMap::from([
+            ("SET".to_string(), "value".to_string()),
+            ("EMPTY".to_string(), String::new()),
+        ]);
+        let substitute = |content: &str| substitute_env_vars(content, &env_vars).unwrap();
+
+        assert_eq!(substitute("${SET-default} ${EMPTY-default}"), "value ");
+        assert_eq!(
+            substitute("${UNSET-default} ${EMPTY:-default}"),
+            "default default"
+        );
+        assert_eq!(
+            substitute("${SET:+alt} ${EMPTY:+alt} ${EMPTY+alt}"),
+            "alt  alt"
+        );
+        assert_eq!(substitute("${UNSET+alt}${UNSET:+alt}"), "");
+        assert_eq!(substitute("${SET:?required} ${EMPTY?required}"), "value ");
+        assert_eq!(substitute("${UNSET:-${EMPTY:-${SET}}}"), "value");
+        assert_eq!(substitute("${UNSET:-a-${SET}-b}"), "a-value-b");
+        assert_eq!(substitute("${SET:-${UNSET:?not evaluated}}"), "value");
+    }
+
+    #[test]
+    fn test_interpolation_escapes_and_single_pass() {
+        let env_vars = HashMap::from([
+            ("PRICE".to_string(), "$HOME".to_string()),
+            ("NAME".to_string(), "app".to_string()),
+        ]);
+        let substitute = |content: &str| substitute_env_vars(content, &env_vars).unwrap();
+
+        assert_eq!(substitute("$$NAME $${NAME} $$$NAME"), "$NAME ${NAME} $app");
+        // Substituted values are not interpolated again
+        assert_eq!(substitute("${PRICE} $PRICE"), "$HOME $HOME");
+        assert_eq!(substitute("cost: 5$ $ $1 ${UNSET:-$$}"), "cost: 5$ $ $1 $");
+        assert_eq!(
+            substitute("${NAME}_suffix $NAME.conf"),
+            "app_suffix app.conf"
+        );
+    }
+
+    #[test]
+    fn test_interpolation_errors() {
+        let env_vars = HashMap::from([("EMPTY".to_string(), String::new())]);
+
+        let content =
+            "services:\n  app:\n    image: ${IMAGE:?set IMAGE to the ${KIND:-node} image}";
+        match substitute_env_vars(content, &env_vars) {
+            Err(DockerError::InterpolationError {
+                variable,
+                location,
+                message,
+            }) => {
+                assert_eq!(variable, "IMAGE");
+                assert_eq!(location, "line 3, column 12");
+                assert!(message.contains("set IMAGE to the node image"));
+            }
+            other => panic!("Expected an interpolation error, got {:?}", other),
+        }
+
+        assert!(substitute_env_vars("${EMPTY:?}", &env_vars).is_err());
+        assert!(substitute_env_vars("${EMPTY?}", &env_vars).is_ok());
+        for invalid in ["${UNCLOSED", "${}", "${1VAR}", "${VAR!x}", "${A:-${B}"] {
+            assert!(
+                matches!(
+                    substitute_env_vars(invalid, &env_vars),
+                    Err(DockerError::InterpolationError { .. })
+                ),
+                "{} should be invalid",
+                invalid
+            );
+        }
+    }
 }
@@ -3,7 +3,10 @@ mod common;
 use bollard::container::ListContainersOptions;
 use color_eyre::Result;
 use common::{is_docker_running, with_docker_cleanup};
-use docktopus::{BuildConfig, ComposeConfig, DockerBuilder, Service};
+use docktopus::builder::compose::ComposeDeployOptions;
+use docktopus::config::{NetworkConfig, PortMapping};
+use docktopus::parser::ComposeParser;
+use docktopus::{BuildConfig, ComposeConfig, DockerBuilder, DockerError, Service};
 use std::{collections::HashMap, time::Duration};
 
 #[tokio::test]
@@ -19,7 +22,7 @@ async fn test_compose_deployment() -> Result<()> {
             let network_name = format!("test-network-{}", test_id);
 
             let mut labels = HashMap::new();
-            labels.insert("test_id".to_string(), test_id.to_string());
+            labels.insert("test_id".to_string(), test_id.clone());
 
             // Create network with retry mechanism
             builder
@@ -32,17 +35,17 @@ async fn test_compose_deployment() -> Result<()> {
             env.insert("TEST".to_string(), "value".to_string());
 
             let mut labels = HashMap::new();
-            labels.insert("test_id".to_string(), test_id.to_string());
+            labels.insert("test_id".to_string(), test_id.clone());
 
             let service_name = format!("test-service-{}", test_id);
             services.insert(
                 service_name,
                 Service {
                     image: Some("alpine:latest".to_string()),
-                    ports: Some(vec!["8080:80".to_string()]),
+                    ports: Some(vec![PortMapping::new(8080, 80)]),
                     environment: Some(env.into()),
                     volumes: None,
-                    networks: Some(vec![network_name.clone()]),
+                    networks: Some(vec![network_name.clone()].into()),
                     labels: Some(labels),
                     ..Service::default()
                 },
@@ -52,6 +55,15 @@ async fn test_compose_deployment() -> Result<()> {
                 version: "3".to_string(),
                 services,
                 volumes: HashMap::new(),
+                // The network is created by the test itself
+                networks: HashMap::from([(
+                    network_name.clone(),
+                    NetworkConfig {
+                        external: true,
+                        ..NetworkConfig::default()
+                    },
+                )]),
+                ..ComposeConfig::default()
             };
 
             let container_ids = builder.deploy_compose(&mut config).await?;
@@ -117,19 +129,7 @@ async fn test_compose_with_build() -> Result<()> {
                         context: "./".to_string(),
                         dockerfile: Some("Dockerfile".to_string()),
                     }),
-                    ports: None,
-                    environment: None,
-                    volumes: None,
-                    networks: None,
-                    requirements: None,
-                    depends_on: None,
-                    healthcheck: None,
-                    restart: None,
-                    command: None,
-                    user: None,
-                    labels: None,
-                    platform: None,
-                    env_file: None,
+                    ..Service::default()
                 },
             );
 
@@ -137,6 +137,8 @@ async fn test_compose_with_build() -> Result<()> {
                 version: "3".to_string(),
                 services,
                 volumes: HashMap::new(),
+                networks: HashMap::new(),
+                ..ComposeConfig::default()
             };
 
             let result = builder.deploy_compose(&mut config).await;
@@ -148,3 +150,229 @@ async fn test_compose_with_build() -> Result<()> {
     })
     .await
 }
+
+#[tokio::test]
+async fn test_compose_rollback_on_failure() -> Result<()> {
+    with_docker_cleanup(|test_id| {
+        Box::pin(async move {
+            if !is_docker_running() {
+                println!("Skipping test: Docker is not running");
+                return Ok(());
+            }
+
+            let builder = DockerBuilder::new().await?;
+
+            let mut labels = HashMap::new();
+            labels.insert("test_id".to_string(), test_id.clone());
+
+            // The second service binds the same host port, so it fails to start
+            let first = format!("test-service-{}", test_id);
+            let second = format!("test-service-{}-conflict", test_id);
+            let mut services = HashMap::new();
+            services.insert(
+                first.clone(),
+                Service {
+                    image: Some("alpine:latest".to_string()),
+                    command: Some(vec!["sleep".to_string(), "30".to_string()]),
+                    ports: Some(vec![PortMapping::new(18080, 80)]),
+                    labels: Some(labels.clone()),
+                    ..Service::default()
+                },
+            );
+            services.insert(
+                second.clone(),
+                Service {
+                    image: Some("alpine:latest".to_string()),
+                    command: Some(vec!["sleep".to_string(), "30".to_string()]),
+                    ports: Some(vec![PortMapping::new(18080, 80)]),
+                    depends_on: Some(vec![first.clone()].into()),
+                    labels: Some(labels),
+                    ..Service::default()
+                },
+            );
+
+            let mut config = ComposeConfig {
+                version: "3".to_string(),
+                services,
+                volumes: HashMap::new(),
+                networks: HashMap::new(),
+                ..ComposeConfig::default()
+            };
+
+            let Err(DockerError::DeploymentFailed(failure)) =
+                builder.deploy_compose(&mut config).await
+            else {
+                panic!("Expected the deployment to fail");
+            };
+
+            assert_eq!(failure.service.as_deref(), Some(second.as_str()));
+            assert!(failure.rollback.performed);
+            assert!(failure.rollback.containers.contains(&first));
+            assert!(failure.rollback.containers.contains(&second));
+            assert_eq!(failure.rollback.networks.len(), 1);
+
+            // Nothing created by the deployment should be left behind
+            let mut filters = HashMap::new();
+            filters.insert("label".to_string(), vec![format!("test_id={}", test_id)]);
+            let containers = builder
+                .client()
+                .list_containers(Some(ListContainersOptions {
+                    all: true,
+                    filters,
+                    ..Default::default()
+                }))
+                .await?;
+            assert!(containers.is_empty());
+
+            Ok(())
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_compose_rollback_of_concurrent_services() -> Result<()> {
+    with_docker_cleanup(|test_id| {
+        Box::pin(async move {
+            if !is_docker_running() {
+                println!("Skipping test: Docker is not running");
+                return Ok(());
+            }
+
+            let builder = DockerBuilder::new().await?;
+
+            let mut labels = HashMap::new();
+            labels.insert("test_id".to_string(), test_id.clone());
+
+            // Both services are in the same level and bind the same host port, so one of them
+            // fails while the other one is being deployed
+            let service = Service {
+                image: Some("alpine:latest".to_string()),
+                command: Some(vec!["sleep".to_string(), "30".to_string()]),
+                ports: Some(vec![PortMapping::new(18081, 80)]),
+                labels: Some(labels.clone()),
+                ..Service::default()
+            };
+            let first = format!("test-service-{}-a", test_id);
+            let second = format!("test-service-{}-b", test_id);
+            let mut config = ComposeConfig {
+                version: "3".to_string(),
+                services: HashMap::from([
+                    (first.clone(), service.clone()),
+                    (second.clone(), service.clone()),
+                ]),
+                ..ComposeConfig::default()
+            };
+
+            let Err(DockerError::DeploymentFailed(failure)) =
+                builder.deploy_compose(&mut config).await
+            else {
+                panic!("Expected the deployment to fail");
+            };
+            assert!(failure.rollback.containers.contains(&first));
+            assert!(failure.rollback.containers.contains(&second));
+
+            let mut filters = HashMap::new();
+            filters.insert("label".to_string(), vec![format!("test_id={}", test_id)]);
+            let containers = builder
+                .client()
+                .list_containers(Some(ListContainersOptions {
+                    all: true,
+                    filters,
+                    ..Default::default()
+                }))
+                .await?;
+            assert!(containers.is_empty());
+
+            // An invalid restart policy fails before any resource is created
+            let mut config = ComposeConfig {
+                version: "3".to_string(),
+                services: HashMap::from([
+                    (first.clone(), service.clone()),
+                    (
+                        second.clone(),
+                        Service {
+                            restart: Some("sometimes".to_string()),
+                            ..service
+                        },
+                    ),
+                ]),
+                ..ComposeConfig::default()
+            };
+
+            let Err(DockerError::DeploymentFailed(failure)) =
+                builder.deploy_compose(&mut config).await
+            else {
+                panic!("Expected the deployment to fail");
+            };
+            assert_eq!(failure.service.as_deref(), Some(second.as_str()));
+            assert!(failure.rollback.containers.is_empty());
+            assert!(failure.rollback.networks.is_empty());
+
+            Ok(())
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_compose_profiles_and_service_selection() -> Result<()> {
+    with_docker_cleanup(|test_id| {
+        Box::pin(async move {
+            if !is_docker_running() {
+                println!("Skipping test: Docker is not running");
+                return Ok(());
+            }
+
+            let builder = DockerBuilder::new().await?;
+
+            let compose = format!(
+                r#"
+version: "3.8"
+services:
+  node-{test_id}:
+    image: alpine:latest
+    command: ["sleep", "30"]
+    labels:
+      test_id: "{test_id}"
+  explorer-{test_id}:
+    image: alpine:latest
+    command: ["sleep", "30"]
+    profiles: [debug]
+    depends_on: [node-{test_id}]
+    labels:
+      test_id: "{test_id}"
+  worker-{test_id}:
+    image: alpine:latest
+    command: ["sleep", "30"]
+    labels:
+      test_id: "{test_id}"
+networks:
+  default:
+    labels:
+      test_id: "{test_id}"
+"#
+            );
+            let mut config = ComposeParser::new().parse(&mut compose.as_bytes())?;
+
+            // Selecting a service in an inactive profile pulls in its dependencies only
+            let options = ComposeDeployOptions::new().services([format!("explorer-{}", test_id)]);
+            let container_ids = builder
+                .deploy_compose_with_options(&mut config, std::env::current_dir()?, options)
+                .await?;
+
+            let mut deployed: Vec<_> = container_ids.keys().cloned().collect();
+            deployed.sort();
+            assert_eq!(
+                deployed,
+                vec![format!("explorer-{}", test_id), format!("node-{}", test_id)]
+            );
+
+            // Skipped services are left in the config
+            assert_eq!(config.services.len(), 3);
+
+            Ok(())
+        })
+    })
+    .await
+}
@@ -2,7 +2,7 @@ mod common;
 
 use color_eyre::Result;
 use common::{is_docker_running, with_docker_cleanup};
-use docktopus::config::{HealthCheck, Method};
+use docktopus::config::{HealthCheck, Method, NetworkConfig, PortMapping};
 use docktopus::{ComposeConfig, DockerBuilder, Service};
 use futures_util::TryStreamExt;
 use std::{collections::HashMap, time::Duration};
@@ -20,7 +20,7 @@ async fn test_healthcheck() -> Result<()> {
             let network_name = format!("test-network-{}", test_id);
 
             let mut network_labels = HashMap::new();
-            network_labels.insert("test_id".to_string(), test_id.to_string());
+            network_labels.insert("test_id".to_string(), test_id.clone());
 
             // Create network with retry mechanism
             builder
@@ -52,7 +52,7 @@ async fn test_healthcheck() -> Result<()> {
             // Create a service with healthcheck
             let mut services = HashMap::new();
             let mut labels = HashMap::new();
-            labels.insert("test_id".to_string(), test_id.to_string());
+            labels.insert("test_id".to_string(), test_id.clone());
 
             services.insert(
                 service_name.clone(),
@@ -67,8 +67,8 @@ async fn test_healthcheck() -> Result<()> {
                         timeout: Duration::from_secs(3),
                         retries: 3,
                     }),
-                    ports: Some(vec!["8080:80".to_string()]),
-                    networks: Some(vec![network_name.clone()]),
+                    ports: Some(vec![PortMapping::new(8080, 80)]),
+                    networks: Some(vec![network_name.clone()].into()),
                     labels: Some(labels),
                     ..Default::default()
                 },
@@ -78,6 +78,15 @@ async fn test_healthcheck() -> Result<()> {
                 version: "3".to_string(),
                 services,
                 volumes: HashMap::new(),
+                // The network is created by the test itself
+                networks: HashMap::from([(
+                    network_name.clone(),
+                    NetworkConfig {
+                        external: true,
+                        ..NetworkConfig::default()
+                    },
+                )]),
+                ..ComposeConfig::default()
             };
 
             let container_ids = builder.deploy_compose(&mut config).await?;
@@ -2,6 +2,11 @@ mod common;
 
 use common::with_docker_cleanup;
 use docktopus::DockerBuilder;
+use docktopus::DockerError;
+use docktopus::bollard::container::{Config, CreateContainerOptions};
+use docktopus::builder::management::ResourceFilters;
+use docktopus::config::{NetworkConfig, NetworkIpam, NetworkIpamPool};
+use docktopus::parser::ComposeParser;
 use std::collections::HashMap;
 use std::time::Duration;
 use uuid::Uuid;
@@ -14,7 +19,7 @@ async fn test_network_management() -> color_eyre::Result<()> {
             let network_name = format!("test-network-{}", Uuid::new_v4());
 
             let mut network_labels = HashMap::new();
-            network_labels.insert("test_id".to_string(), test_id.to_string());
+            network_labels.insert("test_id".to_string(), test_id.clone());
 
             // Create network with retry
             builder
@@ -33,6 +38,244 @@ async fn test_network_management() -> color_eyre::Result<()> {
                 "Created network should be in the list"
             );
 
+            let filters = ResourceFilters::default().label_value("test_id", &test_id);
+            let infos = builder.list_network_infos(&filters).await?;
+            assert_eq!(infos.len(), 1);
+            assert_eq!(infos[0].name, network_name);
+            assert_eq!(infos[0].driver.as_deref(), Some("bridge"));
+            assert!(!infos[0].internal);
+            assert!(infos[0].containers.is_empty());
+            assert!(
+                infos[0]
+                    .subnets
+                    .iter()
+                    .any(|subnet| subnet.subnet.is_some())
+            );
+
+            let filters = ResourceFilters::default().label_value("test_id", "missing");
+            assert!(builder.list_network_infos(&filters).await?.is_empty());
+
+            Ok(())
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_network_config_and_connect() -> color_eyre::Result<()> {
+    with_docker_cleanup(|test_id| {
+        Box::pin(async move {
+            let builder = DockerBuilder::new().await?;
+            let network_name = format!("test-network-{}", Uuid::new_v4());
+
+            let config = NetworkConfig {
+                ipam: Some(NetworkIpam {
+                    config: Some(vec![NetworkIpamPool {
+                        subnet: Some("172.31.0.0/16".to_string()),
+                        gateway: Some("172.31.0.1".to_string()),
+                        ..Default::default()
+                    }]),
+                    ..Default::default()
+                }),
+                internal: true,
+                labels: Some(HashMap::from([("test_id".to_string(), test_id.clone())])),
+                ..Default::default()
+            };
+            builder
+                .create_network_with_config(&network_name, &config)
+                .await?;
+
+            let info = builder.network_info(&network_name).await?;
+            assert!(info.internal);
+            assert_eq!(info.subnets[0].gateway.as_deref(), Some("172.31.0.1"));
+
+            let container = builder
+                .client()
+                .create_container(
+                    None::<CreateContainerOptions<String>>,
+                    Config {
+                        image: Some("alpine:latest".to_string()),
+                        cmd: Some(vec!["sleep".to_string(), "30".to_string()]),
+                        labels: Some(HashMap::from([("test_id".to_string(), test_id.clone())])),
+                        ..Default::default()
+                    },
+                )
+                .await?;
+
+            // Addresses outside of the network's subnets are rejected up front
+            assert!(matches!(
+                builder
+                    .connect_container(&network_name, &container.id, &[], Some("10.0.0.5"))
+                    .await,
+                Err(DockerError::InvalidIpamConfig(_))
+            ));
+
+            builder
+                .connect_container(
+                    &network_name,
+                    &container.id,
+                    &["bootnode"],
+                    Some("172.31.0.10"),
+                )
+                .await?;
+            let info = builder.network_info(&network_name).await?;
+            assert_eq!(info.containers.len(), 1);
+            assert_eq!(
+                info.containers[0].ipv4_address.as_deref(),
+                Some("172.31.0.10/16")
+            );
+
+            builder
+                .disconnect_container(&network_name, &container.id, true)
+                .await?;
+            let info = builder.network_info(&network_name).await?;
+            assert!(info.containers.is_empty());
+
+            // Invalid address pools are rejected before the network is created
+            let invalid = NetworkConfig {
+                ipam: Some(NetworkIpam {
+                    config: Some(vec![NetworkIpamPool {
+                        subnet: Some("172.31.0.0/16".to_string()),
+                        gateway: Some("172.32.0.1".to_string()),
+                        ..Default::default()
+                    }]),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            };
+            assert!(matches!(
+                builder
+                    .create_network_with_config(&format!("{}-invalid", network_name), &invalid)
+                    .await,
+                Err(DockerError::InvalidIpamConfig(_))
+            ));
+
+            Ok(())
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_compose_networks() -> color_eyre::Result<()> {
+    with_docker_cleanup(|test_id| {
+        Box::pin(async move {
+            let builder = DockerBuilder::new().await?;
+
+            let compose = format!(
+                r#"
+version: "3.8"
+services:
+  node:
+    image: alpine:latest
+    command: ["sleep", "30"]
+    labels:
+      test_id: "{test_id}"
+    networks:
+      internal:
+        aliases: [sequencer]
+        ipv4_address: 172.29.0.10
+      public:
+networks:
+  internal:
+    internal: true
+    labels:
+      test_id: "{test_id}"
+    ipam:
+      config:
+        - subnet: 172.29.0.0/16
+  public:
+    labels:
+      test_id: "{test_id}"
+"#
+            );
+
+            let mut config = ComposeParser::new().parse(&mut compose.as_bytes())?;
+            let container_ids = builder.deploy_compose(&mut config).await?;
+
+            let inspect = builder
+                .client()
+                .inspect_container(&container_ids["node"], None)
+                .await?;
+            let networks = inspect
+                .network_settings
+                .and_then(|settings| settings.networks)
+                .unwrap_or_default();
+            assert_eq!(networks.len(), 2);
+
+            let (_, internal) = networks
+                .iter()
+                .find(|(name, _)| name.contains("internal"))
+                .expect("Container should be attached to the internal network");
+            assert_eq!(internal.ip_address.as_deref(), Some("172.29.0.10"));
+
+            let internal_info = builder
+                .network_info(internal.network_id.as_deref().unwrap_or_default())
+                .await?;
+            assert!(internal_info.internal);
+            assert_eq!(
+                internal_info.subnets[0].subnet.as_deref(),
+                Some("172.29.0.0/16")
+            );
+            assert_eq!(internal_info.containers.len(), 1);
+            assert_eq!(
+                internal_info.containers[0].ipv4_address.as_deref(),
+                Some("172.29.0.10/16")
+            );
+            assert!(
+                internal
+                    .aliases
+                    .as_ref()
+                    .is_some_and(|aliases| aliases.iter().any(|alias| alias == "sequencer"))
+            );
+
+            Ok(())
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_compose_network_mode_service() -> color_eyre::Result<()> {
+    with_docker_cleanup(|test_id| {
+        Box::pin(async move {
+            let builder = DockerBuilder::new().await?;
+
+            let compose = format!(
+                r#"
+version: "3.8"
+services:
+  metrics:
+    image: alpine:latest
+    command: ["sleep", "30"]
+    network_mode: "service:node"
+    labels:
+      test_id: "{test_id}"
+  node:
+    image: alpine:latest
+    command: ["sleep", "30"]
+    labels:
+      test_id: "{test_id}"
+networks:
+  default:
+    labels:
+      test_id: "{test_id}"
+"#
+            );
+
+            let mut config = ComposeParser::new().parse(&mut compose.as_bytes())?;
+            let container_ids = builder.deploy_compose(&mut config).await?;
+
+            let inspect = builder
+                .client()
+                .inspect_container(&container_ids["metrics"], None)
+                .await?;
+            let network_mode = inspect.host_config.and_then(|config| config.network_mode);
+            assert_eq!(
+                network_mode,
+                Some(format!("container:{}", container_ids["node"]))
+            );
+
             Ok(())
         })
     })
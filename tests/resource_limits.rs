@@ -2,7 +2,11 @@ mod common;
 
 use color_eyre::Result;
 use common::{is_docker_running, with_docker_cleanup};
+use docktopus::config::FILES_LABEL;
+use docktopus::config::NetworkConfig;
 use docktopus::config::{SystemRequirements, parse_memory_string};
+use docktopus::container::Container;
+use docktopus::parser::ComposeParser;
 use docktopus::{ComposeConfig, DockerBuilder, Service};
 use std::collections::HashMap;
 use std::time::Duration;
@@ -21,7 +25,7 @@ async fn test_resource_limits() -> Result<()> {
             let network_name = format!("test-network-{}", test_id);
 
             let mut network_labels = HashMap::new();
-            network_labels.insert("test_id".to_string(), test_id.to_string());
+            network_labels.insert("test_id".to_string(), test_id.clone());
 
             // Create network with retry mechanism
             builder
@@ -38,7 +42,7 @@ async fn test_resource_limits() -> Result<()> {
             // Create a service with resource limits
             let mut services = HashMap::new();
             let mut labels = HashMap::new();
-            labels.insert("test_id".to_string(), test_id.to_string());
+            labels.insert("test_id".to_string(), test_id.clone());
 
             services.insert(
                 service_name.clone(),
@@ -58,7 +62,7 @@ async fn test_resource_limits() -> Result<()> {
                         cpu_shares: Some(512),
                         cpuset_cpus: Some("0,1".to_string()),
                     }),
-                    networks: Some(vec![network_name.clone()]),
+                    networks: Some(vec![network_name.clone()].into()),
                     labels: Some(labels),
                     ..Default::default()
                 },
@@ -68,6 +72,15 @@ async fn test_resource_limits() -> Result<()> {
                 version: "3".to_string(),
                 services,
                 volumes: HashMap::new(),
+                // The network is created by the test itself
+                networks: HashMap::from([(
+                    network_name.clone(),
+                    NetworkConfig {
+                        external: true,
+                        ..NetworkConfig::default()
+                    },
+                )]),
+                ..ComposeConfig::default()
             };
 
             let container_ids = builder.deploy_compose(&mut config).await?;
@@ -105,3 +118,177 @@ async fn test_resource_limits() -> Result<()> {
     })
     .await
 }
+
+#[tokio::test]
+async fn test_compose_runtime_options() -> Result<()> {
+    with_docker_cleanup(|test_id| {
+        Box::pin(async move {
+            if !is_docker_running() {
+                println!("Skipping test: Docker is not running");
+                return Ok(());
+            }
+
+            let builder = DockerBuilder::new().await?;
+
+            let compose = format!(
+                r#"
+version: "3.8"
+services:
+  node:
+    image: alpine:latest
+    command: ["30"]
+    entrypoint: ["sleep"]
+    working_dir: /tmp
+    hostname: node-1
+    init: true
+    read_only: true
+    pids_limit: 64
+    shm_size: 32m
+    cap_drop: [NET_RAW]
+    tmpfs: /run
+    extra_hosts:
+      - "seed:10.0.0.2"
+    ulimits:
+      nofile:
+        soft: 1024
+        hard: 2048
+    deploy:
+      resources:
+        limits:
+          cpus: "0.5"
+          memory: 256m
+    labels:
+      test_id: "{test_id}"
+networks:
+  default:
+    labels:
+      test_id: "{test_id}"
+"#
+            );
+
+            let mut config = ComposeParser::new().parse(&mut compose.as_bytes())?;
+            let container_ids = builder.deploy_compose(&mut config).await?;
+
+            let inspect = builder
+                .client()
+                .inspect_container(&container_ids["node"], None)
+                .await?;
+
+            let container_config = inspect.config.unwrap();
+            assert_eq!(container_config.entrypoint, Some(vec!["sleep".to_string()]));
+            assert_eq!(container_config.working_dir.as_deref(), Some("/tmp"));
+            assert_eq!(container_config.hostname.as_deref(), Some("node-1"));
+
+            let host_config = inspect.host_config.unwrap();
+            assert_eq!(host_config.memory, Some(256 * 1024 * 1024));
+            assert_eq!(host_config.nano_cpus, Some(500_000_000));
+            assert_eq!(host_config.pids_limit, Some(64));
+            assert_eq!(host_config.shm_size, Some(32 * 1024 * 1024));
+            assert_eq!(host_config.init, Some(true));
+            assert_eq!(host_config.readonly_rootfs, Some(true));
+            assert_eq!(host_config.cap_drop, Some(vec!["NET_RAW".to_string()]));
+            assert!(host_config.tmpfs.unwrap().contains_key("/run"));
+            assert_eq!(
+                host_config.extra_hosts,
+                Some(vec!["seed:10.0.0.2".to_string()])
+            );
+            let ulimits = host_config.ulimits.unwrap();
+            assert_eq!(ulimits[0].name.as_deref(), Some("nofile"));
+            assert_eq!(ulimits[0].hard, Some(2048));
+
+            Ok(())
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_compose_secrets_and_configs() -> Result<()> {
+    with_docker_cleanup(|test_id| {
+        Box::pin(async move {
+            if !is_docker_running() {
+                println!("Skipping test: Docker is not running");
+                return Ok(());
+            }
+
+            let builder = DockerBuilder::new().await?;
+
+            let compose = format!(
+                r#"
+version: "3.8"
+services:
+  node:
+    image: alpine:latest
+    command: ["sleep", "30"]
+    secrets:
+      - validator_key
+      - source: validator_key
+        target: /keys/validator
+        mode: "0400"
+    configs:
+      - source: node_config
+        target: /etc/node.toml
+    labels:
+      test_id: "{test_id}"
+secrets:
+  validator_key:
+    content: "0xdeadbeef"
+configs:
+  node_config:
+    content: "log_level = 'debug'"
+networks:
+  default:
+    labels:
+      test_id: "{test_id}"
+"#
+            );
+
+            let mut config = ComposeParser::new().parse(&mut compose.as_bytes())?;
+            let container_ids = builder.deploy_compose(&mut config).await?;
+
+            let inspect = builder
+                .client()
+                .inspect_container(&container_ids["node"], None)
+                .await?;
+
+            // Secrets are mounted as read-only files, never passed through the environment
+            let container_config = inspect.config.unwrap();
+            let env = container_config.env.unwrap_or_default();
+            assert!(!env.iter().any(|var| var.contains("0xdeadbeef")));
+
+            let mounts = inspect.host_config.unwrap().mounts.unwrap();
+            for target in [
+                "/run/secrets/validator_key",
+                "/keys/validator",
+                "/etc/node.toml",
+            ] {
+                let mount = mounts
+                    .iter()
+                    .find(|mount| mount.target.as_deref() == Some(target))
+                    .unwrap_or_else(|| panic!("{} is not mounted", target));
+                assert_eq!(mount.read_only, Some(true));
+
+                let source = mount.source.as_deref().unwrap();
+                let metadata = std::fs::metadata(source)?;
+                assert!(metadata.is_file());
+            }
+
+            // The files are removed along with the container
+            let labels = container_config.labels.unwrap();
+            let directory = std::path::PathBuf::from(&labels[FILES_LABEL]);
+            assert!(directory.is_dir());
+
+            let container = Container::from_id(builder.client(), &container_ids["node"]).await?;
+            container
+                .remove(Some(bollard::container::RemoveContainerOptions {
+                    force: true,
+                    ..Default::default()
+                }))
+                .await?;
+            assert!(!directory.exists());
+
+            Ok(())
+        })
+    })
+    .await
+}
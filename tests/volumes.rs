@@ -2,6 +2,35 @@ mod common;
 
 use common::with_docker_cleanup;
 use docktopus::DockerBuilder;
+use docktopus::DockerError;
+use docktopus::builder::management::ResourceFilters;
+use docktopus::parser::ComposeParser;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncReadExt, ReadBuf};
+
+/// A reader that fails, like a dropped connection
+struct FailingReader;
+
+impl AsyncRead for FailingReader {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        _buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Err(std::io::Error::other("connection reset")))
+    }
+}
+
+fn archive_with(path: &str, content: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut archive = tar::Builder::new(Vec::new());
+    let mut header = tar::Header::new_gnu();
+    header.set_size(content.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    archive.append_data(&mut header, path, content)?;
+    archive.into_inner()
+}
 
 #[tokio::test]
 async fn test_volume_management() -> color_eyre::Result<()> {
@@ -20,6 +49,175 @@ async fn test_volume_management() -> color_eyre::Result<()> {
                 "Created volume should be in the list"
             );
 
+            let info = builder.volume_info(&volume_name).await?;
+            assert_eq!(info.driver, "local");
+            assert_eq!(info.scope.as_deref(), Some("local"));
+            assert!(info.in_use_by.is_empty());
+
+            let filters = ResourceFilters::default().name(&volume_name).dangling(true);
+            let infos = builder.list_volume_infos(&filters).await?;
+            assert_eq!(infos.len(), 1);
+            assert_eq!(infos[0].name, volume_name);
+
+            Ok(())
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_compose_volume_options() -> color_eyre::Result<()> {
+    with_docker_cleanup(|test_id| {
+        Box::pin(async move {
+            let builder = DockerBuilder::new().await?;
+            let external_name = format!("test-volume-{}-external", test_id);
+            let data_name = format!("test-volume-{}-data", test_id);
+            let host_dir = tempfile::tempdir()?;
+            std::fs::write(host_dir.path().join("hello"), "world")?;
+
+            builder.create_volume(&external_name).await?;
+
+            let compose = format!(
+                r#"
+version: "3.8"
+services:
+  node:
+    image: alpine:latest
+    command: ["sleep", "30"]
+    labels:
+      test_id: "{test_id}"
+    volumes:
+      - data:/data
+      - shared:/shared
+      - host:/host:ro
+volumes:
+  data:
+    name: {data_name}
+    labels:
+      test_id: "{test_id}"
+  shared:
+    name: {external_name}
+    external: true
+  host:
+    name: test-volume-{test_id}-host
+    driver: local
+    driver_opts:
+      type: none
+      o: bind
+      device: {host_dir}
+    labels:
+      test_id: "{test_id}"
+networks:
+  default:
+    labels:
+      test_id: "{test_id}"
+"#,
+                host_dir = host_dir.path().display()
+            );
+
+            let mut config = ComposeParser::new().parse(&mut compose.as_bytes())?;
+            let container_ids = builder.deploy_compose(&mut config).await?;
+
+            let data = builder.client().inspect_volume(&data_name).await?;
+            assert_eq!(
+                data.labels.get("test_id").map(String::as_str),
+                Some(test_id.as_str())
+            );
+
+            let inspect = builder
+                .client()
+                .inspect_container(&container_ids["node"], None)
+                .await?;
+            let mounts = inspect.mounts.unwrap_or_default();
+            for name in [&data_name, &external_name] {
+                assert!(
+                    mounts.iter().any(|m| m.name.as_ref() == Some(name)),
+                    "{} should be mounted",
+                    name
+                );
+            }
+
+            let data = builder.volume_info(&data_name).await?;
+            assert_eq!(data.in_use_by.len(), 1);
+            let filters = ResourceFilters::default().label_value("test_id", &test_id);
+            let labeled = builder.list_volume_infos(&filters).await?;
+            assert!(labeled.iter().any(|volume| volume.name == data_name));
+            assert!(labeled.iter().all(|volume| volume.name != external_name));
+
+            // A missing external volume fails the deployment without creating it
+            let missing = compose.replace(&external_name, &format!("{}-missing", external_name));
+            let mut config = ComposeParser::new().parse(&mut missing.as_bytes())?;
+            assert!(builder.deploy_compose(&mut config).await.is_err());
+            assert!(
+                builder
+                    .client()
+                    .inspect_volume(&format!("{}-missing", external_name))
+                    .await
+                    .is_err()
+            );
+
+            Ok(())
+        })
+    })
+    .await
+}
+
+#[tokio::test]
+async fn test_volume_backup_restore_and_clone() -> color_eyre::Result<()> {
+    with_docker_cleanup(|test_id| {
+        Box::pin(async move {
+            let builder = DockerBuilder::new().await?;
+            let source = format!("test-volume-{}-source", test_id);
+            let clone = format!("test-volume-{}-clone", test_id);
+
+            // Restore an archive into a new volume
+            let archive = archive_with("db/blocks", b"chain")?;
+            builder
+                .restore_volume(&source, std::io::Cursor::new(archive))
+                .await?;
+
+            // A reader failing partway through, or a truncated archive, leaves the volume as is
+            let archive = archive_with("db/blocks", &[b'x'; 4096])?;
+            let failing = std::io::Cursor::new(archive[..2048].to_vec()).chain(FailingReader);
+            assert!(matches!(
+                builder.restore_volume(&source, failing).await,
+                Err(DockerError::FileError(_))
+            ));
+            let truncated = std::io::Cursor::new(archive[..2048].to_vec());
+            assert!(matches!(
+                builder.restore_volume(&source, truncated).await,
+                Err(DockerError::ValidationError(_))
+            ));
+
+            builder.clone_volume(&source, &clone).await?;
+            assert!(builder.clone_volume(&source, &source).await.is_err());
+
+            for volume in [&source, &clone] {
+                let mut backup = Vec::new();
+                builder.backup_volume(volume, &mut backup).await?;
+
+                let mut archive = tar::Archive::new(backup.as_slice());
+                let mut found = false;
+                for entry in archive.entries()? {
+                    let mut entry = entry?;
+                    if entry.path()?.ends_with("db/blocks") {
+                        let mut content = String::new();
+                        std::io::Read::read_to_string(&mut entry, &mut content)?;
+                        assert_eq!(content, "chain");
+                        found = true;
+                    }
+                }
+                assert!(found, "{} should contain db/blocks", volume);
+            }
+
+            let mut backup = Vec::new();
+            assert!(
+                builder
+                    .backup_volume(&format!("{}-missing", source), &mut backup)
+                    .await
+                    .is_err()
+            );
+
             Ok(())
         })
     })